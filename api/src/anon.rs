@@ -0,0 +1,149 @@
+//! Anonymous posting tokens, in the style of Privacy Pass.
+//!
+//! Logged in users with enough karma may have a few tokens issued per day. The client picks a
+//! random message, blinds it, and has the server sign the blinded message; the server sees only
+//! the blinded message, and never the token itself. The client unblinds the signature, and later
+//! redeems the message and signature to post as `[anonymous]`. The server can check that it
+//! signed the token, but not which issuance it came from, so the post cannot be linked to the
+//! account. Hashes of redeemed tokens are kept, so each token may only be used once.
+//!
+//! Signatures are RSA blind signatures, RSABSSA-SHA384-PSS-Randomized.
+//! ref: https://www.rfc-editor.org/rfc/rfc9474
+//!
+//! Tokens remain valid until the signing key changes. Set the key in the config so that tokens
+//! survive restarts; otherwise a key is generated on startup.
+use std::sync::Arc;
+
+use blind_rsa_signatures::{
+  BlindSignature, BlindedMessage, KeyPair, MessageRandomizer, Options, PublicKey, SecretKey,
+  Signature,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::warn;
+use utoipa::ToSchema;
+
+use crate::{ApiError, ApiResult};
+
+const KEY_BITS: usize = 2048;
+
+/// A token to redeem for an anonymous post. All fields are hex encoded.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[schema(default = AnonToken::default, example=AnonToken::default)]
+pub struct AnonToken {
+  /// The random message chosen by the client
+  pub message:    String,
+  /// The randomizer generated while blinding the message
+  pub randomizer: String,
+  /// The unblinded signature over the message
+  pub signature:  String,
+}
+
+/// The server's anonymous token signing key.
+#[derive(Clone)]
+pub struct AnonTokenSigner {
+  public_key: Arc<PublicKey>,
+  secret_key: Arc<SecretKey>,
+}
+
+impl AnonTokenSigner {
+  /// Use the PEM encoded RSA secret key `pem`, or generate a key if None.
+  pub fn new(pem: Option<&str>) -> ApiResult<Self> {
+    let secret_key = match pem {
+      Some(pem) => SecretKey::from_pem(pem)
+        .map_err(|e| ApiError::OtherISE(format!("invalid anonymous token key: {e}")))?,
+      None => {
+        warn!("no anonymous token key configured; tokens will not survive a restart");
+        KeyPair::generate(&mut rand::thread_rng(), KEY_BITS)
+          .map_err(|e| ApiError::OtherISE(e.to_string()))?
+          .sk
+      },
+    };
+    let public_key = secret_key.public_key().map_err(|e| ApiError::OtherISE(e.to_string()))?;
+    Ok(Self { public_key: Arc::new(public_key), secret_key: Arc::new(secret_key) })
+  }
+
+  /// The PEM encoded public key, with which clients blind their messages.
+  pub fn public_key_pem(&self) -> ApiResult<String> {
+    self.public_key.to_pem().map_err(|e| ApiError::OtherISE(e.to_string()))
+  }
+
+  /// Sign the hex encoded `blinded_message`, returning the hex encoded blind signature.
+  pub fn blind_sign(&self, blinded_message: &str) -> ApiResult<String> {
+    let blinded_message = hex::decode(blinded_message)
+      .map_err(|_| ApiError::BadRequest("blinded message is not hex".into()))?;
+    let signature = self
+      .secret_key
+      .blind_sign(&mut rand::thread_rng(), BlindedMessage(blinded_message), &Options::default())
+      .map_err(|e| ApiError::BadRequest(format!("invalid blinded message: {e}")))?;
+    Ok(hex::encode(signature.0))
+  }
+
+  /// Verify that we signed `token`, returning the hash by which it is recorded once spent.
+  pub fn verify(&self, token: &AnonToken) -> ApiResult<String> {
+    let invalid = || ApiError::UnauthorizedInvalidSignature("anonymous token".into());
+    let message = hex::decode(&token.message).map_err(|_| invalid())?;
+    let randomizer: [u8; 32] =
+      hex::decode(&token.randomizer).ok().and_then(|r| r.try_into().ok()).ok_or_else(invalid)?;
+    let signature = hex::decode(&token.signature).map_err(|_| invalid())?;
+
+    Signature(signature)
+      .verify(&self.public_key, Some(MessageRandomizer(randomizer)), &message, &Options::default())
+      .map_err(|_| invalid())?;
+    Ok(hex::encode(Sha256::digest(&message)))
+  }
+}
+
+/// A client's token, before it has been signed. Convenience for clients and testing.
+pub struct AnonTokenRequest {
+  public_key:          PublicKey,
+  message:             [u8; 32],
+  secret:              blind_rsa_signatures::Secret,
+  randomizer:          MessageRandomizer,
+  /// The hex encoded blinded message, to be sent to the server for signing
+  pub blinded_message: String,
+}
+
+impl AnonTokenRequest {
+  /// Pick a random message, and blind it with the PEM encoded public key `public_key_pem`.
+  pub fn new(public_key_pem: &str) -> ApiResult<Self> {
+    let invalid = |e: blind_rsa_signatures::Error| ApiError::BadRequest(e.to_string());
+    let public_key = PublicKey::from_pem(public_key_pem).map_err(invalid)?;
+    let message: [u8; 32] = rand::random();
+    let blinding = public_key
+      .blind(&mut rand::thread_rng(), message, true, &Options::default())
+      .map_err(invalid)?;
+    let randomizer = blinding.msg_randomizer.expect("randomized blinding");
+
+    Ok(Self {
+      public_key,
+      message,
+      secret: blinding.secret,
+      randomizer,
+      blinded_message: hex::encode(blinding.blind_msg.0),
+    })
+  }
+
+  /// Unblind the server's hex encoded `blind_signature`, producing a token to redeem.
+  pub fn finalize(self, blind_signature: &str) -> ApiResult<AnonToken> {
+    let blind_signature = hex::decode(blind_signature)
+      .map_err(|_| ApiError::BadRequest("blind signature is not hex".into()))?;
+    let signature = self
+      .public_key
+      .finalize(
+        &BlindSignature(blind_signature),
+        &self.secret,
+        Some(self.randomizer),
+        self.message,
+        &Options::default(),
+      )
+      .map_err(|e| ApiError::BadRequest(e.to_string()))?;
+
+    Ok(AnonToken {
+      message:    hex::encode(self.message),
+      randomizer: hex::encode(self.randomizer.0),
+      signature:  hex::encode(signature.0),
+    })
+  }
+}
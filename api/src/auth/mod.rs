@@ -1,6 +1,8 @@
 //! Authentication with axum-login.
 
 mod password;
+mod siwe;
+mod throttle;
 mod users;
 mod web;
 
@@ -13,16 +15,29 @@ use utoipa::ToSchema;
 
 pub use self::{
   password::PasswordExt,
+  siwe::{
+    address as siwe_address, issue_nonce as issue_siwe_nonce, sign as sign_siwe_message,
+    SiweMessage,
+  },
+  throttle::{client_ip, LoginThrottle},
   users::{AuthBackend, AuthSession},
-  web::{login_post_internal, logout_post_internal},
+  web::{login_post_internal, logout_post_internal, siwe_verify_internal},
+};
+use crate::{
+  config::{Privilege, PrivilegeTable},
+  sessions::MySessionManagerLayer,
+  ApiConfig, ApiError, ApiResult,
 };
-use crate::{sessions::MySessionManagerLayer, ApiError, ApiResult};
 
 pub type MyAuthLayer = AuthManagerLayer<AuthBackend, PostgresStore, SignedCookie>;
 
-pub fn get_auth_layer(pool: DbPool, session_layer: MySessionManagerLayer) -> MyAuthLayer {
-  let backend = AuthBackend::new(pool);
-  AuthManagerLayerBuilder::new(backend, session_layer).build()
+pub async fn get_auth_layer(
+  pool: DbPool,
+  session_layer: MySessionManagerLayer,
+  config: &ApiConfig,
+) -> ApiResult<MyAuthLayer> {
+  let backend = AuthBackend::new(pool, config.argon2_params.clone()).await?;
+  Ok(AuthManagerLayerBuilder::new(backend, session_layer).build())
 }
 
 pub(crate) trait AuthenticationExt {
@@ -40,6 +55,22 @@ pub(crate) trait AuthenticationExt {
   /// Return Err(ApiError::Unauthorized) if caller is not logged in.
   /// Return Err(ApiError::Forbidden) if caller is banned.
   fn get_assert_user_from_session_assert_match(&self, username: &Username) -> ApiResult<User>;
+  /// Get the user from the session store, or else return an Error
+  ///
+  /// Return Ok(user) if the caller is authenticated and has `privilege`.
+  /// Return Err(ApiError::Unauthorized) if caller is not logged in.
+  /// Return Err(ApiError::Forbidden) if caller is banned, or lacks the karma for `privilege`.
+  fn get_assert_user_with_privilege(
+    &self,
+    privileges: &PrivilegeTable,
+    privilege: Privilege,
+  ) -> ApiResult<User>;
+  /// Get the user from the session store, or else return an Error
+  ///
+  /// Return Ok(user) if the caller is authenticated as a moderator.
+  /// Return Err(ApiError::Unauthorized) if caller is not logged in.
+  /// Return Err(ApiError::Forbidden) if caller is banned, or not a moderator.
+  fn get_assert_moderator_from_session(&self) -> ApiResult<User>;
   /// Return whether the caller is logged in and not banned
   fn am_authenticated_and_not_banned(&self) -> bool;
 }
@@ -63,6 +94,26 @@ impl AuthenticationExt for AuthSession {
     Ok(user)
   }
 
+  fn get_assert_user_with_privilege(
+    &self,
+    privileges: &PrivilegeTable,
+    privilege: Privilege,
+  ) -> ApiResult<User> {
+    let user = self.get_assert_user_from_session()?;
+    if !privileges.has(&user, privilege) {
+      return Err(ApiError::ForbiddenKarmaRequired(privileges.karma(privilege)));
+    }
+    Ok(user)
+  }
+
+  fn get_assert_moderator_from_session(&self) -> ApiResult<User> {
+    let user = self.get_assert_user_from_session()?;
+    if !user.is_moderator {
+      return Err(ApiError::ForbiddenModeratorRequired);
+    }
+    Ok(user)
+  }
+
   fn am_authenticated_and_not_banned(&self) -> bool {
     self.user.as_ref().map(|user| !user.0.banned).unwrap_or(false)
   }
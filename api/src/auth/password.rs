@@ -1,46 +1,73 @@
 use argon2::{
   password_hash::{rand_core::OsRng, SaltString},
-  Argon2, PasswordHasher, PasswordVerifier,
+  Algorithm, Argon2, Params, PasswordHasher, PasswordVerifier, Version,
 };
 use db::{Password, PasswordHash};
 use tokio::task::spawn_blocking;
-use tracing::debug;
+use tracing::{debug, error};
 
 use crate::{ApiError, ApiResult};
 
 pub trait PasswordExt {
-  async fn hash(&self) -> PasswordHash;
+  async fn hash(&self, params: &Params) -> ApiResult<PasswordHash>;
   async fn hash_and_verify(&self, other_hash: &PasswordHash) -> ApiResult<()>;
 }
 
 impl PasswordExt for Password {
   /// Hashes the password using argon2 and compares it to the provided hash.
   ///
+  /// The argon2 parameters are read from the provided hash, so hashes created under previous
+  /// parameters still verify.
+  ///
   /// Ok(())            - Password matches provided hash
-  /// Err(Unauthorized) - Password does not match provided hash
+  /// Err(Unauthorized) - Password does not match provided hash, or the hash is malformed
   async fn hash_and_verify(&self, other_hash: &PasswordHash) -> ApiResult<()> {
-    let password_bytes = self.0.as_bytes(); // Clone the password data
-    let parsed_hash = argon2::password_hash::PasswordHash::new(&other_hash.0).unwrap();
-    Argon2::default()
-      .verify_password(password_bytes, &parsed_hash)
-      .map_err(|e| ApiError::UnauthorizedIncorrectPassword)?;
-    Ok(())
+    let password_bytes = self.0.as_bytes().to_owned();
+    let other_hash = other_hash.0.clone();
+
+    spawn_blocking(move || {
+      let parsed_hash = argon2::password_hash::PasswordHash::new(&other_hash).map_err(|e| {
+        error!("malformed password hash in db: {e}");
+        ApiError::UnauthorizedIncorrectPassword
+      })?;
+      Argon2::default()
+        .verify_password(&password_bytes, &parsed_hash)
+        .map_err(|_| ApiError::UnauthorizedIncorrectPassword)
+    })
+    .await?
   }
 
-  /// Hashes the password using argon2. Hashes take ~400ms.
-  async fn hash(&self) -> PasswordHash {
+  /// Hashes the password using argon2 with `params`. Hashes take ~400ms with default params.
+  async fn hash(&self, params: &Params) -> ApiResult<PasswordHash> {
     let salt = SaltString::generate(&mut OsRng);
-    let salt_clone = salt.clone(); // backlog: for logging, eventually remove
-    let argon2 = Argon2::default();
+    let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, params.clone());
     let password_bytes = self.0.as_bytes().to_owned();
 
-    // let instant = std::time::Instant::now();
-    let password_hash =
-      spawn_blocking(move || argon2.hash_password(&password_bytes, &salt).unwrap().to_string())
-        .await
-        .expect("tokio runtime error");
-    // let elapsed = instant.elapsed();
-    debug!("hashing password: {}; with salt: {salt_clone:?}: output: {password_hash}", self.0);
-    PasswordHash(password_hash)
+    let password_hash = spawn_blocking(move || {
+      argon2
+        .hash_password(&password_bytes, &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| ApiError::OtherISE(format!("failed to hash password: {e}")))
+    })
+    .await??;
+    debug!("hashed password");
+    Ok(PasswordHash(password_hash))
   }
 }
+
+/// Whether `hash` was created with an algorithm, version, or parameters other than the current
+/// ones, and so should be replaced the next time the user logs in.
+pub fn needs_rehash(hash: &PasswordHash, params: &Params) -> bool {
+  let Ok(parsed_hash) = argon2::password_hash::PasswordHash::new(&hash.0) else { return true };
+  let algorithm_matches = parsed_hash.algorithm == Algorithm::Argon2id.ident();
+  let version_matches = parsed_hash.version == Some(Version::V0x13.into());
+  let params_match = Params::try_from(&parsed_hash)
+    .map(|p| {
+      p.m_cost() == params.m_cost()
+        && p.t_cost() == params.t_cost()
+        && p.p_cost() == params.p_cost()
+    })
+    .unwrap_or(false);
+
+  !(algorithm_matches && version_matches && params_match)
+}
@@ -0,0 +1,260 @@
+//! Sign-In with Ethereum (EIP-4361).
+//!
+//! The client fetches a nonce, which we keep in its session, and has the user's wallet sign an
+//! EIP-4361 message containing it with `personal_sign` (EIP-191). We check the message's domain,
+//! nonce, and validity window, and recover the signing address from the secp256k1 signature.
+//! The nonce is removed from the session on the first verification attempt, so a signed message
+//! cannot be replayed.
+//!
+//! ref: https://eips.ethereum.org/EIPS/eip-4361
+use std::{fmt, iter::Peekable, str::FromStr};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use k256::ecdsa::{RecoveryId, Signature, SigningKey, VerifyingKey};
+use rand::{distributions::Alphanumeric, Rng};
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256};
+use tower_sessions::Session;
+
+use crate::{ApiError, ApiResult};
+
+/// Session key under which the outstanding nonce is kept.
+const NONCE_KEY: &str = "siwe.nonce";
+/// How long a nonce may be signed and submitted within.
+const NONCE_TTL_SECS: i64 = 10 * 60;
+const NONCE_LEN: usize = 17;
+const HEADER_SUFFIX: &str = " wants you to sign in with your Ethereum account:";
+
+/// A nonce issued to a session, and when it expires.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SessionNonce {
+  nonce:   String,
+  expires: i64,
+}
+
+/// Generate a nonce and keep it in `session`, replacing any outstanding nonce.
+pub(crate) async fn issue_nonce(session: &Session) -> ApiResult<String> {
+  let nonce: String =
+    rand::thread_rng().sample_iter(&Alphanumeric).take(NONCE_LEN).map(char::from).collect();
+  let expires = Utc::now().timestamp() + NONCE_TTL_SECS;
+  session
+    .insert(NONCE_KEY, SessionNonce { nonce: nonce.clone(), expires })
+    .await
+    .map_err(|e| ApiError::OtherISE(e.to_string()))?;
+  Ok(nonce)
+}
+
+/// Remove the session's nonce, returning it if it has not expired.
+pub(crate) async fn take_nonce(session: &Session) -> ApiResult<Option<String>> {
+  let nonce = session
+    .remove::<SessionNonce>(NONCE_KEY)
+    .await
+    .map_err(|e| ApiError::OtherISE(e.to_string()))?;
+  Ok(nonce.filter(|n| n.expires >= Utc::now().timestamp()).map(|n| n.nonce))
+}
+
+/// An EIP-4361 message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SiweMessage {
+  /// The host requesting the sign in, e.g. `example.com` or `localhost:8000`
+  pub domain:          String,
+  /// The signing address, 0x-prefixed hex
+  pub address:         String,
+  /// Human readable text shown to the user by their wallet
+  pub statement:       Option<String>,
+  pub uri:             String,
+  /// Always `1`
+  pub version:         String,
+  pub chain_id:        u64,
+  pub nonce:           String,
+  pub issued_at:       DateTime<Utc>,
+  pub expiration_time: Option<DateTime<Utc>>,
+  pub not_before:      Option<DateTime<Utc>>,
+  pub request_id:      Option<String>,
+  pub resources:       Vec<String>,
+}
+
+impl SiweMessage {
+  /// Check that the message was made for `domain` and this session's `nonce`, and is currently
+  /// valid.
+  pub fn verify(&self, domain: &str, nonce: Option<&str>) -> ApiResult<()> {
+    let invalid = |reason: &str| ApiError::UnauthorizedInvalidSignature(reason.to_string());
+    if self.domain != domain {
+      return Err(invalid("issued for another domain"));
+    }
+    if nonce != Some(self.nonce.as_str()) {
+      return Err(invalid("unknown or expired nonce"));
+    }
+    let now = Utc::now();
+    if self.expiration_time.is_some_and(|t| t <= now) {
+      return Err(invalid("expired"));
+    }
+    if self.not_before.is_some_and(|t| t > now) {
+      return Err(invalid("not yet valid"));
+    }
+    Ok(())
+  }
+}
+
+impl FromStr for SiweMessage {
+  type Err = ApiError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let invalid = |part: &str| ApiError::BadRequest(format!("invalid siwe message: {part}"));
+    let mut lines = s.lines().peekable();
+
+    let domain =
+      lines.next().and_then(|l| l.strip_suffix(HEADER_SUFFIX)).ok_or(invalid("header"))?;
+    // the domain may be preceded by a scheme, which we don't check
+    let domain = domain.split_once("://").map_or(domain, |(_, domain)| domain).to_string();
+    let address = lines.next().filter(|a| is_address(a)).ok_or(invalid("address"))?.to_string();
+
+    // a blank line, the optional statement, and another blank line
+    if lines.next() != Some("") {
+      return Err(invalid("statement"));
+    }
+    let statement = lines.next_if(|l| !l.is_empty()).map(String::from);
+    if lines.next() != Some("") {
+      return Err(invalid("statement"));
+    }
+
+    let uri = tagged(&mut lines, "URI: ").ok_or(invalid("uri"))?;
+    let version = tagged(&mut lines, "Version: ").filter(|v| v == "1").ok_or(invalid("version"))?;
+    let chain_id =
+      tagged(&mut lines, "Chain ID: ").and_then(|c| c.parse().ok()).ok_or(invalid("chain id"))?;
+    let nonce = tagged(&mut lines, "Nonce: ")
+      .filter(|n| n.len() >= 8 && n.chars().all(|c| c.is_ascii_alphanumeric()))
+      .ok_or(invalid("nonce"))?;
+    let issued_at =
+      tagged(&mut lines, "Issued At: ").and_then(|t| parse_time(&t)).ok_or(invalid("issued at"))?;
+    let expiration_time = match tagged(&mut lines, "Expiration Time: ") {
+      Some(t) => Some(parse_time(&t).ok_or(invalid("expiration time"))?),
+      None => None,
+    };
+    let not_before = match tagged(&mut lines, "Not Before: ") {
+      Some(t) => Some(parse_time(&t).ok_or(invalid("not before"))?),
+      None => None,
+    };
+    let request_id = tagged(&mut lines, "Request ID: ");
+    let mut resources = Vec::new();
+    if lines.next_if_eq(&"Resources:").is_some() {
+      while let Some(resource) = tagged(&mut lines, "- ") {
+        resources.push(resource);
+      }
+    }
+    if lines.next().is_some() {
+      return Err(invalid("trailing content"));
+    }
+
+    Ok(Self {
+      domain,
+      address,
+      statement,
+      uri,
+      version,
+      chain_id,
+      nonce,
+      issued_at,
+      expiration_time,
+      not_before,
+      request_id,
+      resources,
+    })
+  }
+}
+
+impl fmt::Display for SiweMessage {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let time = |t: &DateTime<Utc>| t.to_rfc3339_opts(SecondsFormat::Secs, true);
+    writeln!(f, "{}{HEADER_SUFFIX}", self.domain)?;
+    writeln!(f, "{}", self.address)?;
+    writeln!(f)?;
+    if let Some(statement) = &self.statement {
+      writeln!(f, "{statement}")?;
+    }
+    writeln!(f)?;
+    writeln!(f, "URI: {}", self.uri)?;
+    writeln!(f, "Version: {}", self.version)?;
+    writeln!(f, "Chain ID: {}", self.chain_id)?;
+    writeln!(f, "Nonce: {}", self.nonce)?;
+    write!(f, "Issued At: {}", time(&self.issued_at))?;
+    if let Some(t) = &self.expiration_time {
+      write!(f, "\nExpiration Time: {}", time(t))?;
+    }
+    if let Some(t) = &self.not_before {
+      write!(f, "\nNot Before: {}", time(t))?;
+    }
+    if let Some(request_id) = &self.request_id {
+      write!(f, "\nRequest ID: {request_id}")?;
+    }
+    if !self.resources.is_empty() {
+      write!(f, "\nResources:")?;
+      for resource in &self.resources {
+        write!(f, "\n- {resource}")?;
+      }
+    }
+    Ok(())
+  }
+}
+
+/// Recover the lowercase, 0x-prefixed address that produced the `personal_sign` `signature`, 65
+/// hex-encoded bytes `r || s || v`, over `message`.
+pub fn recover_address(message: &str, signature: &str) -> ApiResult<String> {
+  let invalid = |reason: &str| ApiError::UnauthorizedInvalidSignature(reason.to_string());
+  let bytes = hex::decode(signature.trim_start_matches("0x")).map_err(|_| invalid("malformed"))?;
+  let [rs @ .., v] = bytes.as_slice() else { return Err(invalid("malformed")) };
+  if rs.len() != 64 {
+    return Err(invalid("malformed"));
+  }
+  let mut signature = Signature::from_slice(rs).map_err(|_| invalid("malformed"))?;
+  let mut recovery_id = match v {
+    0 | 27 => RecoveryId::new(false, false),
+    1 | 28 => RecoveryId::new(true, false),
+    _ => return Err(invalid("malformed")),
+  };
+  // k256 only accepts low-s signatures; flipping s flips the recovered point's y parity
+  if let Some(normalized) = signature.normalize_s() {
+    signature = normalized;
+    recovery_id = RecoveryId::new(!recovery_id.is_y_odd(), recovery_id.is_x_reduced());
+  }
+
+  let key = VerifyingKey::recover_from_prehash(&eip191_hash(message), &signature, recovery_id)
+    .map_err(|_| invalid("recovery failed"))?;
+  Ok(address(&key))
+}
+
+/// The lowercase, 0x-prefixed address of `key`.
+pub fn address(key: &VerifyingKey) -> String {
+  let point = key.to_encoded_point(false);
+  let hash = Keccak256::digest(&point.as_bytes()[1..]);
+  format!("0x{}", hex::encode(&hash[12..]))
+}
+
+/// Sign `message` as a wallet's `personal_sign` would. Convenience for clients and testing.
+pub fn sign(key: &SigningKey, message: &str) -> String {
+  let (signature, recovery_id) =
+    key.sign_prehash_recoverable(&eip191_hash(message)).expect("prehash is 32 bytes");
+  format!("0x{}{:02x}", hex::encode(signature.to_bytes()), 27 + recovery_id.to_byte())
+}
+
+/// The EIP-191 `personal_sign` hash of `message`.
+fn eip191_hash(message: &str) -> [u8; 32] {
+  let mut hasher = Keccak256::new();
+  hasher.update(format!("\x19Ethereum Signed Message:\n{}", message.len()).as_bytes());
+  hasher.update(message.as_bytes());
+  hasher.finalize().into()
+}
+
+fn is_address(s: &str) -> bool {
+  s.strip_prefix("0x")
+    .is_some_and(|hex| hex.len() == 40 && hex.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+fn parse_time(s: &str) -> Option<DateTime<Utc>> {
+  DateTime::parse_from_rfc3339(s).ok().map(|t| t.with_timezone(&Utc))
+}
+
+/// Consume the next line if it starts with `tag`, returning the rest of it.
+fn tagged<'a>(lines: &mut Peekable<impl Iterator<Item = &'a str>>, tag: &str) -> Option<String> {
+  lines.next_if(|l| l.starts_with(tag)).map(|l| l[tag.len()..].to_string())
+}
@@ -0,0 +1,151 @@
+//! Brute-force protection for login.
+//!
+//! Failed logins are counted per username and per client IP. After a number of free attempts,
+//! each further failure doubles the time the client must wait before trying again, until the key
+//! is locked out entirely. A successful login resets the counters.
+//!
+//! Each attempt is counted as a failure when it starts, before the slow password check, so that
+//! parallel guesses can't all pass the throttle before any of them is counted.
+use std::{
+  collections::HashMap,
+  net::IpAddr,
+  sync::{Arc, Mutex},
+  time::{Duration, Instant},
+};
+
+use axum::http::HeaderMap;
+use db::Username;
+use tracing::warn;
+
+use crate::{ApiError, ApiResult};
+
+/// Counters older than this are forgotten.
+const RESET_WINDOW: Duration = Duration::from_secs(60 * 60);
+/// Prune stale counters once this many keys are tracked.
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// How many failures a key is allowed before being throttled, and how hard.
+#[derive(Debug, Clone, Copy)]
+struct ThrottlePolicy {
+  /// failures allowed before any delay is imposed
+  free_attempts: u32,
+  /// failures after which the key is locked out for `lockout`
+  lockout_after: u32,
+  /// the longest a key may be locked out for
+  lockout:       Duration,
+}
+
+// todo(config)
+const USERNAME_POLICY: ThrottlePolicy = ThrottlePolicy {
+  free_attempts: 3,
+  lockout_after: 10,
+  lockout:       Duration::from_secs(15 * 60),
+};
+// many users may share an IP behind a NAT, so be more lenient
+const IP_POLICY: ThrottlePolicy = ThrottlePolicy {
+  free_attempts: 10,
+  lockout_after: 50,
+  lockout:       Duration::from_secs(15 * 60),
+};
+
+impl ThrottlePolicy {
+  /// How long to wait after the `failures`th failure: 1s, 2s, 4s, ... then `lockout`.
+  fn delay(&self, failures: u32) -> Duration {
+    if failures < self.free_attempts {
+      Duration::ZERO
+    } else if failures >= self.lockout_after {
+      self.lockout
+    } else {
+      let exponent = failures - self.free_attempts;
+      Duration::from_secs(1u64 << exponent.min(32)).min(self.lockout)
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Attempts {
+  failures:      u32,
+  last_failure:  Instant,
+  blocked_until: Instant,
+}
+
+/// Shared login attempt counters.
+#[derive(Debug, Clone, Default)]
+pub struct LoginThrottle {
+  attempts: Arc<Mutex<HashMap<String, Attempts>>>,
+}
+
+impl LoginThrottle {
+  pub fn new() -> Self { Self::default() }
+
+  /// Reserve a login attempt for the username and ip, counting it as a failure until
+  /// `record_success` resets the counters.
+  ///
+  /// Return `Err(TooManyLoginAttempts)`, without counting the attempt, if either the username or ip
+  /// is currently throttled.
+  pub fn check(&self, username: &Username, ip: Option<IpAddr>) -> ApiResult<()> {
+    let now = Instant::now();
+    let mut attempts = self.attempts.lock().expect("login throttle lock poisoned");
+    let retry_after = keys(username, ip)
+      .filter_map(|(key, _)| attempts.get(&key))
+      .filter(|a| now.duration_since(a.last_failure) < RESET_WINDOW)
+      .map(|a| a.blocked_until.saturating_duration_since(now))
+      .max()
+      .unwrap_or_default();
+    if !retry_after.is_zero() {
+      // round up, so that clients honoring Retry-After don't retry a moment too early
+      return Err(ApiError::TooManyLoginAttempts(retry_after.as_secs() + 1));
+    }
+
+    if attempts.len() >= PRUNE_THRESHOLD {
+      attempts.retain(|_, a| now.duration_since(a.last_failure) < RESET_WINDOW);
+    }
+    for (key, policy) in keys(username, ip) {
+      let entry = attempts.entry(key.clone()).or_insert(Attempts {
+        failures:      0,
+        last_failure:  now,
+        blocked_until: now,
+      });
+      if now.duration_since(entry.last_failure) >= RESET_WINDOW {
+        entry.failures = 0;
+      }
+      entry.failures += 1;
+      entry.last_failure = now;
+      entry.blocked_until = now + policy.delay(entry.failures);
+      if entry.failures == policy.lockout_after {
+        warn!("login locked out for {key} after {} failures", entry.failures);
+      }
+    }
+    Ok(())
+  }
+
+  /// Reset the counters for the username and ip after a successful login.
+  pub fn record_success(&self, username: &Username, ip: Option<IpAddr>) {
+    let mut attempts = self.attempts.lock().expect("login throttle lock poisoned");
+    for (key, _) in keys(username, ip) {
+      attempts.remove(&key);
+    }
+  }
+}
+
+fn keys(username: &Username, ip: Option<IpAddr>) -> impl Iterator<Item = (String, ThrottlePolicy)> {
+  // usernames are case-insensitive for throttling purposes
+  let username_key = (format!("username:{}", username.0.to_lowercase()), USERNAME_POLICY);
+  let ip_key = ip.map(|ip| (format!("ip:{ip}"), IP_POLICY));
+  std::iter::once(username_key).chain(ip_key)
+}
+
+/// Get the client's ip from the `X-Forwarded-For` header set by our reverse proxy.
+///
+/// The proxy appends the address it was connected from, so only the last entry can be trusted;
+/// earlier entries are whatever the client sent, and would let it dodge the ip throttle or lock out
+/// an ip of its choosing.
+pub fn client_ip(headers: &HeaderMap) -> Option<IpAddr> {
+  headers
+    .get_all("x-forwarded-for")
+    .iter()
+    .last()
+    .and_then(|v| v.to_str().ok())
+    .and_then(|v| v.rsplit(',').next())
+    .and_then(|ip| ip.trim().parse().ok())
+}
@@ -1,10 +1,12 @@
+use argon2::Params;
 use axum_login::{AuthUser, AuthnBackend, UserId};
-use db::{models::user::User, DbPool, Username};
+use db::{models::user::User, DbPool, Password, Username};
 use serde::Serialize;
 use tokio::task;
+use tracing::debug;
 
-use super::PasswordExt;
-use crate::{error::ApiError, CredentialsPayload};
+use super::{password::needs_rehash, PasswordExt};
+use crate::{error::ApiError, ApiResult, CredentialsPayload};
 
 #[derive(Debug, Clone, Serialize)]
 pub struct UserWrapper(pub User);
@@ -21,11 +23,18 @@ impl AuthUser for UserWrapper {
 
 #[derive(Debug, Clone)]
 pub struct AuthBackend {
-  db: DbPool,
+  db:            DbPool,
+  argon2_params: Params,
+  /// Verified against when the username is unknown, so that unknown usernames and incorrect
+  /// passwords take the same time to reject.
+  dummy_hash:    db::PasswordHash,
 }
 
 impl AuthBackend {
-  pub fn new(db: DbPool) -> Self { Self { db } }
+  pub async fn new(db: DbPool, argon2_params: Params) -> ApiResult<Self> {
+    let dummy_hash = Password("dummy password".into()).hash(&argon2_params).await?;
+    Ok(Self { db, argon2_params, dummy_hash })
+  }
 }
 
 #[axum::async_trait]
@@ -38,20 +47,35 @@ impl AuthnBackend for AuthBackend {
   ///
   /// Ok(Some(User)) - If the user exists, and the password is correct
   /// Ok(None) - Never
-  /// Err(ApiError) - If the user doesn't exist, or the password is incorrect.
+  /// Err(UnauthorizedIncorrectPassword) - If the user doesn't exist, or the password is incorrect.
+  ///
+  /// If the stored hash was made with outdated argon2 parameters, replace it. Note that this
+  /// invalidates the user's other sessions, as the hash is the session auth hash.
   async fn authenticate(
     &self,
     creds: Self::Credentials,
   ) -> Result<Option<Self::User>, Self::Error> {
-    // safety - get_user errors on None, always some
-    let user = self.get_user(&creds.username).await?.unwrap();
-    creds.password.hash_and_verify(&user.0.password_hash).await?;
-    Ok(Some(user))
+    let Some(mut user) = db::queries::users::get_user(&self.db, &creds.username).await? else {
+      // don't reveal whether the username exists, by result or by timing
+      let _ = creds.password.hash_and_verify(&self.dummy_hash).await;
+      return Err(ApiError::UnauthorizedIncorrectPassword);
+    };
+    creds.password.hash_and_verify(&user.password_hash).await?;
+
+    if needs_rehash(&user.password_hash, &self.argon2_params) {
+      debug!("rehashing password for: {}", user.username);
+      let new_hash = creds.password.hash(&self.argon2_params).await?;
+      db::queries::users::update_user_password(&self.db, &user.username, &new_hash).await?;
+      user.password_hash = new_hash;
+    }
+
+    Ok(Some(UserWrapper(user)))
   }
 
+  /// Get the user for a session. Ok(None) if the user no longer exists, ending the session.
   async fn get_user(&self, username: &UserId<Self>) -> Result<Option<Self::User>, Self::Error> {
-    let user = db::queries::users::get_assert_user(&self.db, username).await?;
-    Ok(Some(UserWrapper(user)))
+    let user = db::queries::users::get_user(&self.db, username).await?;
+    Ok(user.map(UserWrapper))
   }
 }
 
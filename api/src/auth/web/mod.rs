@@ -1,28 +1,121 @@
+use std::net::IpAddr;
+
 use axum::http::StatusCode;
+use db::{
+  models::{user_session::UserSession, user_wallet::UserWallet},
+  queries::{user_sessions, user_wallets},
+  DbPool, Username,
+};
 use tracing::{debug, error};
 
-use crate::{auth::users::AuthSession, ApiError, ApiResult, CredentialsPayload};
+use crate::{
+  auth::{
+    siwe::{self, SiweMessage},
+    users::{AuthSession, UserWrapper},
+    AuthenticationExt, LoginThrottle,
+  },
+  ApiError, ApiResult, CredentialsPayload, SiweVerifyPayload,
+};
 
 /// Internal login logic.
 ///
 /// Isolate from the login handler to maintain consistency with axum-login style example.
+/// Refuse to check the password if the username or ip has failed to log in too often recently,
+/// and otherwise count the attempt as a failure before checking it. On success, reset the failure
+/// counters, and record the session's metadata so the user can later list and revoke it.
 pub async fn login_post_internal(
+  pool: &DbPool,
+  throttle: &LoginThrottle,
   mut auth_session: AuthSession,
   creds: CredentialsPayload,
+  user_agent: Option<String>,
+  ip: Option<IpAddr>,
 ) -> ApiResult<StatusCode> {
+  throttle.check(&creds.username, ip)?;
   // safety - authenticate never returns None
   let user = auth_session.authenticate(creds.clone()).await?.unwrap();
+  throttle.record_success(&creds.username, ip);
   auth_session.login(&user).await?;
+  record_user_session(pool, &auth_session, creds.username.clone(), user_agent).await?;
+
   debug!("login success for user: {}", creds.username);
   Ok(StatusCode::OK)
 }
 
+/// Internal Sign-In with Ethereum logic.
+///
+/// Consume the nonce issued to the session, verify the message against it and `domain`, and
+/// recover the signing address. If the address is linked to an account, log in to it, as in
+/// `login_post_internal`. Otherwise, if the caller is logged in, link the address to their account.
+pub async fn siwe_verify_internal(
+  pool: &DbPool,
+  domain: &str,
+  mut auth_session: AuthSession,
+  payload: SiweVerifyPayload,
+  user_agent: Option<String>,
+) -> ApiResult<StatusCode> {
+  // take the nonce before anything else, so that each is only ever checked once
+  let nonce = siwe::take_nonce(&auth_session.session).await?;
+  let message: SiweMessage = payload.message.parse()?;
+  message.verify(domain, nonce.as_deref())?;
+  let address = siwe::recover_address(&payload.message, &payload.signature)?;
+  if address != message.address.to_lowercase() {
+    return Err(ApiError::UnauthorizedInvalidSignature("signed by another address".into()));
+  }
+
+  match user_wallets::get_user_by_wallet(pool, &address).await? {
+    Some(user) => {
+      let username = user.username.clone();
+      auth_session.login(&UserWrapper(user)).await?;
+      record_user_session(pool, &auth_session, username.clone(), user_agent).await?;
+      debug!("siwe login success for user: {username} with: {address}");
+    },
+    None => {
+      // nobody to link the wallet to
+      let user = auth_session.get_assert_user_from_session()?;
+      user_wallets::create_user_wallet(
+        pool,
+        &UserWallet::new(address.clone(), user.username.clone()),
+      )
+      .await?;
+      debug!("linked wallet {address} to user: {}", user.username);
+    },
+  }
+  Ok(StatusCode::OK)
+}
+
+/// Record the metadata for a freshly logged in session, so the user can later list and revoke it.
+async fn record_user_session(
+  pool: &DbPool,
+  auth_session: &AuthSession,
+  username: Username,
+  user_agent: Option<String>,
+) -> ApiResult<()> {
+  // login cycles the session id; save now so that the new id is assigned before we record it
+  auth_session.session.save().await.map_err(|e| ApiError::OtherISE(e.to_string()))?;
+  let session_id = auth_session
+    .session
+    .id()
+    .ok_or(ApiError::OtherISE("session id missing after login".to_string()))?
+    .to_string();
+  let user_session = UserSession::new(session_id, username, user_agent);
+  user_sessions::create_user_session(pool, &user_session).await?;
+  Ok(())
+}
+
 /// Internal logout logic.
 ///
 /// Isolate from the login handler to maintain consistency with axum-login style example.
 /// Will only error if db fails to flush the session.
-pub async fn logout_post_internal(mut auth_session: AuthSession) -> ApiResult<StatusCode> {
+pub async fn logout_post_internal(
+  pool: &DbPool,
+  mut auth_session: AuthSession,
+) -> ApiResult<StatusCode> {
+  let session_id = auth_session.session.id().map(|id| id.to_string());
   // NB - logout may return None if the user is not already logged in, ignored
   auth_session.logout().await?;
+  if let Some(session_id) = session_id {
+    user_sessions::delete_user_session_by_session_id(pool, &session_id).await?;
+  }
   Ok(StatusCode::OK)
 }
@@ -0,0 +1,302 @@
+//! Runtime configuration, supplied by the server on startup.
+use std::{collections::BTreeMap, str::FromStr, time::Duration};
+
+use argon2::Params;
+use db::{
+  models::{item::ItemType, user::User},
+  Title,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{ApiError, ApiResult};
+
+#[derive(Debug, Clone)]
+pub struct ApiConfig {
+  /// argon2 parameters for new password hashes. Existing hashes are rehashed on login when these
+  /// change.
+  pub argon2_params:              Params,
+  /// Who may create an account.
+  pub registration_mode:          RegistrationMode,
+  /// The karma required for each privilege.
+  pub privileges:                 PrivilegeTable,
+  /// Whether signup and submissions must include a proof of work. Solutions are verified when
+  /// given, regardless.
+  pub require_proof_of_work:      bool,
+  /// Proof of work difficulty for signups, in leading zero bits. Established accounts get less.
+  pub pow_base_difficulty:        u32,
+  /// PEM encoded RSA secret key for signing anonymous posting tokens. Generated on startup if
+  /// unset, in which case issued tokens do not survive a restart.
+  pub anon_token_key:             Option<String>,
+  /// The domain Sign-In with Ethereum messages must be issued for, e.g. `example.com`.
+  pub siwe_domain:                String,
+  /// Whether webhooks may target loopback and private network addresses. Enable only for local
+  /// development and tests; in production this would let users probe our internal network.
+  pub allow_private_webhook_urls: bool,
+  /// How often the webhook worker checks for due deliveries.
+  pub webhook_poll_interval:      Duration,
+  /// Accounts with more rows than this are exported in the background.
+  pub export_inline_limit:        i64,
+  /// How often the export worker checks for pending data exports.
+  pub export_poll_interval:       Duration,
+  /// How often karma is recomputed from content and votes, to report drift. None disables the
+  /// job; moderators may still run it on demand.
+  pub karma_reconcile_interval:   Option<Duration>,
+  /// How often recent votes are analyzed for vote rings and sockpuppets. None disables the job;
+  /// moderators may still run it on demand.
+  pub vote_analysis_interval:     Option<Duration>,
+  /// Whether votes flagged by vote analysis stop counting as soon as they are found, rather than
+  /// once a moderator confirms the flag.
+  pub discount_flagged_votes:     bool,
+  /// The title prefixes that mark `Show` and `Ask` items.
+  pub title_prefixes:             TitlePrefixes,
+}
+
+impl Default for ApiConfig {
+  fn default() -> Self {
+    Self {
+      argon2_params:              Params::default(),
+      registration_mode:          RegistrationMode::default(),
+      privileges:                 PrivilegeTable::default(),
+      require_proof_of_work:      true,
+      pow_base_difficulty:        20,
+      siwe_domain:                "localhost:8000".into(),
+      anon_token_key:             None,
+      allow_private_webhook_urls: false,
+      webhook_poll_interval:      Duration::from_secs(5),
+      export_inline_limit:        1000,
+      export_poll_interval:       Duration::from_secs(5),
+      karma_reconcile_interval:   Some(Duration::from_secs(24 * 60 * 60)),
+      vote_analysis_interval:     Some(Duration::from_secs(60 * 60)),
+      discount_flagged_votes:     false,
+      title_prefixes:             TitlePrefixes::default(),
+    }
+  }
+}
+
+impl ApiConfig {
+  /// Override the argon2 memory cost (KiB), iterations, and parallelism.
+  pub fn with_argon2_params(self, m_cost: u32, t_cost: u32, p_cost: u32) -> ApiResult<Self> {
+    let argon2_params = Params::new(m_cost, t_cost, p_cost, None)
+      .map_err(|e| ApiError::OtherISE(format!("invalid argon2 params: {e}")))?;
+    Ok(Self { argon2_params, ..self })
+  }
+
+  /// Restrict who may create an account.
+  pub fn with_registration_mode(self, registration_mode: RegistrationMode) -> Self {
+    Self { registration_mode, ..self }
+  }
+
+  /// Override the karma required for `privilege`.
+  pub fn with_privilege_karma(mut self, privilege: Privilege, karma: i32) -> Self {
+    self.privileges.0.insert(privilege, karma);
+    self
+  }
+
+  /// Set whether proof of work is required, and its difficulty for signups.
+  pub fn with_proof_of_work(self, require_proof_of_work: bool, pow_base_difficulty: u32) -> Self {
+    Self { require_proof_of_work, pow_base_difficulty, ..self }
+  }
+
+  /// Set the domain Sign-In with Ethereum messages must be issued for.
+  pub fn with_siwe_domain(self, siwe_domain: &str) -> Self {
+    Self { siwe_domain: siwe_domain.into(), ..self }
+  }
+
+  /// Set the PEM encoded RSA secret key for signing anonymous posting tokens.
+  pub fn with_anon_token_key(self, anon_token_key: &str) -> Self {
+    Self { anon_token_key: Some(anon_token_key.into()), ..self }
+  }
+
+  /// Allow webhooks to target loopback and private network addresses.
+  pub fn with_private_webhook_urls(self, allow_private_webhook_urls: bool) -> Self {
+    Self { allow_private_webhook_urls, ..self }
+  }
+
+  /// Override how often the webhook worker checks for due deliveries.
+  pub fn with_webhook_poll_interval(self, webhook_poll_interval: Duration) -> Self {
+    Self { webhook_poll_interval, ..self }
+  }
+
+  /// Override how large an account may be before its data exports are generated in the
+  /// background, and how often the export worker checks for pending exports.
+  pub fn with_data_exports(self, export_inline_limit: i64, export_poll_interval: Duration) -> Self {
+    Self { export_inline_limit, export_poll_interval, ..self }
+  }
+
+  /// Override how often karma is reconciled, or disable the job with None.
+  pub fn with_karma_reconcile_interval(self, karma_reconcile_interval: Option<Duration>) -> Self {
+    Self { karma_reconcile_interval, ..self }
+  }
+
+  /// Override how often votes are analyzed, or disable the job with None, and set whether flagged
+  /// votes are discounted without waiting for review.
+  pub fn with_vote_analysis(
+    self,
+    vote_analysis_interval: Option<Duration>,
+    discount_flagged_votes: bool,
+  ) -> Self {
+    Self { vote_analysis_interval, discount_flagged_votes, ..self }
+  }
+
+  /// Override the title prefixes that mark `Show` and `Ask` items.
+  pub fn with_title_prefixes(self, show: &str, ask: &str) -> Self {
+    Self { title_prefixes: TitlePrefixes { show: show.into(), ask: ask.into() }, ..self }
+  }
+}
+
+/// The title prefixes that mark `Show` and `Ask` items, compared case-insensitively. Titles with
+/// neither are `News` items.
+#[derive(Debug, Clone)]
+pub struct TitlePrefixes {
+  pub show: String,
+  pub ask:  String,
+}
+
+impl Default for TitlePrefixes {
+  fn default() -> Self { Self { show: "Show ZKHN:".into(), ask: "Ask ZKHN:".into() } }
+}
+
+impl TitlePrefixes {
+  /// The item type implied by `title`.
+  pub fn infer(&self, title: &Title) -> ItemType {
+    let starts_with =
+      |prefix: &str| title.0.get(..prefix.len()).is_some_and(|t| t.eq_ignore_ascii_case(prefix));
+    if starts_with(&self.show) {
+      ItemType::Show
+    } else if starts_with(&self.ask) {
+      ItemType::Ask
+    } else {
+      ItemType::News
+    }
+  }
+
+  /// Infer an item's type from its title. Return BadRequest if the type `requested` by the client,
+  /// if any, doesn't match, or if an `Ask` item has a url.
+  pub fn assert_item_type(
+    &self,
+    title: &Title,
+    requested: Option<ItemType>,
+    has_url: bool,
+  ) -> ApiResult<ItemType> {
+    let item_type = self.infer(title);
+    match requested {
+      Some(requested) if requested != item_type => {
+        let expected = match requested {
+          ItemType::Show => format!("start with `{}`", self.show),
+          ItemType::Ask => format!("start with `{}`", self.ask),
+          ItemType::News => format!("not start with `{}` or `{}`", self.show, self.ask),
+        };
+        return Err(ApiError::BadRequest(format!("titles of {requested} items must {expected}")));
+      },
+      _ => {},
+    }
+    if item_type == ItemType::Ask && has_url {
+      return Err(ApiError::BadRequest("ask items may not have a url".into()));
+    }
+    Ok(item_type)
+  }
+}
+
+/// Who may create an account.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RegistrationMode {
+  /// Anyone may register
+  #[default]
+  Open,
+  /// Registration requires an unused invite code
+  InviteOnly,
+  /// Nobody may register
+  Closed,
+}
+
+impl FromStr for RegistrationMode {
+  type Err = ApiError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "open" => Ok(Self::Open),
+      "invite-only" => Ok(Self::InviteOnly),
+      "closed" => Ok(Self::Closed),
+      _ => Err(ApiError::OtherISE(format!("invalid registration mode: {s}"))),
+    }
+  }
+}
+
+/// Actions that require a minimum karma. Moderators have every privilege.
+#[derive(
+  Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, ToSchema,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum Privilege {
+  Downvote,
+  /// todo(moderation): flagging is not implemented yet
+  Flag,
+  /// todo(moderation): vouching is not implemented yet
+  Vouch,
+  /// Submit items with a url, rather than text
+  SubmitLinks,
+  /// todo(polls): polls are not implemented yet
+  CreatePolls,
+  MintInvites,
+  /// Be issued anonymous posting tokens
+  AnonymousPosting,
+}
+
+impl Privilege {
+  pub const ALL: [Privilege; 7] = [
+    Privilege::Downvote,
+    Privilege::Flag,
+    Privilege::Vouch,
+    Privilege::SubmitLinks,
+    Privilege::CreatePolls,
+    Privilege::MintInvites,
+    Privilege::AnonymousPosting,
+  ];
+
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      Privilege::Downvote => "downvote",
+      Privilege::Flag => "flag",
+      Privilege::Vouch => "vouch",
+      Privilege::SubmitLinks => "submit-links",
+      Privilege::CreatePolls => "create-polls",
+      Privilege::MintInvites => "mint-invites",
+      Privilege::AnonymousPosting => "anonymous-posting",
+    }
+  }
+}
+
+/// The karma required for each privilege.
+#[derive(Debug, Clone)]
+pub struct PrivilegeTable(BTreeMap<Privilege, i32>);
+
+impl Default for PrivilegeTable {
+  fn default() -> Self {
+    Self(BTreeMap::from([
+      (Privilege::Downvote, 10),
+      (Privilege::Flag, 30),
+      (Privilege::Vouch, 30),
+      // new accounts start with 1 karma
+      (Privilege::SubmitLinks, 1),
+      (Privilege::CreatePolls, 200),
+      (Privilege::MintInvites, 50),
+      (Privilege::AnonymousPosting, 100),
+    ]))
+  }
+}
+
+impl PrivilegeTable {
+  /// The karma required for `privilege`.
+  pub fn karma(&self, privilege: Privilege) -> i32 { self.0[&privilege] }
+
+  /// Whether `user` has `privilege`.
+  pub fn has(&self, user: &User, privilege: Privilege) -> bool {
+    user.is_moderator || user.karma >= self.karma(privilege)
+  }
+
+  /// The privileges `user` has unlocked.
+  pub fn unlocked(&self, user: &User) -> Vec<Privilege> {
+    Privilege::ALL.into_iter().filter(|p| self.has(user, *p)).collect()
+  }
+}
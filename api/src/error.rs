@@ -3,7 +3,10 @@
 
 use std::fmt::write;
 
-use axum::http::StatusCode;
+use axum::{
+  http::{header, HeaderValue, StatusCode},
+  response::{IntoResponse, Response},
+};
 use db::DbError;
 use oauth2::{basic::BasicRequestTokenError, reqwest::AsyncHttpClientError};
 use tokio::task;
@@ -28,7 +31,8 @@ pub enum ApiError {
   /// New entry conflicts with another entry in the db
   #[status(StatusCode::CONFLICT)] // 409
   UniqueViolation(String),
-  #[status(StatusCode::INTERNAL_SERVER_ERROR)]
+  /// Entry references an entry that does not exist, or is still referenced by another entry
+  #[status(StatusCode::CONFLICT)] // 409
   ForeignKeyViolation(String),
   #[status(StatusCode::INTERNAL_SERVER_ERROR)]
   NotNullViolation(String),
@@ -54,6 +58,9 @@ pub enum ApiError {
   /// The client submitted an incorrect auth token
   #[status(StatusCode::UNAUTHORIZED)] // 401
   UnauthorizedIncorrectToken,
+  /// The client submitted an invalid signature, or signed an invalid message
+  #[status(StatusCode::UNAUTHORIZED)] // 401
+  UnauthorizedInvalidSignature(String),
   /// The client submitted a change to an item that is no longer editable (but not dead)
   #[status(StatusCode::FORBIDDEN)] // 403
   ForbiddenNotEditable(String),
@@ -69,6 +76,24 @@ pub enum ApiError {
   /// Caller must be a moderator
   #[status(StatusCode::FORBIDDEN)] // 403
   ForbiddenModeratorRequired,
+  /// Caller lacks the karma for this action
+  #[status(StatusCode::FORBIDDEN)] // 403
+  ForbiddenKarmaRequired(i32),
+  /// Caller has reached a limit on how many of something they may hold
+  #[status(StatusCode::FORBIDDEN)] // 403
+  ForbiddenLimitReached(String),
+  /// A required proof of work was missing or invalid
+  #[status(StatusCode::FORBIDDEN)] // 403
+  ForbiddenInvalidProofOfWork(String),
+  /// Registration is closed
+  #[status(StatusCode::FORBIDDEN)] // 403
+  ForbiddenRegistrationClosed,
+  /// Registration is invite-only, and no valid, unused invite code was given
+  #[status(StatusCode::FORBIDDEN)] // 403
+  ForbiddenInvalidInviteCode,
+  /// The client has failed to log in too many times; retry after the given number of seconds
+  #[status(StatusCode::TOO_MANY_REQUESTS)] // 429
+  TooManyLoginAttempts(u64),
   /// Garde payload validation failure.
   #[status(StatusCode::UNPROCESSABLE_ENTITY)] // 422
   InvalidPayload(#[from] garde::Report),
@@ -91,17 +116,45 @@ impl std::fmt::Display for ApiError {
       ApiError::UnauthorizedPleaseLogin => write!(f, "Unauthorized: please log in",),
       ApiError::UnauthorizedIncorrectPassword => write!(f, "Unauthorized: Incorrect password"),
       ApiError::UnauthorizedIncorrectToken => write!(f, "Unauthorized: Incorrect Token"),
+      ApiError::UnauthorizedInvalidSignature(e) =>
+        write!(f, "Unauthorized: invalid signature: {e}"),
       ApiError::ForbiddenNotEditable(e) => write!(f, "Forbidden: {e}"),
       ApiError::ForbiddenDead => write!(f, "Forbidden: item or comment is dead"),
       ApiError::ForbiddenBanned => write!(f, "Forbidden: User is banned"),
       ApiError::ForbiddenUsernameDoesNotMatchSession =>
         write!(f, "Forbidden: provided username does not match session"),
       ApiError::ForbiddenModeratorRequired => write!(f, "Forbidden: Moderator only"),
+      ApiError::ForbiddenKarmaRequired(karma) =>
+        write!(f, "Forbidden: at least {karma} karma required"),
+      ApiError::ForbiddenLimitReached(e) => write!(f, "Forbidden: limit reached: {e}"),
+      ApiError::ForbiddenInvalidProofOfWork(e) => write!(f, "Forbidden: proof of work: {e}"),
+      ApiError::ForbiddenRegistrationClosed => write!(f, "Forbidden: registration is closed"),
+      ApiError::ForbiddenInvalidInviteCode =>
+        write!(f, "Forbidden: a valid, unused invite code is required"),
+      ApiError::TooManyLoginAttempts(secs) =>
+        write!(f, "Too Many Requests: too many failed logins, retry in {secs} seconds"),
       ApiError::InvalidPayload(e) => write!(f, "Invalid Payload: {0}", e.to_string().trim()),
     }
   }
 }
 
+impl ApiError {
+  /// Convert the error into a response, attaching a `Retry-After` header to throttled responses.
+  ///
+  /// `ErrorResponse` cannot set headers, so handlers that may throttle the caller use this instead.
+  pub(crate) fn into_response_with_retry_after(self) -> Response {
+    let retry_after = match self {
+      ApiError::TooManyLoginAttempts(secs) => Some(secs),
+      _ => None,
+    };
+    let mut response = self.into_response();
+    if let Some(secs) = retry_after {
+      response.headers_mut().insert(header::RETRY_AFTER, HeaderValue::from(secs));
+    }
+    response
+  }
+}
+
 impl From<DbError> for ApiError {
   fn from(e: DbError) -> Self {
     match e {
@@ -0,0 +1,166 @@
+//! Personal data exports.
+//!
+//! Exports are streamed to the client as they are serialized, a row at a time, and stored exports
+//! are read back in slices, so that serving an export never holds it in memory whole.
+//!
+//! Exports of accounts too large to export inline are queued in `data_exports` by
+//! `GET /users/export`. A background worker polls the queue, leasing each pending export while it
+//! generates the bundle, so that an export interrupted by a restart is picked up again once its
+//! lease expires. The worker also deletes expired exports.
+use std::time::Duration;
+
+use db::{
+  models::{data_export::DataExport, user::User},
+  queries, DbPool, DbResult, Timestamp,
+};
+use futures::{channel::mpsc, stream::BoxStream, SinkExt, Stream, TryStreamExt};
+use serde::Serialize;
+use tracing::{debug, error};
+
+use crate::{ApiConfig, ApiError, ApiResult, ExportedUser};
+
+/// Give up on an export after this many attempts.
+const MAX_ATTEMPTS: i32 = 3; // todo(config)
+/// Claimed exports are retried after this long if the worker dies before storing them.
+const LEASE: Duration = Duration::from_secs(15 * 60);
+/// Exports claimed per poll.
+const BATCH_SIZE: i64 = 5;
+/// Serialized rows are sent on once they fill a chunk this large, in bytes.
+const CHUNK_SIZE: usize = 64 * 1024;
+/// Stored bundles are read from the database in slices this large, in characters.
+pub(crate) const STORED_CHUNK_CHARS: i32 = 256 * 1024;
+
+/// Spawn the background task generating queued data exports, and deleting expired ones.
+pub(crate) fn spawn_export_worker(pool: DbPool, config: &ApiConfig) {
+  let poll_interval = config.export_poll_interval;
+  tokio::spawn(async move {
+    let mut interval = tokio::time::interval(poll_interval);
+    loop {
+      interval.tick().await;
+      if let Err(e) = generate_pending(&pool).await {
+        error!("data export generation failed: {e}");
+      }
+      if let Err(e) = delete_expired(&pool).await {
+        error!("deleting expired data exports failed: {e}");
+      }
+    }
+  });
+}
+
+/// Stream `user`'s export as JSON, in the shape of `UserExport`. An error ends the stream, as
+/// text.
+pub(crate) fn stream_export(
+  pool: DbPool,
+  user: User,
+) -> impl Stream<Item = Result<String, String>> {
+  let (mut tx, rx) = mpsc::channel(1);
+  tokio::spawn(async move {
+    let username = user.username.clone();
+    if let Err(e) = write_export(&pool, user, &mut tx).await {
+      // the receiver is gone if the download was cancelled
+      debug!("data export for {username} ended early: {e}");
+      let _ = tx.send(Err(e.to_string())).await;
+    }
+  });
+  rx
+}
+
+/// Serialize `user`'s export into `tx`, reading each collection as it is written.
+async fn write_export(
+  pool: &DbPool,
+  user: User,
+  tx: &mut mpsc::Sender<Result<String, String>>,
+) -> ApiResult<()> {
+  let username = user.username.clone();
+  let mut chunk = format!(
+    "{{\"exported\":{},\"user\":{}",
+    to_json(&Timestamp::now())?,
+    to_json(&ExportedUser::from(user))?
+  );
+  write_array(tx, &mut chunk, "items", queries::items::get_user_items(pool, &username)).await?;
+  let comments = queries::comments::get_comments_by_user(pool, &username);
+  write_array(tx, &mut chunk, "comments", comments).await?;
+  let votes = queries::user_votes::get_user_votes(pool, &username);
+  write_array(tx, &mut chunk, "votes", votes).await?;
+  let favorites = queries::user_favorites::get_user_favorites(pool, &username);
+  write_array(tx, &mut chunk, "favorites", favorites).await?;
+  let moderation_logs = queries::moderation_logs::get_moderation_logs_for_user(pool, &username);
+  write_array(tx, &mut chunk, "moderationLogs", moderation_logs).await?;
+  chunk.push('}');
+  send(tx, chunk).await
+}
+
+/// Append `rows` to `chunk` as the JSON array field `key`, sending the chunk on whenever it fills.
+async fn write_array<T: Serialize>(
+  tx: &mut mpsc::Sender<Result<String, String>>,
+  chunk: &mut String,
+  key: &str,
+  mut rows: BoxStream<'_, DbResult<T>>,
+) -> ApiResult<()> {
+  chunk.push_str(&format!(",\"{key}\":["));
+  let mut first = true;
+  while let Some(row) = rows.try_next().await? {
+    if !first {
+      chunk.push(',');
+    }
+    first = false;
+    chunk.push_str(&to_json(&row)?);
+    if chunk.len() >= CHUNK_SIZE {
+      send(tx, std::mem::take(chunk)).await?;
+    }
+  }
+  chunk.push(']');
+  Ok(())
+}
+
+async fn send(tx: &mut mpsc::Sender<Result<String, String>>, chunk: String) -> ApiResult<()> {
+  tx.send(Ok(chunk)).await.map_err(|_| ApiError::OtherISE("data export cancelled".into()))
+}
+
+fn to_json(value: &impl Serialize) -> ApiResult<String> {
+  serde_json::to_string(value).map_err(|e| ApiError::OtherISE(e.to_string()))
+}
+
+/// Generate and store every pending export.
+async fn generate_pending(pool: &DbPool) -> ApiResult<()> {
+  let lease_until = Timestamp::now() + chrono::Duration::from_std(LEASE).unwrap();
+  let pending =
+    db::queries::data_exports::claim_pending_data_exports(pool, BATCH_SIZE, &lease_until).await?;
+
+  for data_export in pending {
+    let result = if data_export.attempts > MAX_ATTEMPTS {
+      Err(format!("gave up after {MAX_ATTEMPTS} attempts"))
+    } else {
+      generate(pool, &data_export).await
+    };
+    match &result {
+      Ok(_) => debug!("generated data export {}", data_export.id),
+      Err(e) => error!("data export {} failed: {e}", data_export.id),
+    }
+    if let Err(e) =
+      db::queries::data_exports::complete_data_export(pool, &data_export.id, result).await
+    {
+      error!("failed to store data export {}: {e}", data_export.id);
+    }
+  }
+  Ok(())
+}
+
+/// The serialized `UserExport` for the export's user, or the reason it couldn't be generated.
+async fn generate(pool: &DbPool, data_export: &DataExport) -> Result<String, String> {
+  let user = db::queries::users::get_assert_user(pool, &data_export.username)
+    .await
+    .map_err(|e| e.to_string())?;
+  stream_export(pool.clone(), user).try_collect().await
+}
+
+/// Delete the exports past their expiration, whether or not they were downloaded.
+async fn delete_expired(pool: &DbPool) -> ApiResult<()> {
+  let expired_before = Timestamp::now() + -DataExport::ttl();
+  let deleted =
+    db::queries::data_exports::delete_expired_data_exports(pool, &expired_before).await?;
+  if deleted > 0 {
+    debug!("deleted {deleted} expired data exports");
+  }
+  Ok(())
+}
@@ -0,0 +1,33 @@
+//! Karma reconciliation.
+//!
+//! Every change to a user's karma is recorded in the karma ledger, by the db transaction making
+//! the change. A background job periodically recomputes each user's karma from their content and
+//! the votes on it, and records any user whose stored karma or ledger has drifted from it.
+//!
+//! The scheduled job only reports drift; moderators review it, and may run the job with
+//! corrections applied, from the admin endpoints.
+use db::DbPool;
+use tracing::{error, info, warn};
+
+use crate::ApiConfig;
+
+/// Spawn the reconciliation job, if enabled.
+pub(crate) fn spawn_reconciliation_job(pool: DbPool, config: &ApiConfig) {
+  let Some(period) = config.karma_reconcile_interval else { return };
+  tokio::spawn(async move {
+    let mut interval = tokio::time::interval(period);
+    // the first tick completes immediately; don't reconcile on every restart
+    interval.tick().await;
+    loop {
+      interval.tick().await;
+      match db::queries::reconcile_karma(&pool, false).await {
+        Ok(r) if r.discrepancy_count > 0 => warn!(
+          "karma reconciliation {} found drift for {} of {} users",
+          r.id, r.discrepancy_count, r.users_checked
+        ),
+        Ok(r) => info!("karma reconciliation {} found no drift", r.id),
+        Err(e) => error!("karma reconciliation failed: {e}"),
+      }
+    }
+  });
+}
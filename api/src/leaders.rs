@@ -0,0 +1,123 @@
+//! Leaderboards, like HN's `/leaders`.
+//!
+//! Boards are expensive to compute, so each is cached for `CACHE_TTL` after it is first requested.
+//! Concurrent requests for a stale board may each recompute it; the last to finish is kept.
+use std::{
+  collections::HashMap,
+  sync::{Arc, Mutex},
+  time::{Duration, Instant},
+};
+
+use chrono::TimeDelta;
+use db::{models::leader::Leader, DbPool, Timestamp, Username};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::ApiResult;
+
+/// Users listed per board.
+const LEADERS_COUNT: i64 = 100; // todo(config)
+/// The span of the rolling boards.
+const ROLLING_WINDOW_DAYS: i64 = 30;
+/// Submissions are counted on the `submissions` board once they have this many points.
+pub const SUBMISSION_MIN_POINTS: i32 = 10; // todo(config)
+const CACHE_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// A leaderboard.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum LeaderBoard {
+  /// All time, by karma
+  #[default]
+  Karma,
+  /// Net karma gained over the last 30 days
+  KarmaGained,
+  /// Items submitted over the last 30 days that reached `SUBMISSION_MIN_POINTS` points
+  Submissions,
+}
+
+/// Query for `GET /leaders`
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, IntoParams)]
+pub struct LeadersQuery {
+  /// `karma`, `karma-gained`, or `submissions`; defaults to `karma`
+  #[serde(default)]
+  pub board: LeaderBoard,
+}
+
+/// A leaderboard, as of `computed`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[schema(default = LeadersResponse::default, example=LeadersResponse::default)]
+pub struct LeadersResponse {
+  pub board:    LeaderBoard,
+  pub leaders:  Vec<LeaderResponse>,
+  /// when the board was computed; boards are cached for a few minutes
+  pub computed: Timestamp,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[schema(default = LeaderResponse::default, example=LeaderResponse::default)]
+pub struct LeaderResponse {
+  /// 1-indexed
+  pub rank:     usize,
+  pub username: Username,
+  /// karma, karma gained, or submissions counted, depending on the board
+  pub score:    i32,
+}
+
+/// Recently computed leaderboards.
+#[derive(Clone, Default)]
+pub struct LeaderboardCache {
+  boards: Arc<Mutex<HashMap<LeaderBoard, (Instant, LeadersResponse)>>>,
+}
+
+impl LeaderboardCache {
+  pub fn new() -> Self { Self::default() }
+
+  /// Get `board`, computing it if it isn't cached or has gone stale.
+  pub async fn get(&self, pool: &DbPool, board: LeaderBoard) -> ApiResult<LeadersResponse> {
+    if let Some(response) = self.cached(board) {
+      return Ok(response);
+    }
+    let response = compute(pool, board).await?;
+    self
+      .boards
+      .lock()
+      .expect("leaderboard cache lock poisoned")
+      .insert(board, (Instant::now(), response.clone()));
+    Ok(response)
+  }
+
+  fn cached(&self, board: LeaderBoard) -> Option<LeadersResponse> {
+    let boards = self.boards.lock().expect("leaderboard cache lock poisoned");
+    boards
+      .get(&board)
+      .filter(|(computed, _)| computed.elapsed() < CACHE_TTL)
+      .map(|(_, response)| response.clone())
+  }
+}
+
+async fn compute(pool: &DbPool, board: LeaderBoard) -> ApiResult<LeadersResponse> {
+  let since = Timestamp(Timestamp::now().0 - TimeDelta::days(ROLLING_WINDOW_DAYS));
+  let leaders = match board {
+    LeaderBoard::Karma => db::queries::leaders::get_karma_leaders(pool, LEADERS_COUNT).await?,
+    LeaderBoard::KarmaGained =>
+      db::queries::leaders::get_karma_gained_leaders(pool, &since, LEADERS_COUNT).await?,
+    LeaderBoard::Submissions =>
+      db::queries::leaders::get_submission_leaders(
+        pool,
+        &since,
+        SUBMISSION_MIN_POINTS,
+        LEADERS_COUNT,
+      )
+      .await?,
+  };
+  let leaders = leaders
+    .into_iter()
+    .enumerate()
+    .map(|(i, Leader { username, score })| LeaderResponse { rank: i + 1, username, score })
+    .collect();
+
+  Ok(LeadersResponse { board, leaders, computed: Timestamp::now() })
+}
@@ -6,11 +6,20 @@
 #![allow(clippy::clone_on_copy)]
 #![allow(unused_mut)]
 
+mod anon;
 mod auth;
+mod config;
 mod error;
+mod exports;
+mod karma;
+mod leaders;
+mod pow;
+mod revisions;
 mod routes;
 mod sessions;
 mod utils;
+mod vote_analysis;
+mod webhooks;
 
 use axum::Router;
 use db::DbPool;
@@ -22,19 +31,33 @@ pub(crate) type ApiResult<T> = Result<T, ApiError>;
 
 // export payloads and responses
 pub use self::{
+  anon::{AnonToken, AnonTokenRequest},
+  auth::{sign_siwe_message, siwe_address, SiweMessage},
+  config::{ApiConfig, Privilege, PrivilegeTable, RegistrationMode, TitlePrefixes},
   error::ApiError,
-  routes::{items::*, users::*},
+  leaders::{LeaderBoard, LeaderResponse, LeadersResponse},
+  pow::{solve as solve_pow_challenge, PowAction, PowChallengeResponse, PowSolution},
+  revisions::{RevisionDiffQuery, RevisionDiffResponse, RevisionResponse, RevisionsResponse},
+  routes::{admin::*, anon::*, comments::*, items::*, siwe::*, users::*, webhooks::*},
+  webhooks::{sign as sign_webhook_payload, DELIVERY_HEADER, EVENT_HEADER, SIGNATURE_HEADER},
 };
 
-pub const MINIMUM_KARMA_TO_DOWNVOTE: i32 = 10; // todo(config)
 pub const COMMENTS_PER_PAGE: usize = 10; // todo(config)
+/// The most unused invite codes a non-moderator may hold at once
+pub const MAX_UNUSED_INVITE_CODES: i64 = 5; // todo(config)
+/// The most anonymous posting tokens a user may be issued per day
+pub const ANON_TOKENS_PER_DAY: i32 = 3; // todo(config)
 
-pub async fn app(pool: DbPool, session_key: Key) -> ApiResult<Router> {
+pub async fn app(pool: DbPool, session_key: Key, config: ApiConfig) -> ApiResult<Router> {
   let session_layer = create_migrate_session_layer(pool.clone(), session_key).await;
-  let auth_layer = get_auth_layer(pool.clone(), session_layer);
+  let auth_layer = get_auth_layer(pool.clone(), session_layer, &config).await?;
+  webhooks::spawn_delivery_worker(pool.clone(), &config);
+  exports::spawn_export_worker(pool.clone(), &config);
+  karma::spawn_reconciliation_job(pool.clone(), &config);
+  vote_analysis::spawn_analysis_job(pool.clone(), &config);
 
   // serve the router and layer any route-agnostic middleware.
-  let router = routes::routes(pool).layer(auth_layer);
+  let router = routes::routes(pool, config)?.layer(auth_layer);
 
   Ok(router)
 }
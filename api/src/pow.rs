@@ -0,0 +1,203 @@
+//! Hashcash-style proof of work, deterring bots from signing up and submitting content.
+//!
+//! The server hands out a signed challenge naming the action, the account it was issued to, a
+//! difficulty, and an expiry. The client searches for a solution such that
+//! `sha256("{challenge}:{solution}")` has at least `difficulty` leading zero bits, and submits both
+//! with the request. Verifying takes one HMAC and one hash; solving takes ~2^difficulty hashes.
+//!
+//! Challenges are signed with a key generated on startup, so a restart invalidates outstanding
+//! challenges. Each challenge may be used once.
+use std::{
+  collections::HashMap,
+  sync::{Arc, Mutex},
+};
+
+use chrono::Utc;
+use db::models::user::User;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{ApiError, ApiResult};
+
+/// How long a challenge may be solved and submitted within.
+const CHALLENGE_TTL_SECS: i64 = 5 * 60;
+/// Established accounts never get a challenge easier than this, unless the base is lower.
+const MIN_DIFFICULTY: u32 = 8;
+/// An account's difficulty drops one bit per week of age, up to this many bits...
+const MAX_AGE_DISCOUNT: u32 = 6;
+/// ...and one bit per `KARMA_PER_BIT` karma, up to this many bits.
+const MAX_KARMA_DISCOUNT: u32 = 6;
+const KARMA_PER_BIT: i32 = 50;
+
+/// The action a challenge is issued for. A solution is only accepted for the same action.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum PowAction {
+  #[default]
+  Signup,
+  Item,
+  Comment,
+}
+
+impl PowAction {
+  fn as_str(&self) -> &'static str {
+    match self {
+      PowAction::Signup => "signup",
+      PowAction::Item => "item",
+      PowAction::Comment => "comment",
+    }
+  }
+}
+
+/// Query for `GET /pow/challenge`
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, IntoParams)]
+pub struct PowChallengeQuery {
+  pub action: PowAction,
+}
+
+/// A challenge to solve before performing `action`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[schema(default = PowChallengeResponse::default, example=PowChallengeResponse::default)]
+pub struct PowChallengeResponse {
+  /// Opaque, signed challenge string, to be returned with the solution
+  pub challenge:  String,
+  /// Required leading zero bits of `sha256("{challenge}:{solution}")`
+  pub difficulty: u32,
+  /// Unix time after which the challenge is no longer accepted
+  pub expires:    i64,
+}
+
+/// A solved challenge, submitted alongside a request requiring proof of work.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[schema(default = PowSolution::default, example=PowSolution::default)]
+pub struct PowSolution {
+  pub challenge: String,
+  pub solution:  String,
+}
+
+/// Issues and verifies challenges.
+#[derive(Clone)]
+pub struct ProofOfWork {
+  key:  Arc<[u8; 32]>,
+  /// Challenges that have been used, with their expiry, to reject replays
+  used: Arc<Mutex<HashMap<String, i64>>>,
+}
+
+impl Default for ProofOfWork {
+  fn default() -> Self { Self::new() }
+}
+
+impl ProofOfWork {
+  pub fn new() -> Self { Self { key: Arc::new(rand::random()), used: Default::default() } }
+
+  /// Issue a challenge for `action`, for `user` if logged in.
+  pub fn challenge(
+    &self,
+    action: PowAction,
+    user: Option<&User>,
+    base_difficulty: u32,
+  ) -> PowChallengeResponse {
+    let difficulty = difficulty(base_difficulty, user);
+    let expires = Utc::now().timestamp() + CHALLENGE_TTL_SECS;
+    let nonce = hex::encode(rand::random::<[u8; 16]>());
+    let subject = user.map(|u| u.username.0.as_str()).unwrap_or_default();
+
+    let claims = format!("{}:{subject}:{difficulty}:{expires}:{nonce}", action.as_str());
+    let challenge = format!("{claims}.{}", hex::encode(self.sign(&claims)));
+    PowChallengeResponse { challenge, difficulty, expires }
+  }
+
+  /// Verify that `solution` solves an unexpired, unused challenge we issued for `action` and
+  /// `user`, and mark the challenge used.
+  pub fn verify(
+    &self,
+    solution: &PowSolution,
+    action: PowAction,
+    user: Option<&User>,
+  ) -> ApiResult<()> {
+    let invalid = |reason: &str| ApiError::ForbiddenInvalidProofOfWork(reason.to_string());
+    let (claims, signature) = solution.challenge.rsplit_once('.').ok_or(invalid("malformed"))?;
+    let signature = hex::decode(signature).map_err(|_| invalid("malformed"))?;
+    let mut mac = self.mac();
+    mac.update(claims.as_bytes());
+    mac.verify_slice(&signature).map_err(|_| invalid("bad signature"))?;
+
+    let fields: Vec<&str> = claims.split(':').collect();
+    let [claimed_action, subject, difficulty, expires, _nonce] = fields[..] else {
+      return Err(invalid("malformed"));
+    };
+    let difficulty: u32 = difficulty.parse().map_err(|_| invalid("malformed"))?;
+    let expires: i64 = expires.parse().map_err(|_| invalid("malformed"))?;
+
+    if claimed_action != action.as_str() {
+      return Err(invalid("issued for another action"));
+    }
+    if subject != user.map(|u| u.username.0.as_str()).unwrap_or_default() {
+      return Err(invalid("issued for another account"));
+    }
+    let now = Utc::now().timestamp();
+    if now > expires {
+      return Err(invalid("expired"));
+    }
+    if leading_zero_bits(&hash(&solution.challenge, &solution.solution)) < difficulty {
+      return Err(invalid("incorrect solution"));
+    }
+
+    let mut used = self.used.lock().expect("proof of work lock poisoned");
+    used.retain(|_, expires| *expires >= now);
+    if used.insert(solution.challenge.clone(), expires).is_some() {
+      return Err(invalid("already used"));
+    }
+    Ok(())
+  }
+
+  fn mac(&self) -> Hmac<Sha256> {
+    Hmac::<Sha256>::new_from_slice(self.key.as_slice()).expect("hmac accepts keys of any length")
+  }
+
+  fn sign(&self, claims: &str) -> Vec<u8> {
+    let mut mac = self.mac();
+    mac.update(claims.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+  }
+}
+
+/// The difficulty of a challenge for `user`.
+///
+/// Signups pay the full base difficulty. Established accounts get easier challenges: one bit
+/// less per week of age, and per `KARMA_PER_BIT` karma.
+pub fn difficulty(base_difficulty: u32, user: Option<&User>) -> u32 {
+  let Some(user) = user else { return base_difficulty };
+  let age_weeks = (Utc::now() - user.created.0).num_weeks().max(0) as u32;
+  let karma_bits = (user.karma.max(0) / KARMA_PER_BIT) as u32;
+  let discount = age_weeks.min(MAX_AGE_DISCOUNT) + karma_bits.min(MAX_KARMA_DISCOUNT);
+  base_difficulty.saturating_sub(discount).max(MIN_DIFFICULTY.min(base_difficulty))
+}
+
+/// Find a solution to `challenge`. Clients may use this, or their own implementation.
+pub fn solve(challenge: &PowChallengeResponse) -> PowSolution {
+  let solution = (0u64..)
+    .map(|n| n.to_string())
+    .find(|n| leading_zero_bits(&hash(&challenge.challenge, n)) >= challenge.difficulty)
+    .expect("a solution exists");
+  PowSolution { challenge: challenge.challenge.clone(), solution }
+}
+
+fn hash(challenge: &str, solution: &str) -> [u8; 32] {
+  Sha256::digest(format!("{challenge}:{solution}").as_bytes()).into()
+}
+
+fn leading_zero_bits(hash: &[u8]) -> u32 {
+  let mut bits = 0;
+  for byte in hash {
+    bits += byte.leading_zeros();
+    if *byte != 0 {
+      break;
+    }
+  }
+  bits
+}
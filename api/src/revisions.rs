@@ -0,0 +1,148 @@
+//! Edit history for items and comments.
+//!
+//! Every edit is recorded as a revision by the db, in the edit's transaction. Revisions are shown
+//! to moderators and the content's author, either whole or as a unified diff between two of them.
+use db::{
+  models::{item::ItemCategory, revision::Revision, user::User},
+  Timestamp, Ulid, Username,
+};
+use serde::{Deserialize, Serialize};
+use similar::TextDiff;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+  auth::{AuthSession, AuthenticationExt},
+  ApiError, ApiResult,
+};
+
+/// Lines of unchanged context around each change in a diff.
+const DIFF_CONTEXT_LINES: usize = 3;
+
+/// Return the caller if they are a moderator or `author`, who may see the content's revisions.
+pub(crate) fn assert_may_view_revisions(
+  auth_session: &AuthSession,
+  author: &Username,
+) -> ApiResult<User> {
+  let user = auth_session.get_assert_user_from_session()?;
+  if !user.is_moderator && user.username != *author {
+    return Err(ApiError::ForbiddenUsernameDoesNotMatchSession);
+  }
+  Ok(user)
+}
+
+/// Every revision of an item or comment, oldest first. Empty if it was never edited.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[schema(default = RevisionsResponse::default, example=RevisionsResponse::default)]
+pub struct RevisionsResponse {
+  pub content_id: Ulid,
+  pub revisions:  Vec<RevisionResponse>,
+}
+
+impl RevisionsResponse {
+  pub fn new(content_id: Ulid, revisions: Vec<Revision>) -> Self {
+    Self { content_id, revisions: revisions.into_iter().map(RevisionResponse::from).collect() }
+  }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[schema(default = RevisionResponse::default, example=RevisionResponse::default)]
+pub struct RevisionResponse {
+  /// counts from 1, the original version
+  pub revision:      i32,
+  /// items only
+  pub title:         Option<String>,
+  /// items only
+  pub item_category: Option<ItemCategory>,
+  pub text:          Option<String>,
+  pub edited_by:     Username,
+  pub created:       Timestamp,
+}
+
+impl From<Revision> for RevisionResponse {
+  fn from(r: Revision) -> Self {
+    Self {
+      revision:      r.revision,
+      title:         r.title,
+      item_category: r.item_category,
+      text:          r.text,
+      edited_by:     r.edited_by,
+      created:       r.created,
+    }
+  }
+}
+
+/// Query for the diff between two revisions.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, IntoParams)]
+pub struct RevisionDiffQuery {
+  /// The older revision; defaults to the one before `to`
+  pub from: Option<i32>,
+  /// The newer revision; defaults to the latest
+  pub to:   Option<i32>,
+}
+
+/// The changes between two revisions, as a unified diff.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[schema(default = RevisionDiffResponse::default, example=RevisionDiffResponse::default)]
+pub struct RevisionDiffResponse {
+  pub content_id: Ulid,
+  pub from:       i32,
+  pub to:         i32,
+  pub diff:       String,
+}
+
+impl RevisionDiffResponse {
+  /// Diff the revisions picked by `query`, from `revisions`.
+  pub fn new(
+    content_id: Ulid,
+    revisions: &[Revision],
+    query: RevisionDiffQuery,
+  ) -> ApiResult<Self> {
+    if query.from.is_some_and(|n| n < 1) || query.to.is_some_and(|n| n < 1) {
+      return Err(ApiError::BadRequest("revisions count from 1".into()));
+    }
+    let to = match query.to {
+      Some(to) => to,
+      None => revisions.last().ok_or(ApiError::DbEntryNotFound("revisions".into()))?.revision,
+    };
+    let from = query.from.unwrap_or(to - 1);
+    if from < 1 || from >= to {
+      return Err(ApiError::BadRequest("`from` must be an earlier revision than `to`".into()));
+    }
+    let find = |n: i32| {
+      revisions
+        .iter()
+        .find(|r| r.revision == n)
+        .ok_or(ApiError::DbEntryNotFound(format!("revision {n}")))
+    };
+    let (old, new) = (render(find(from)?), render(find(to)?));
+
+    let diff = TextDiff::from_lines(&old, &new)
+      .unified_diff()
+      .context_radius(DIFF_CONTEXT_LINES)
+      .header(&format!("revision {from}"), &format!("revision {to}"))
+      .to_string();
+    Ok(Self { content_id, from, to, diff })
+  }
+}
+
+/// Render a revision as text to diff. Items lead with their title and category.
+fn render(revision: &Revision) -> String {
+  let mut rendered = String::new();
+  if let Some(title) = &revision.title {
+    rendered.push_str(&format!("title: {title}\n"));
+  }
+  if let Some(category) = &revision.item_category {
+    rendered.push_str(&format!("category: {category}\n"));
+  }
+  if !rendered.is_empty() {
+    rendered.push('\n');
+  }
+  if let Some(text) = &revision.text {
+    rendered.push_str(text);
+    rendered.push('\n');
+  }
+  rendered
+}
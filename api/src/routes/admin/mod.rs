@@ -0,0 +1,333 @@
+pub(super) mod payload;
+pub(super) mod response;
+
+use axum::{
+  extract::{Path, Query, State},
+  http::StatusCode,
+  routing, Json, Router,
+};
+use db::{
+  models::{
+    comment::Comment,
+    item::Item,
+    karma::{KarmaDiscrepancy, KarmaReconciliation},
+    vote_flag::{VoteFlag, VoteFlagKind, VoteFlagStatus},
+  },
+  CommentText, Page, Timestamp, Title, Ulid, Username,
+};
+use garde::Validate;
+use serde::{Deserialize, Serialize};
+use tracing::{info, trace};
+use utoipa::{IntoParams, ToSchema};
+
+pub use self::{payload::*, response::*};
+use super::SharedState;
+use crate::{
+  auth::{AuthSession, AuthenticationExt},
+  error::ApiError,
+  vote_analysis::analyze_votes,
+  ApiResult,
+};
+
+/// Router to be mounted at "/admin". Every route is moderator only.
+pub(super) fn admin_router(state: SharedState) -> Router {
+  Router::new()
+    .route("/karma/reconcile", routing::post(post::reconcile_karma))
+    .route("/karma/reconciliations", routing::get(get::get_karma_reconciliations))
+    .route("/karma/reconciliations/:id", routing::get(get::get_karma_reconciliation))
+    .route("/vote-flags", routing::get(get::get_vote_flags))
+    .route("/vote-flags/analyze", routing::post(post::analyze_votes))
+    .route("/vote-flags/:id", routing::put(put::review_vote_flag))
+    .route("/items/deleted", routing::get(get::get_deleted_items))
+    .route("/items/:id/restore", routing::post(post::restore_item))
+    .route("/comments/deleted", routing::get(get::get_deleted_comments))
+    .route("/comments/:id/restore", routing::post(post::restore_comment))
+    .with_state(state)
+}
+
+pub(super) mod get {
+  use super::*;
+
+  #[utoipa::path(
+      get,
+      path = "/admin/karma/reconciliations",
+      params( Page ),
+      responses(
+        (status = 401, description = "Not logged in"),
+        (status = 403, description = "Moderators only"),
+        (status = 422, description = "Invalid page"),
+        (status = 200, body = [KarmaReconciliationResponse]),
+      ),
+  )]
+  /// Moderators: get the `page` of karma reconciliation runs, newest first.
+  pub async fn get_karma_reconciliations(
+    State(state): State<SharedState>,
+    auth_session: AuthSession,
+    Query(page): Query<Page>,
+  ) -> ApiResult<Json<Vec<KarmaReconciliationResponse>>> {
+    page.validate(&())?;
+    auth_session.get_assert_moderator_from_session()?;
+    let reconciliations = db::queries::karma::get_karma_reconciliations(&state.pool, &page).await?;
+
+    Ok(Json(reconciliations.into_iter().map(KarmaReconciliationResponse::from).collect()))
+  }
+
+  #[utoipa::path(
+      get,
+      path = "/admin/karma/reconciliations/{id}",
+      params( ("id" = String, Path, example = Ulid::new) ),
+      responses(
+        (status = 401, description = "Not logged in"),
+        (status = 403, description = "Moderators only"),
+        (status = 404, description = "Reconciliation not found"),
+        (status = 422, description = "Invalid id"),
+        (status = 200, body = KarmaReconciliationDetailResponse),
+      ),
+  )]
+  /// Moderators: get a karma reconciliation run, and the discrepancies it found.
+  pub async fn get_karma_reconciliation(
+    State(state): State<SharedState>,
+    auth_session: AuthSession,
+    Path(id): Path<Ulid>,
+  ) -> ApiResult<Json<KarmaReconciliationDetailResponse>> {
+    id.validate(&())?;
+    auth_session.get_assert_moderator_from_session()?;
+    let (reconciliation, discrepancies) = tokio::try_join!(
+      db::queries::karma::get_assert_karma_reconciliation(&state.pool, &id),
+      db::queries::karma::get_karma_discrepancies(&state.pool, &id),
+    )?;
+
+    Ok(Json(KarmaReconciliationDetailResponse {
+      reconciliation: reconciliation.into(),
+      discrepancies:  discrepancies.into_iter().map(KarmaDiscrepancyResponse::from).collect(),
+    }))
+  }
+
+  #[utoipa::path(
+      get,
+      path = "/admin/vote-flags",
+      params( VoteFlagsQuery, Page ),
+      responses(
+        (status = 401, description = "Not logged in"),
+        (status = 403, description = "Moderators only"),
+        (status = 422, description = "Invalid page"),
+        (status = 200, body = [VoteFlagResponse]),
+      ),
+  )]
+  /// Moderators: get the `page` of the vote flag review queue, oldest first.
+  pub async fn get_vote_flags(
+    State(state): State<SharedState>,
+    auth_session: AuthSession,
+    Query(query): Query<VoteFlagsQuery>,
+    Query(page): Query<Page>,
+  ) -> ApiResult<Json<Vec<VoteFlagResponse>>> {
+    page.validate(&())?;
+    auth_session.get_assert_moderator_from_session()?;
+    let flags = db::queries::vote_flags::get_vote_flags(&state.pool, query.status, &page).await?;
+
+    Ok(Json(flags.into_iter().map(VoteFlagResponse::from).collect()))
+  }
+
+  #[utoipa::path(
+      get,
+      path = "/admin/items/deleted",
+      params( Page ),
+      responses(
+        (status = 401, description = "Not logged in"),
+        (status = 403, description = "Moderators only"),
+        (status = 422, description = "Invalid page"),
+        (status = 200, body = [Item]),
+      ),
+  )]
+  /// Moderators: get the `page` of deleted items, most recently deleted first.
+  pub async fn get_deleted_items(
+    State(state): State<SharedState>,
+    auth_session: AuthSession,
+    Query(page): Query<Page>,
+  ) -> ApiResult<Json<Vec<Item>>> {
+    page.validate(&())?;
+    auth_session.get_assert_moderator_from_session()?;
+    let items = db::queries::items::get_deleted_items(&state.pool, &page).await?;
+
+    Ok(Json(items))
+  }
+
+  #[utoipa::path(
+      get,
+      path = "/admin/comments/deleted",
+      params( Page ),
+      responses(
+        (status = 401, description = "Not logged in"),
+        (status = 403, description = "Moderators only"),
+        (status = 422, description = "Invalid page"),
+        (status = 200, body = [DeletedCommentResponse]),
+      ),
+  )]
+  /// Moderators: get the `page` of deleted comments, most recently deleted first.
+  pub async fn get_deleted_comments(
+    State(state): State<SharedState>,
+    auth_session: AuthSession,
+    Query(page): Query<Page>,
+  ) -> ApiResult<Json<Vec<DeletedCommentResponse>>> {
+    page.validate(&())?;
+    auth_session.get_assert_moderator_from_session()?;
+    let comments = db::queries::comments::get_deleted_comments(&state.pool, &page).await?;
+
+    Ok(Json(comments.into_iter().map(DeletedCommentResponse::from).collect()))
+  }
+}
+
+pub(super) mod post {
+  use super::*;
+
+  #[utoipa::path(
+      post,
+      path = "/admin/karma/reconcile",
+      params( ReconcileKarmaQuery ),
+      responses(
+        (status = 401, description = "Not logged in"),
+        (status = 403, description = "Moderators only"),
+        (status = 200, body = KarmaReconciliationResponse),
+      ),
+  )]
+  /// Moderators: recompute every user's karma from their content and votes, and record any
+  /// drift. With `correct`, drifted karma is set to the recomputed value.
+  pub async fn reconcile_karma(
+    State(state): State<SharedState>,
+    auth_session: AuthSession,
+    Query(query): Query<ReconcileKarmaQuery>,
+  ) -> ApiResult<Json<KarmaReconciliationResponse>> {
+    trace!("reconcile_karma called with: {query:?}");
+    let moderator = auth_session.get_assert_moderator_from_session()?;
+    let reconciliation = db::queries::karma::reconcile_karma(&state.pool, query.correct).await?;
+    info!(
+      "{} ran karma reconciliation {}: {} discrepancies, corrected: {}",
+      moderator.username, reconciliation.id, reconciliation.discrepancy_count, query.correct
+    );
+
+    Ok(Json(reconciliation.into()))
+  }
+
+  #[utoipa::path(
+      post,
+      path = "/admin/vote-flags/analyze",
+      responses(
+        (status = 401, description = "Not logged in"),
+        (status = 403, description = "Moderators only"),
+        (status = 200, body = [VoteFlagResponse]),
+      ),
+  )]
+  /// Moderators: analyze recent votes now, rather than waiting for the scheduled job, returning
+  /// any newly flagged patterns.
+  pub async fn analyze_votes(
+    State(state): State<SharedState>,
+    auth_session: AuthSession,
+  ) -> ApiResult<Json<Vec<VoteFlagResponse>>> {
+    let moderator = auth_session.get_assert_moderator_from_session()?;
+    let flags = super::analyze_votes(&state.pool, state.config.discount_flagged_votes).await?;
+    info!("{} ran vote analysis: {} new flags", moderator.username, flags.len());
+
+    Ok(Json(flags.into_iter().map(VoteFlagResponse::from).collect()))
+  }
+
+  #[utoipa::path(
+      post,
+      path = "/admin/items/{id}/restore",
+      params( ("id" = String, Path, example = Ulid::new) ),
+      responses(
+        (status = 401, description = "Not logged in"),
+        (status = 403, description = "Moderators only"),
+        (status = 404, description = "Deleted item not found"),
+        (status = 422, description = "Invalid id"),
+        (status = 200, body = Item),
+      ),
+  )]
+  /// Moderators: restore a deleted item, and the karma its author lost with it.
+  pub async fn restore_item(
+    State(state): State<SharedState>,
+    auth_session: AuthSession,
+    Path(id): Path<Ulid>,
+  ) -> ApiResult<Json<Item>> {
+    id.validate(&())?;
+    let moderator = auth_session.get_assert_moderator_from_session()?;
+    db::queries::items::restore_item(&state.pool, &id).await?;
+    info!("{} restored item {id}", moderator.username);
+    let item = db::queries::items::get_assert_item(&state.pool, &id).await?;
+
+    Ok(Json(item))
+  }
+
+  #[utoipa::path(
+      post,
+      path = "/admin/comments/{id}/restore",
+      params( ("id" = String, Path, example = Ulid::new) ),
+      responses(
+        (status = 401, description = "Not logged in"),
+        (status = 403, description = "Moderators only"),
+        (status = 404, description = "Deleted comment not found"),
+        (status = 422, description = "Invalid id"),
+        (status = 200, description = "Success"),
+      ),
+  )]
+  /// Moderators: restore a deleted comment, and the karma its author lost with it.
+  pub async fn restore_comment(
+    State(state): State<SharedState>,
+    auth_session: AuthSession,
+    Path(id): Path<Ulid>,
+  ) -> ApiResult<StatusCode> {
+    id.validate(&())?;
+    let moderator = auth_session.get_assert_moderator_from_session()?;
+    db::queries::comments::restore_comment(&state.pool, &id).await?;
+    info!("{} restored comment {id}", moderator.username);
+
+    Ok(StatusCode::OK)
+  }
+}
+
+pub(super) mod put {
+  use super::*;
+
+  #[utoipa::path(
+      put,
+      path = "/admin/vote-flags/{id}",
+      params( ("id" = String, Path, example = Ulid::new) ),
+      request_body = ReviewVoteFlagPayload,
+      responses(
+        (status = 400, description = "Invalid verdict"),
+        (status = 401, description = "Not logged in"),
+        (status = 403, description = "Moderators only"),
+        (status = 404, description = "Vote flag not found"),
+        (status = 422, description = "Invalid id"),
+        (status = 200, body = VoteFlagResponse),
+      ),
+  )]
+  /// Moderators: confirm or dismiss a vote flag, and set whether its votes count.
+  pub async fn review_vote_flag(
+    State(state): State<SharedState>,
+    auth_session: AuthSession,
+    Path(id): Path<Ulid>,
+    Json(payload): Json<ReviewVoteFlagPayload>,
+  ) -> ApiResult<Json<VoteFlagResponse>> {
+    id.validate(&())?;
+    let moderator = auth_session.get_assert_moderator_from_session()?;
+    match payload {
+      ReviewVoteFlagPayload { status: VoteFlagStatus::Pending, .. } =>
+        return Err(ApiError::BadRequest("a review must confirm or dismiss".into())),
+      ReviewVoteFlagPayload { status: VoteFlagStatus::Dismissed, discount: true } =>
+        return Err(ApiError::BadRequest("only confirmed flags may be discounted".into())),
+      _ => {},
+    }
+
+    db::queries::vote_flags::review_vote_flag(
+      &state.pool,
+      &id,
+      &moderator.username,
+      payload.status,
+      payload.discount,
+    )
+    .await?;
+    let flag = db::queries::vote_flags::get_assert_vote_flag(&state.pool, &id).await?;
+
+    Ok(Json(flag.into()))
+  }
+}
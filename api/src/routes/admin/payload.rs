@@ -0,0 +1,33 @@
+use super::*;
+
+/// Query for `POST /admin/karma/reconcile`
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, IntoParams)]
+pub struct ReconcileKarmaQuery {
+  /// Correct any drift found, rather than only reporting it
+  #[serde(default)]
+  pub correct: bool,
+}
+
+/// Query for `GET /admin/vote-flags`
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, IntoParams)]
+pub struct VoteFlagsQuery {
+  /// Only flags with this status; defaults to all
+  pub status: Option<VoteFlagStatus>,
+}
+
+/// A moderator's verdict on a vote flag.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[schema(default = ReviewVoteFlagPayload::default, example=ReviewVoteFlagPayload::default)]
+pub struct ReviewVoteFlagPayload {
+  /// `confirmed` or `dismissed`
+  pub status:   VoteFlagStatus,
+  /// Whether the flagged votes stop counting. Only confirmed flags may be discounted; dismissing
+  /// a discounted flag restores its votes.
+  #[serde(default)]
+  pub discount: bool,
+}
+
+impl ReviewVoteFlagPayload {
+  pub fn new(status: VoteFlagStatus, discount: bool) -> Self { Self { status, discount } }
+}
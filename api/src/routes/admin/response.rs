@@ -0,0 +1,123 @@
+use super::*;
+
+/// A run of the karma reconciliation job.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, Default)]
+#[serde(rename_all = "camelCase")]
+#[schema(default = KarmaReconciliationResponse::default, example=KarmaReconciliationResponse::default)]
+pub struct KarmaReconciliationResponse {
+  pub id:                Ulid,
+  /// whether drift found was corrected, or only reported
+  pub corrected:         bool,
+  pub users_checked:     i32,
+  pub discrepancy_count: i32,
+  pub created:           Timestamp,
+}
+
+impl From<KarmaReconciliation> for KarmaReconciliationResponse {
+  fn from(r: KarmaReconciliation) -> Self {
+    Self {
+      id:                r.id,
+      corrected:         r.corrected,
+      users_checked:     r.users_checked,
+      discrepancy_count: r.discrepancy_count,
+      created:           r.created,
+    }
+  }
+}
+
+/// A user whose karma had drifted, as found by a reconciliation run.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, Default)]
+#[serde(rename_all = "camelCase")]
+#[schema(default = KarmaDiscrepancyResponse::default, example=KarmaDiscrepancyResponse::default)]
+pub struct KarmaDiscrepancyResponse {
+  pub username: Username,
+  /// the user's karma when the job ran
+  pub stored:   i32,
+  /// one per item and comment, plus the net votes on them
+  pub expected: i32,
+  /// the sum of the user's karma ledger entries
+  pub ledger:   i32,
+}
+
+impl From<KarmaDiscrepancy> for KarmaDiscrepancyResponse {
+  fn from(d: KarmaDiscrepancy) -> Self {
+    Self { username: d.username, stored: d.stored, expected: d.expected, ledger: d.ledger }
+  }
+}
+
+/// A reconciliation run, and the discrepancies it found.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, Default)]
+#[serde(rename_all = "camelCase")]
+#[schema(default = KarmaReconciliationDetailResponse::default, example=KarmaReconciliationDetailResponse::default)]
+pub struct KarmaReconciliationDetailResponse {
+  pub reconciliation: KarmaReconciliationResponse,
+  pub discrepancies:  Vec<KarmaDiscrepancyResponse>,
+}
+
+/// A suspicious voting pattern in the review queue.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, Default)]
+#[serde(rename_all = "camelCase")]
+#[schema(default = VoteFlagResponse::default, example=VoteFlagResponse::default)]
+pub struct VoteFlagResponse {
+  pub id:              Ulid,
+  pub kind:            VoteFlagKind,
+  /// the voting accounts involved
+  pub usernames:       Vec<String>,
+  /// the author voted for, for new account votes
+  pub target_username: Option<String>,
+  /// the item voted on, for bursts
+  pub content_id:      Option<String>,
+  pub vote_ids:        Vec<String>,
+  pub status:          VoteFlagStatus,
+  /// whether the flagged votes have stopped counting
+  pub discounted:      bool,
+  pub created:         Timestamp,
+  pub reviewed_by:     Option<String>,
+  pub reviewed:        Option<Timestamp>,
+}
+
+impl From<VoteFlag> for VoteFlagResponse {
+  fn from(flag: VoteFlag) -> Self {
+    Self {
+      id:              flag.id,
+      kind:            flag.kind,
+      usernames:       flag.usernames,
+      target_username: flag.target_username,
+      content_id:      flag.content_id,
+      vote_ids:        flag.vote_ids,
+      status:          flag.status,
+      discounted:      flag.discounted,
+      created:         flag.created,
+      reviewed_by:     flag.reviewed_by,
+      reviewed:        flag.reviewed,
+    }
+  }
+}
+
+/// A deleted comment, as seen by moderators.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, Default)]
+#[serde(rename_all = "camelCase")]
+#[schema(default = DeletedCommentResponse::default, example=DeletedCommentResponse::default)]
+pub struct DeletedCommentResponse {
+  pub id:             Ulid,
+  pub username:       Username,
+  pub parent_item_id: Ulid,
+  pub comment_text:   CommentText,
+  pub points:         i32,
+  pub created:        Timestamp,
+  pub deleted_at:     Option<Timestamp>,
+}
+
+impl From<Comment> for DeletedCommentResponse {
+  fn from(c: Comment) -> Self {
+    Self {
+      id:             c.id,
+      username:       c.username,
+      parent_item_id: c.parent_item_id,
+      comment_text:   c.comment_text,
+      points:         c.points,
+      created:        c.created,
+      deleted_at:     c.deleted_at,
+    }
+  }
+}
@@ -0,0 +1,92 @@
+pub(super) mod payload;
+pub(super) mod response;
+
+use axum::{extract::State, routing, Json, Router};
+use garde::Validate;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, trace};
+use utoipa::ToSchema;
+
+pub use self::{payload::*, response::*};
+use super::SharedState;
+use crate::{
+  auth::{AuthSession, AuthenticationExt},
+  ApiError, ApiResult, Privilege, ANON_TOKENS_PER_DAY,
+};
+
+/// Router to be mounted at "/anon"
+pub(super) fn anon_router(state: SharedState) -> Router {
+  Router::new()
+    .route("/key", routing::get(get::get_anon_key))
+    .route("/tokens", routing::post(post::issue_anon_tokens))
+    .with_state(state)
+}
+
+pub(super) mod get {
+  use super::*;
+
+  #[utoipa::path(
+      get,
+      path = "/anon/key",
+      responses(
+        (status = 200, body = AnonKeyResponse),
+      ),
+  )]
+  /// Get the public key anonymous posting tokens are signed with.
+  pub async fn get_anon_key(State(state): State<SharedState>) -> ApiResult<Json<AnonKeyResponse>> {
+    Ok(Json(AnonKeyResponse {
+      public_key:     state.anon_tokens.public_key_pem()?,
+      tokens_per_day: ANON_TOKENS_PER_DAY,
+      minimum_karma:  state.config.privileges.karma(Privilege::AnonymousPosting),
+    }))
+  }
+}
+
+pub(super) mod post {
+  use super::*;
+
+  #[utoipa::path(
+      post,
+      path = "/anon/tokens",
+      request_body = IssueAnonTokensPayload,
+      responses(
+        (status = 400, description = "Invalid blinded message"),
+        (status = 401, description = "Not logged in"),
+        (status = 403, description = "Banned, not enough karma, or daily limit reached"),
+        (status = 422, description = "Invalid Payload"),
+        (status = 200, body = AnonTokensResponse),
+      ),
+  )]
+  /// Blindly sign anonymous posting tokens.
+  ///
+  /// Users with the `anonymous-posting` privilege may have a few tokens issued per day. Redeem a
+  /// token with `create_anonymous_item`.
+  pub async fn issue_anon_tokens(
+    State(state): State<SharedState>,
+    auth_session: AuthSession,
+    Json(payload): Json<IssueAnonTokensPayload>,
+  ) -> ApiResult<Json<AnonTokensResponse>> {
+    trace!("issue_anon_tokens called");
+    payload.validate(&())?;
+    let session_user = auth_session
+      .get_assert_user_with_privilege(&state.config.privileges, Privilege::AnonymousPosting)?;
+
+    // sign first, so that invalid messages don't use up the allowance
+    let blind_signatures = payload
+      .blinded_messages
+      .iter()
+      .map(|m| state.anon_tokens.blind_sign(m))
+      .collect::<ApiResult<Vec<_>>>()?;
+    let issued = db::queries::anon_tokens::reserve_anon_tokens(
+      &state.pool,
+      &session_user.username,
+      blind_signatures.len() as i32,
+      ANON_TOKENS_PER_DAY,
+    )
+    .await?
+    .ok_or(ApiError::ForbiddenLimitReached("anonymous tokens issued today".into()))?;
+    debug!("issued {} anonymous tokens to: {}", blind_signatures.len(), session_user.username);
+
+    Ok(Json(AnonTokensResponse { blind_signatures, remaining: ANON_TOKENS_PER_DAY - issued }))
+  }
+}
@@ -0,0 +1,15 @@
+use super::*;
+
+/// Blinded messages to be signed, one per token requested.
+#[derive(Debug, Default, Clone, Serialize, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[schema(default = IssueAnonTokensPayload::default, example=IssueAnonTokensPayload::default)]
+pub struct IssueAnonTokensPayload {
+  /// Hex encoded blinded messages, see `AnonTokenRequest`
+  #[garde(length(min = 1, max = ANON_TOKENS_PER_DAY as usize), inner(ascii, length(max = 1024)))]
+  pub blinded_messages: Vec<String>,
+}
+
+impl IssueAnonTokensPayload {
+  pub fn new(blinded_messages: Vec<String>) -> Self { Self { blinded_messages } }
+}
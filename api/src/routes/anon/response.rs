@@ -0,0 +1,23 @@
+use super::*;
+
+/// The key anonymous tokens are signed with, and who may have them issued.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, Default)]
+#[serde(rename_all = "camelCase")]
+#[schema(default = AnonKeyResponse::default, example=AnonKeyResponse::default)]
+pub struct AnonKeyResponse {
+  /// PEM encoded RSA public key, to blind messages with
+  pub public_key:     String,
+  pub tokens_per_day: i32,
+  pub minimum_karma:  i32,
+}
+
+/// Blind signatures, in the order of the blinded messages submitted.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, Default)]
+#[serde(rename_all = "camelCase")]
+#[schema(default = AnonTokensResponse::default, example=AnonTokensResponse::default)]
+pub struct AnonTokensResponse {
+  /// Hex encoded blind signatures
+  pub blind_signatures: Vec<String>,
+  /// Tokens that may still be issued today
+  pub remaining:        i32,
+}
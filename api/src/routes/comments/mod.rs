@@ -21,15 +21,295 @@ use futures::{select, FutureExt};
 use garde::Validate;
 pub use payload::*;
 pub use response::*;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use tokio::spawn;
 use uuid::Uuid;
 
 use super::SharedState;
 use crate::{error::ApiError, ApiResult, DbPool};
 
-// // todo
-// pub fn comments_router(state: SharedState) -> Router { Router::new().with_state(state) }
+/// Router to be mounted at "/comments"
+pub(super) fn comments_router(state: SharedState) -> Router {
+  Router::new()
+    .route("/", axum::routing::post(post::create_comment))
+    .route("/anonymous", axum::routing::post(post::create_anonymous_comment))
+    .route("/edit-comment", axum::routing::put(put::edit_comment))
+    .route("/delete-comment/:id", axum::routing::delete(delete::delete_comment))
+    .route("/:id/revisions", axum::routing::get(get::get_comment_revisions))
+    .route("/:id/revisions/diff", axum::routing::get(get::get_comment_revision_diff))
+    .with_state(state)
+}
+
+pub(super) mod get {
+  use axum::extract::Query;
+  use db::Ulid;
+  use tracing::debug;
+
+  use super::*;
+  use crate::{
+    auth::AuthSession,
+    revisions::{
+      assert_may_view_revisions, RevisionDiffQuery, RevisionDiffResponse, RevisionsResponse,
+    },
+  };
+
+  #[utoipa::path(
+    get,
+    path = "/comments/{id}/revisions",
+    params( ("id" = String, Path, example = Ulid::new) ),
+    responses( (status = 401, description = "Unauthorized"),
+               (status = 403, description = "Moderators and the author only"),
+               (status = 404, description = "Comment not found"),
+               (status = 200, description = "Success", body = RevisionsResponse) ),
+    )]
+  /// Get every revision of a comment, oldest first. Moderators and the comment's author only.
+  pub async fn get_comment_revisions(
+    State(state): State<SharedState>,
+    Path(id): Path<Ulid>,
+    auth_session: AuthSession,
+  ) -> ApiResult<Json<RevisionsResponse>> {
+    debug!("get_comment_revisions called with id: {id}");
+    let comment = queries::comments::get_assert_comment(&state.pool, &id).await?;
+    assert_may_view_revisions(&auth_session, &comment.username)?;
+    let revisions = queries::revisions::get_revisions(&state.pool, &id).await?;
+
+    Ok(Json(RevisionsResponse::new(id, revisions)))
+  }
+
+  #[utoipa::path(
+    get,
+    path = "/comments/{id}/revisions/diff",
+    params( ("id" = String, Path, example = Ulid::new),
+            RevisionDiffQuery ),
+    responses( (status = 400, description = "`from` is not before `to`"),
+               (status = 401, description = "Unauthorized"),
+               (status = 403, description = "Moderators and the author only"),
+               (status = 404, description = "Comment or revision not found"),
+               (status = 200, description = "Success", body = RevisionDiffResponse) ),
+    )]
+  /// Get a unified diff between two revisions of a comment, by default the latest edit.
+  /// Moderators and the comment's author only.
+  pub async fn get_comment_revision_diff(
+    State(state): State<SharedState>,
+    Path(id): Path<Ulid>,
+    Query(query): Query<RevisionDiffQuery>,
+    auth_session: AuthSession,
+  ) -> ApiResult<Json<RevisionDiffResponse>> {
+    debug!("get_comment_revision_diff called with id: {id}, query: {query:?}");
+    let comment = queries::comments::get_assert_comment(&state.pool, &id).await?;
+    assert_may_view_revisions(&auth_session, &comment.username)?;
+    let revisions = queries::revisions::get_revisions(&state.pool, &id).await?;
+
+    Ok(Json(RevisionDiffResponse::new(id, &revisions, query)?))
+  }
+}
+
+pub(super) mod post {
+  use db::Ulid;
+  use tracing::{debug, trace};
+
+  use super::*;
+  use crate::{
+    auth::{AuthSession, AuthenticationExt},
+    pow::PowAction,
+    utils::sanitize_text,
+  };
+
+  #[utoipa::path(
+    post,
+    path = "/comments",
+    request_body = CreateCommentPayload,
+    responses( (status = 400, description = "Parent is dead, or not on the item"),
+               (status = 401, description = "Unauthorized"),
+               (status = 403, description = "ForbiddenBanned"),
+               (status = 403, description = "Invalid proof of work"),
+               (status = 404, description = "Item or parent comment not found"),
+               (status = 422, description = "Invalid Payload"),
+               (status = 200, body = Ulid) ),
+    )]
+  /// Comment on an item, or reply to a comment on it. The user must be logged in.
+  /// - validate payload, and proof of work if required
+  /// - sanitize the text, linking `@mentions` of existing users
+  /// - create the comment
+  /// - increment user karma, the item's comment count, and the parent comment's children count
+  /// - notify the author of the item or comment replied to, and any users `@mentioned`
+  /// - return the comment's id
+  pub async fn create_comment(
+    State(state): State<SharedState>,
+    auth_session: AuthSession,
+    Json(payload): Json<CreateCommentPayload>,
+  ) -> ApiResult<Json<Ulid>> {
+    debug!("create_comment called with payload: {payload:?}");
+    payload.validate(&())?;
+    let user = auth_session.get_assert_user_from_session()?;
+    state.assert_proof_of_work(payload.pow.as_ref(), PowAction::Comment, Some(&user))?;
+    let comment = new_comment(&state.pool, user.username, payload).await?;
+    queries::comments::create_comment(&state.pool, &comment).await?;
+
+    Ok(Json(comment.id))
+  }
+
+  #[utoipa::path(
+    post,
+    path = "/comments/anonymous",
+    request_body = CreateAnonymousCommentPayload,
+    responses( (status = 400, description = "Parent is dead, or not on the item"),
+               (status = 401, description = "Invalid token"),
+               (status = 404, description = "Item or parent comment not found"),
+               (status = 409, description = "Token already spent"),
+               (status = 422, description = "Invalid Payload"),
+               (status = 200, body = Ulid) ),
+    )]
+  /// Comment as `[anonymous]`, redeeming an anonymous posting token, see `issue_anon_tokens`.
+  ///
+  /// As with `create_anonymous_item`, the session is ignored, and the token stands in for the
+  /// proof of work.
+  pub async fn create_anonymous_comment(
+    State(state): State<SharedState>,
+    Json(payload): Json<CreateAnonymousCommentPayload>,
+  ) -> ApiResult<Json<Ulid>> {
+    trace!("create_anonymous_comment called");
+    payload.validate(&())?;
+    let token_hash = state.anon_tokens.verify(&payload.token)?;
+    let comment = new_comment(&state.pool, db::ANONYMOUS_USERNAME.into(), payload.comment).await?;
+    queries::anon_tokens::create_anonymous_comment(&state.pool, &comment, &token_hash)
+      .await
+      .map_err(|e| match e {
+        DbError::UniqueViolation(_) => ApiError::UniqueViolation("token already spent".into()),
+        e => e.into(),
+      })?;
+
+    Ok(Json(comment.id))
+  }
+
+  /// Build the comment `payload` describes, checking that what it replies to may be replied to,
+  /// and sanitizing its text.
+  pub(super) async fn new_comment(
+    pool: &DbPool,
+    username: Username,
+    payload: CreateCommentPayload,
+  ) -> ApiResult<Comment> {
+    let mentions = queries::users::resolve_mentions(pool, &payload.text.0).await?;
+    let text = CommentText::from(sanitize_text(&payload.text.0, &mentions).as_str());
+    // sanitizing may have shortened it
+    text.validate(&())?;
+
+    let item = queries::items::get_assert_item(pool, &payload.parent_item_id).await?;
+    if item.is_deleted() {
+      return Err(ApiError::DbEntryNotFound("item".into()));
+    }
+    if item.dead {
+      return Err(ApiError::BadRequest("dead items may not be commented on".into()));
+    }
+
+    let Some(parent_comment_id) = payload.parent_comment_id else {
+      return Ok(Comment::new(username, &item.id, &item.title, true, None, None, text, false));
+    };
+    let mut parent = queries::comments::get_assert_comment(pool, &parent_comment_id).await?;
+    if parent.is_deleted() {
+      return Err(ApiError::DbEntryNotFound("parent comment".into()));
+    }
+    if parent.parent_item_id != item.id {
+      return Err(ApiError::BadRequest("parent comment is not on the item".into()));
+    }
+    if parent.dead {
+      return Err(ApiError::BadRequest("dead comments may not be replied to".into()));
+    }
+    Ok(parent.create_child_comment(username, text, false))
+  }
+}
+
+pub(super) mod put {
+  use tracing::debug;
+
+  use super::*;
+  use crate::{
+    auth::{AuthSession, AuthenticationExt},
+    utils::sanitize_text,
+  };
+
+  #[utoipa::path(
+    put,
+    path = "/comments/edit-comment",
+    request_body = EditCommentPayload,
+    responses( (status = 401, description = "Unauthorized"),
+               (status = 403, description = "Forbidden"),
+               (status = 403, description = "Forbidden not editable"),
+               (status = 404, description = "Comment not found"),
+               (status = 422, description = "Invalid Payload"),
+               (status = 200, description = "Success") ),
+    )]
+  /// Edit a comment's text. The edit is recorded as a revision.
+  /// - only the author may edit, and only comments without replies, less than an hour old
+  /// - the text is sanitized, linking `@mentions` of existing users
+  pub async fn edit_comment(
+    State(state): State<SharedState>,
+    auth_session: AuthSession,
+    Json(payload): Json<EditCommentPayload>,
+  ) -> ApiResult<StatusCode> {
+    debug!("edit_comment called with payload: {payload:?}");
+    payload.validate(&())?;
+    let comment = queries::comments::get_assert_comment(&state.pool, &payload.id).await?;
+    let session_user = auth_session.get_assert_user_from_session_assert_match(&comment.username)?;
+    if comment.is_deleted() {
+      return Err(ApiError::ForbiddenNotEditable("deleted".into()));
+    }
+    if !comment.is_editable() {
+      return Err(ApiError::ForbiddenNotEditable("has replies, or expired".into()));
+    }
+
+    let mentions = queries::users::resolve_mentions(&state.pool, &payload.text.0).await?;
+    let text = CommentText::from(sanitize_text(&payload.text.0, &mentions).as_str());
+    // sanitizing may have shortened it
+    text.validate(&())?;
+    queries::comments::edit_comment(&state.pool, &comment.id, &text, &session_user.username)
+      .await?;
+
+    Ok(StatusCode::OK)
+  }
+}
+
+pub(super) mod delete {
+  use db::Ulid;
+  use tracing::debug;
+
+  use super::*;
+  use crate::auth::{AuthSession, AuthenticationExt};
+
+  #[utoipa::path(
+    delete,
+    path = "/comments/delete-comment/{id}",
+    params( ("id" = String, Path, example = Ulid::new) ),
+    responses( (status = 401, description = "Unauthorized"),
+               (status = 403, description = "Forbidden not editable"),
+               (status = 404, description = "Comment not found"),
+               (status = 422, description = "Invalid id"),
+               (status = 200, description = "Success") ),
+    )]
+  /// Delete a comment. Like a deleted item, it stays in its thread, shown as `[deleted]`, and may
+  /// be restored by a moderator.
+  ///
+  /// As with editing, only comments without replies, less than an hour old, may be deleted.
+  pub async fn delete_comment(
+    State(state): State<SharedState>,
+    auth_session: AuthSession,
+    Path(id): Path<Ulid>,
+  ) -> ApiResult<StatusCode> {
+    debug!("delete_comment called with id: {id:?}");
+    id.validate(&())?;
+    let comment = queries::comments::get_assert_comment(&state.pool, &id).await?;
+    auth_session.get_assert_user_from_session_assert_match(&comment.username)?;
+    if comment.is_deleted() {
+      return Err(ApiError::ForbiddenNotEditable("deleted".into()));
+    }
+    if !comment.is_editable() {
+      return Err(ApiError::ForbiddenNotEditable("has replies, or expired".into()));
+    }
+    queries::comments::delete_comment(&state.pool, &comment).await?;
+
+    Ok(StatusCode::OK)
+  }
+}
 
 // /// if user is signed in, check if the user has voted on this comment.
 // /// If no comment exists, return Not Found.
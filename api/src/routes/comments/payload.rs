@@ -1,69 +1,70 @@
-//! Provided methods:
-//! - `create_comment`
-//! - `get_comment`
-//! - `update_comment_vote`
-//! - `update_comment_favorite`
-//! - `update_comment_text`
-//! - `delete_comment`
-
 use db::Ulid;
+use utoipa::ToSchema;
 
 use super::*;
+use crate::{anon::AnonToken, pow::PowSolution};
 
-// corresponding to `add_new_comment` in API
-#[derive(Debug, Deserialize, Validate)]
-pub struct CommentPayload {
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[schema(default = CreateCommentPayload::default, example=CreateCommentPayload::default)]
+pub struct CreateCommentPayload {
+  /// The item commented on
   #[garde(dive)]
-  pub username:          Username,
-  #[garde(skip)]
   pub parent_item_id:    Ulid,
+  /// The comment replied to, if not commenting on the item directly
   #[garde(dive)]
-  pub parent_item_title: Title,
-  #[garde(skip)]
-  pub is_parent:         bool,
-  #[garde(skip)]
-  pub root_comment_id:   Option<Ulid>,
-  #[garde(skip)]
+  #[serde(default)]
   pub parent_comment_id: Option<Ulid>,
   #[garde(dive)]
   pub text:              CommentText,
+  /// A solved `comment` challenge, see `get_challenge`
   #[garde(skip)]
-  pub dead:              bool,
+  #[serde(default)]
+  pub pow:               Option<PowSolution>,
 }
 
-impl TryFrom<CommentPayload> for Comment {
-  type Error = ApiError;
+impl CreateCommentPayload {
+  /// convenience method for testing
+  pub fn new(parent_item_id: &Ulid, parent_comment_id: Option<&Ulid>, text: &str) -> Self {
+    Self {
+      parent_item_id:    parent_item_id.clone(),
+      parent_comment_id: parent_comment_id.cloned(),
+      text:              text.into(),
+      pow:               None,
+    }
+  }
+
+  /// convenience method for testing
+  pub fn with_pow(self, pow: PowSolution) -> Self { Self { pow: Some(pow), ..self } }
+}
 
-  fn try_from(payload: CommentPayload) -> ApiResult<Self> {
-    payload.validate(&())?;
+/// A comment to post anonymously, and the token to redeem for it.
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[schema(default = CreateAnonymousCommentPayload::default, example=CreateAnonymousCommentPayload::default)]
+pub struct CreateAnonymousCommentPayload {
+  #[garde(dive)]
+  pub comment: CreateCommentPayload,
+  #[garde(skip)]
+  pub token:   AnonToken,
+}
 
-    let comment = Comment::new(
-      payload.username,
-      &payload.parent_item_id,
-      &payload.parent_item_title,
-      payload.is_parent,
-      payload.root_comment_id,
-      payload.parent_comment_id.map(|id| id.to_string()),
-      payload.text,
-      payload.dead,
-    );
+impl CreateAnonymousCommentPayload {
+  pub fn new(comment: CreateCommentPayload, token: AnonToken) -> Self { Self { comment, token } }
+}
 
-    Ok(comment)
-  }
+/// A payload for editing a comment's text.
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[schema(default = EditCommentPayload::default, example=EditCommentPayload::default)]
+pub struct EditCommentPayload {
+  #[garde(dive)]
+  pub id:   Ulid,
+  #[garde(dive)]
+  pub text: CommentText,
 }
 
-impl CommentPayload {
-  /// Assume Comment Payload has already been validated.
-  fn into_comment(self) -> Comment {
-    Comment::new(
-      self.username,
-      &self.parent_item_id,
-      &self.parent_item_title,
-      self.is_parent,
-      self.root_comment_id,
-      self.parent_comment_id.map(|id| id.to_string()),
-      self.text,
-      self.dead,
-    )
-  }
+impl EditCommentPayload {
+  /// convenience method for testing
+  pub fn new(id: &Ulid, text: &str) -> Self { Self { id: id.clone(), text: text.into() } }
 }
@@ -6,6 +6,9 @@ use db::{
 };
 
 use super::*;
+use crate::revisions::{
+  assert_may_view_revisions, RevisionDiffQuery, RevisionDiffResponse, RevisionsResponse,
+};
 
 #[utoipa::path(
   get,
@@ -19,6 +22,7 @@ use super::*;
 /// Get item:
 /// - validate page and item id
 /// - If user is logged out: get and return the item and the `page` of comments
+/// - Deleted items and comments are shown as `[deleted]`, except to moderators
 ///
 /// User is logged in: (todo: blocked by comments upvotes, favorites, and items)
 /// - get the user's votes, favorites, and comment votes for the item
@@ -49,8 +53,22 @@ pub async fn get_item(
     db::queries::comments::get_comments_page(&state.pool, &id, page, show_dead),
   )?;
 
+  // only moderators see deleted content
+  let (item, comments_page) = match session_user.as_ref().is_some_and(|u| u.is_moderator) {
+    true => (item, comments_page),
+    false => (item.redacted(), comments_page.into_iter().map(Comment::redacted).collect()),
+  };
+
   Ok(Json(match session_user {
-    None => GetItemResponse::new(item, comments_page, total_comments, None, None, None)?,
+    None => GetItemResponse::new(
+      item,
+      comments_page,
+      total_comments,
+      None,
+      None,
+      None,
+      &state.config.privileges,
+    )?,
     Some(user) => {
       // get the user-related item-votes, favorites, and comment-votes for this item
       let (vote, favorite, user_comment_votes): (
@@ -75,6 +93,7 @@ pub async fn get_item(
         Some(item_metadata),
         Some(user),
         Some(user_comment_votes),
+        &state.config.privileges,
       )?
     },
   }))
@@ -127,3 +146,53 @@ pub async fn get_items_by_page(
     },
   }))
 }
+
+#[utoipa::path(
+  get,
+  path = "/items/{id}/revisions",
+  params( ("id" = String, Path, example = Ulid::new) ),
+  responses( (status = 401, description = "Unauthorized"),
+             (status = 403, description = "Moderators and the author only"),
+             (status = 404, description = "Item not found"),
+             (status = 200, description = "Success", body = RevisionsResponse) ),
+  )]
+/// Get every revision of an item, oldest first. Moderators and the item's author only.
+pub async fn get_item_revisions(
+  State(state): State<SharedState>,
+  Path(id): Path<Ulid>,
+  auth_session: AuthSession,
+) -> ApiResult<Json<RevisionsResponse>> {
+  debug!("get_item_revisions called with id: {id}");
+  let item = queries::items::get_assert_item(&state.pool, &id).await?;
+  assert_may_view_revisions(&auth_session, &item.username)?;
+  let revisions = queries::revisions::get_revisions(&state.pool, &id).await?;
+
+  Ok(Json(RevisionsResponse::new(id, revisions)))
+}
+
+#[utoipa::path(
+  get,
+  path = "/items/{id}/revisions/diff",
+  params( ("id" = String, Path, example = Ulid::new),
+          RevisionDiffQuery ),
+  responses( (status = 400, description = "`from` is not before `to`"),
+             (status = 401, description = "Unauthorized"),
+             (status = 403, description = "Moderators and the author only"),
+             (status = 404, description = "Item or revision not found"),
+             (status = 200, description = "Success", body = RevisionDiffResponse) ),
+  )]
+/// Get a unified diff between two revisions of an item, by default the latest edit. Moderators
+/// and the item's author only.
+pub async fn get_item_revision_diff(
+  State(state): State<SharedState>,
+  Path(id): Path<Ulid>,
+  Query(query): Query<RevisionDiffQuery>,
+  auth_session: AuthSession,
+) -> ApiResult<Json<RevisionDiffResponse>> {
+  debug!("get_item_revision_diff called with id: {id}, query: {query:?}");
+  let item = queries::items::get_assert_item(&state.pool, &id).await?;
+  assert_may_view_revisions(&auth_session, &item.username)?;
+  let revisions = queries::revisions::get_revisions(&state.pool, &id).await?;
+
+  Ok(Json(RevisionDiffResponse::new(id, &revisions, query)?))
+}
@@ -17,7 +17,7 @@ use db::{
     user::User,
     user_vote::VoteState,
   },
-  queries, Domain, Page, Text, TextOrUrl, Timestamp, Title, Url, Username,
+  queries, Domain, ItemContent, Page, Text, Timestamp, Title, Url, Username,
 };
 use garde::Validate;
 use serde::{Deserialize, Serialize};
@@ -28,17 +28,24 @@ use utoipa::ToSchema;
 pub use self::{payload::*, response::*};
 use super::SharedState;
 use crate::{
+  anon::AnonToken,
   auth::{AuthSession, AuthenticationExt},
+  config::TitlePrefixes,
   error::ApiError,
-  ApiResult, COMMENTS_PER_PAGE,
+  pow::{PowAction, PowSolution},
+  utils::{sanitize_text, sanitize_title},
+  ApiResult, Privilege, COMMENTS_PER_PAGE,
 };
 
 /// Router to be mounted at "/items"
 pub(super) fn items_router(state: SharedState) -> Router {
   Router::new()
     .route("/:id", routing::get(get::get_item))
+    .route("/:id/revisions", routing::get(get::get_item_revisions))
+    .route("/:id/revisions/diff", routing::get(get::get_item_revision_diff))
     .route("/get-items-by-page/:item_kind", routing::get(get::get_items_by_page))
     .route("/", routing::post(post::create_item))
+    .route("/anonymous", routing::post(post::create_anonymous_item))
     .route("/vote", routing::post(post::vote_item))
     .route("/favorite", routing::post(post::favorite_item))
     .route("/edit-item", routing::put(put::edit_item))
@@ -54,7 +61,8 @@ pub(super) mod delete {
 
   use super::*;
 
-  /// Delete an item
+  /// Delete an item. The item stays resolvable, shown as `[deleted]`, and may be restored by a
+  /// moderator.
   ///
   /// https://github.com/thor314/zkhn/blob/main/rest-api/routes/items/api.js#L559
   /// https://github.com/thor314/zkhn/blob/main/rest-api/routes/items/index.js#L262
@@ -9,34 +9,85 @@ use super::*;
 #[schema(default = CreateItemPayload::default, example=CreateItemPayload::default)]
 pub struct CreateItemPayload {
   #[garde(dive)]
-  pub title:           Title,
+  pub title:     Title,
+  /// Inferred from the title's prefix if not given; if given, it must match
   #[garde(skip)]
-  item_type:           ItemType,
+  #[serde(default)]
+  item_type:     Option<ItemType>,
+  /// A url, text, or both
   #[garde(dive)]
-  text_or_url_content: TextOrUrl,
+  content:       ItemContent,
+  #[garde(skip)]
+  item_category: ItemCategory,
+  /// A solved `item` challenge, see `get_challenge`
   #[garde(skip)]
-  item_category:       ItemCategory,
+  #[serde(default)]
+  pub pow:       Option<PowSolution>,
 }
 
 impl CreateItemPayload {
-  pub async fn into_item(self, username: Username) -> Item {
-    Item::new(username, self.title, self.item_type, self.text_or_url_content, self.item_category)
+  /// Build the item, sanitizing its title and text, and linking `@mentions` of existing users.
+  ///
+  /// The item's type is inferred from the sanitized title, as stored, and checked against the type
+  /// given, if any.
+  pub async fn into_item(
+    self,
+    pool: &db::DbPool,
+    username: Username,
+    title_prefixes: &TitlePrefixes,
+  ) -> ApiResult<Item> {
+    let title = Title::from(sanitize_title(&self.title.0));
+    let text = match self.content.text {
+      Some(text) => {
+        let mentions = queries::users::resolve_mentions(pool, &text.0).await?;
+        Some(Text::from(sanitize_text(&text.0, &mentions)))
+      },
+      None => None,
+    };
+    let content = ItemContent::new(self.content.url, text);
+    // sanitizing may have shortened them
+    title.validate(&())?;
+    content.validate(&())?;
+    let item_type =
+      title_prefixes.assert_item_type(&title, self.item_type, content.url.is_some())?;
+
+    Ok(Item::new(username, title, item_type, content, self.item_category))
   }
 
   /// convenience method for testing
   pub fn new(
     title: &str,
-    item_type: ItemType,
-    is_text: bool,
-    text_or_url_content: TextOrUrl,
+    item_type: Option<ItemType>,
+    content: ItemContent,
     item_category: ItemCategory,
   ) -> ApiResult<Self> {
     let title = title.into();
 
-    let item_payload = Self { title, item_type, text_or_url_content, item_category };
+    let item_payload = Self { title, item_type, content, item_category, pow: None };
     item_payload.validate(&())?;
     Ok(item_payload)
   }
+
+  /// Whether the item has a link
+  pub fn is_url(&self) -> bool { self.content.url.is_some() }
+
+  /// convenience method for testing
+  pub fn with_pow(self, pow: PowSolution) -> Self { Self { pow: Some(pow), ..self } }
+}
+
+/// An item to post anonymously, and the token to redeem for it.
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[schema(default = CreateAnonymousItemPayload::default, example=CreateAnonymousItemPayload::default)]
+pub struct CreateAnonymousItemPayload {
+  #[garde(dive)]
+  pub item:  CreateItemPayload,
+  #[garde(skip)]
+  pub token: AnonToken,
+}
+
+impl CreateAnonymousItemPayload {
+  pub fn new(item: CreateItemPayload, token: AnonToken) -> Self { Self { item, token } }
 }
 
 /// A payload for voting on an item or comment
@@ -65,32 +116,35 @@ impl FavoritePayload {
   pub fn new(id: &Ulid, favorite: FavoriteStateEnum) -> Self { Self { id: id.clone(), favorite } }
 }
 
-/// A payload for editing an item
+/// A payload for editing an item. Only the fields given are changed.
 #[derive(Default, Debug, Clone, Serialize, Deserialize, ToSchema, Validate)]
 #[schema(default = EditItemPayload::default, example=EditItemPayload::default)]
 #[serde(rename_all = "camelCase")]
 pub struct EditItemPayload {
   #[garde(dive)]
-  pub id:        Ulid,
+  pub id:       Ulid,
+  /// The item's type is inferred again from a new title
   #[garde(dive)]
-  pub title:     Title,
+  pub title:    Option<Title>,
   #[garde(dive)]
-  pub text:      Text,
-  #[garde(skip)]
-  pub category:  ItemCategory,
+  pub text:     Option<Text>,
   #[garde(skip)]
-  pub item_type: ItemType,
+  pub category: Option<ItemCategory>,
 }
 
 impl EditItemPayload {
   pub fn new(
     id: &Ulid,
-    title: &str,
-    text: &str,
-    category: ItemCategory,
-    item_type: ItemType,
+    title: Option<&str>,
+    text: Option<&str>,
+    category: Option<ItemCategory>,
   ) -> Self {
-    Self { id: id.clone(), title: title.into(), text: text.into(), category, item_type }
+    Self { id: id.clone(), title: title.map(Title::from), text: text.map(Text::from), category }
+  }
+
+  /// Whether the payload changes nothing
+  pub fn is_empty(&self) -> bool {
+    self.title.is_none() && self.text.is_none() && self.category.is_none()
   }
 }
 
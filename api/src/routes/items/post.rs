@@ -8,15 +8,22 @@ use super::*;
   request_body = CreateItemPayload,
   responses(
     (status = 400, description = "Payload Parsing failed"),
+    (status = 400, description = "Title prefix does not match item type"),
+    (status = 400, description = "Ask items may not have a url"),
     (status = 401, description = "Unauthorized"),
     (status = 403, description = "ForbiddenBanned"),
+    (status = 403, description = "Invalid proof of work"),
+    (status = 403, description = "Insufficient karma to submit links"),
     (status = 422, description = "Invalid Payload"),
     // (status = 409, description = "Duplication Conflict"), - cannot occur, ulid generated on server
     (status = 200, body = Ulid),
   ),
   )]
-/// Create a new item. The user must be logged in to call this method.
-/// - validate payload
+/// Create a new item. The user must be logged in to call this method, and have the
+/// `submit-links` privilege to submit a url.
+/// - validate payload, and proof of work if required
+/// - sanitize the title and text, linking `@mentions` of existing users
+/// - infer the item's type from the sanitized title's prefix; `Ask` items may not have a url
 /// - create a new item
 /// - increment user karma
 /// - return the item's id
@@ -29,13 +36,56 @@ pub async fn create_item(
 ) -> ApiResult<Json<Ulid>> {
   debug!("create_item called with payload: {payload:?}");
   payload.validate(&())?;
-  let user = auth_session.get_assert_user_from_session()?;
-  let item = payload.into_item(user.username).await;
+  let user = if payload.is_url() {
+    auth_session.get_assert_user_with_privilege(&state.config.privileges, Privilege::SubmitLinks)?
+  } else {
+    auth_session.get_assert_user_from_session()?
+  };
+  state.assert_proof_of_work(payload.pow.as_ref(), PowAction::Item, Some(&user))?;
+  let item = payload.into_item(&state.pool, user.username, &state.config.title_prefixes).await?;
   queries::items::create_item(&state.pool, &item).await?;
 
   Ok(Json(item.id))
 }
 
+#[utoipa::path(
+  post,
+  path = "/items/anonymous",
+  request_body = CreateAnonymousItemPayload,
+  responses(
+    (status = 400, description = "Payload Parsing failed"),
+    (status = 400, description = "Title prefix does not match item type"),
+    (status = 400, description = "Ask items may not have a url"),
+    (status = 401, description = "Invalid token"),
+    (status = 409, description = "Token already spent"),
+    (status = 422, description = "Invalid Payload"),
+    (status = 200, body = Ulid),
+  ),
+  )]
+/// Create a new item as `[anonymous]`, redeeming an anonymous posting token, see
+/// `issue_anon_tokens`.
+///
+/// The session is deliberately ignored, so that nothing links the item to an account. The token
+/// stands in for the proof of work, as tokens are already rate limited.
+pub async fn create_anonymous_item(
+  State(state): State<SharedState>,
+  Json(payload): Json<CreateAnonymousItemPayload>,
+) -> ApiResult<Json<Ulid>> {
+  trace!("create_anonymous_item called");
+  payload.validate(&())?;
+  let token_hash = state.anon_tokens.verify(&payload.token)?;
+  let username = db::ANONYMOUS_USERNAME.into();
+  let item = payload.item.into_item(&state.pool, username, &state.config.title_prefixes).await?;
+  queries::anon_tokens::create_anonymous_item(&state.pool, &item, &token_hash).await.map_err(
+    |e| match e {
+      db::DbError::UniqueViolation(_) => ApiError::UniqueViolation("token already spent".into()),
+      e => e.into(),
+    },
+  )?;
+
+  Ok(Json(item.id))
+}
+
 #[utoipa::path(
   post,
   path = "/items/vote",
@@ -43,11 +93,12 @@ pub async fn create_item(
   responses(
     (status = 400, description = "Payload Parsing failed"),
     (status = 401, description = "Unauthorized"),
+    (status = 403, description = "Insufficient karma to downvote"),
     (status = 200, body = Ulid),
   ),
   )]
 /// Submit an {up,down,un}vote on an item:
-/// - get the user from the session store
+/// - get the user from the session store, who must have the `downvote` privilege to downvote
 /// - get the item from the database, and any previously existing vote on the item
 ///
 /// | State \\ Payload | Up     | Down |
@@ -71,7 +122,11 @@ pub async fn vote_item(
   Json(payload): Json<VotePayload>,
 ) -> ApiResult<Json<VoteState>> {
   debug!("vote_item called with payload: {payload:?}");
-  let user = auth_session.get_assert_user_from_session()?;
+  let user = match payload.vote_state {
+    VoteState::Downvote =>
+      auth_session.get_assert_user_with_privilege(&state.config.privileges, Privilege::Downvote)?,
+    _ => auth_session.get_assert_user_from_session()?,
+  };
   let item = queries::items::get_assert_item(&state.pool, &payload.content_id).await?;
   let vote_state =
     queries::user_votes::vote_item(&state.pool, &item.id, &user.username, payload.vote_state)
@@ -1,6 +1,8 @@
 use super::*;
 
-/// Edit an item's title, text, type, or category
+/// Edit an item's title, text, or category. Only the fields given are changed.
+/// - the title and text are sanitized
+/// - the item's type is inferred again from a new title, which may not mark a url item as `Ask`
 ///
 /// ref: https://github.com/thor314/zkhn/blob/main/rest-api/routes/items/api.js#L492
 /// ref: https://github.com/thor314/zkhn/blob/main/rest-api/routes/items/index.js#L212
@@ -9,6 +11,8 @@ use super::*;
   path = "/items/edit-item",
   request_body = EditItemPayload,
   responses(
+    (status = 400, description = "Nothing to edit"),
+    (status = 400, description = "Ask items may not have a url"),
     (status = 401, description = "Unauthorized"),
     (status = 403, description = "Forbidden"),
     (status = 403, description = "Forbidden not editable"),
@@ -23,15 +27,39 @@ pub async fn edit_item(
 ) -> ApiResult<StatusCode> {
   debug!("edit_item called with payload: {payload:?}");
   payload.validate(&())?;
+  if payload.is_empty() {
+    return Err(ApiError::BadRequest("nothing to edit".into()));
+  }
   let item = queries::items::get_assert_item(&state.pool, &payload.id).await?;
   item.assert_is_editable(&state.pool).await?;
-  let _session_user = auth_session.get_assert_user_from_session_assert_match(&item.username)?;
+  let session_user = auth_session.get_assert_user_from_session_assert_match(&item.username)?;
 
-  // payload.sanitize() // backlog(sanitize) - sanitize title and text
-  // if title changed, we may need to change the item type; see routes/utils.js/getitemtype
+  let title = payload.title.map(|title| Title::from(sanitize_title(&title.0)));
+  let text = match payload.text {
+    Some(text) => {
+      let mentions = queries::users::resolve_mentions(&state.pool, &text.0).await?;
+      Some(Text::from(sanitize_text(&text.0, &mentions)))
+    },
+    None => None,
+  };
+  // sanitizing may have shortened them
+  title.validate(&())?;
+  text.validate(&())?;
+  let item_type = match &title {
+    Some(title) => state.config.title_prefixes.assert_item_type(title, None, item.url.is_some())?,
+    None => item.item_type,
+  };
 
-  queries::items::edit_item(&state.pool, &item.id, &payload.title, payload.category, &payload.text)
-    .await?;
+  queries::items::edit_item(
+    &state.pool,
+    &item.id,
+    title.as_ref(),
+    payload.category,
+    text.as_ref(),
+    item_type,
+    &session_user.username,
+  )
+  .await?;
 
   // backlog(search) search::editItem(itemId, newItemTitle, newItemText, newItemCategory).await?;
 
@@ -7,7 +7,7 @@ use db::{
 };
 
 use super::*;
-use crate::AuthUserResponseInternal;
+use crate::{AuthUserResponseInternal, PrivilegeTable};
 
 #[derive(Debug, Serialize, Deserialize, ToSchema, Default)]
 #[schema(default = GetItemResponse::default, example=GetItemResponse::default)]
@@ -29,8 +29,9 @@ impl GetItemResponse {
     authenticated_item_data: Option<GetItemResponseAuthenticated>,
     session_user: Option<User>,
     mut user_comment_votes: Option<Vec<UserVote>>,
+    privileges: &PrivilegeTable,
   ) -> ApiResult<Self> {
-    let auth_user = AuthUserResponseInternal::new(session_user);
+    let auth_user = AuthUserResponseInternal::new(session_user, privileges);
     let with_comments = match user_comment_votes {
       Some(votes) =>
         Some(WithCommentsResponse::new(comments, page, authenticated_item_data, votes.clone())?),
@@ -126,11 +127,11 @@ impl GetItemCommentResponse {
 pub struct GetItemsPageResponse {
   /// The items for this page
   // todo: should these items be transformed?
-  items: Vec<RankedItemResponse>,
+  pub items: Vec<RankedItemResponse>,
   /// whether there are more items after the page returned
-  is_more: bool,
+  pub is_more: bool,
   /// total number of items matching query
-  count:   usize,
+  pub count:   usize,
 }
 impl GetItemsPageResponse {
   pub fn new(
@@ -0,0 +1,43 @@
+use axum::{
+  extract::{Query, State},
+  routing, Json, Router,
+};
+use tracing::trace;
+
+use super::SharedState;
+use crate::{
+  leaders::{LeadersQuery, LeadersResponse},
+  ApiResult,
+};
+
+/// Router to be mounted at "/leaders"
+pub(super) fn leaders_router(state: SharedState) -> Router {
+  Router::new().route("/", routing::get(get::get_leaders)).with_state(state)
+}
+
+pub(super) mod get {
+  use super::*;
+
+  #[utoipa::path(
+      get,
+      path = "/leaders",
+      params( LeadersQuery ),
+      responses(
+        (status = 400, description = "Invalid board"),
+        (status = 200, body = LeadersResponse),
+      ),
+  )]
+  /// Get a leaderboard: all time by `karma`, or over the last 30 days by `karma-gained` or by
+  /// well-received `submissions`.
+  ///
+  /// Moderators and banned users are not listed. Boards are cached for a few minutes.
+  pub async fn get_leaders(
+    State(state): State<SharedState>,
+    Query(query): Query<LeadersQuery>,
+  ) -> ApiResult<Json<LeadersResponse>> {
+    trace!("get_leaders called with: {query:?}");
+    let leaders = state.leaders.get(&state.pool, query.board).await?;
+
+    Ok(Json(leaders))
+  }
+}
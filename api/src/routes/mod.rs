@@ -1,27 +1,45 @@
 use axum::{routing, Json, Router};
 use axum_login::AuthManagerLayer;
-use db::DbPool;
+use db::{models::user::User, DbPool};
 use tower_sessions_sqlx_store::PostgresStore;
 use tracing::debug;
 
-use self::{openapi::docs_router, users::users_router};
-use crate::{auth::MyAuthLayer, routes::items::items_router};
+use self::{
+  admin::admin_router, anon::anon_router, comments::comments_router, leaders::leaders_router,
+  openapi::docs_router, pow::pow_router, siwe::siwe_router, users::users_router,
+  webhooks::webhooks_router,
+};
+use crate::{
+  anon::AnonTokenSigner,
+  auth::{LoginThrottle, MyAuthLayer},
+  leaders::LeaderboardCache,
+  pow::{PowAction, PowSolution, ProofOfWork},
+  routes::items::items_router,
+  sessions::{touch_session, SessionActivity},
+  ApiConfig, ApiError, ApiResult,
+};
 
 // pub mod so that payloads and responses can be accessed by integration tests
+pub mod admin;
+pub mod anon;
 pub mod comments;
 pub mod items;
+pub mod leaders;
 pub mod openapi;
+pub mod pow;
+pub mod siwe;
 pub mod user_votes;
 pub mod users;
+pub mod webhooks;
 
 async fn health() -> &'static str { "ok" }
 
 // pub(crate) fn routes(pool: DbPool, auth_layer: MyAuthLayer) -> Router {
-pub(crate) fn routes(pool: DbPool) -> Router {
+pub(crate) fn routes(pool: DbPool, config: ApiConfig) -> ApiResult<Router> {
   debug!("Initializing routes...");
-  let state = SharedState::new(pool);
+  let state = SharedState::new(pool, config)?;
 
-  Router::new()
+  let router = Router::new()
     //// login protected routes go above the login route_layer
     // .route_layer(login_required!(AuthBackend, login_url = "/login"))
     //// unprotected routes (like "/login") go below the login route_layer
@@ -29,15 +47,62 @@ pub(crate) fn routes(pool: DbPool) -> Router {
     .nest("/docs", docs_router())
     .nest("/users", users_router(state.clone()))
     .nest("/items", items_router(state.clone()))
+    .nest("/comments", comments_router(state.clone()))
+    .nest("/webhooks", webhooks_router(state.clone()))
+    .nest("/pow", pow_router(state.clone()))
+    .nest("/auth/siwe", siwe_router(state.clone()))
+    .nest("/anon", anon_router(state.clone()))
+    .nest("/admin", admin_router(state.clone()))
+    .nest("/leaders", leaders_router(state.clone()))
+    .layer(axum::middleware::from_fn_with_state(state.clone(), touch_session));
+  Ok(router)
 }
 
 /// shared state for handlers to access via the State Extractor
 #[derive(Clone)]
 pub struct SharedState {
   /// Access to the database
-  pub pool: DbPool,
+  pub pool:             DbPool,
+  /// Runtime configuration
+  pub config:           ApiConfig,
+  /// Failed login counters, for brute-force protection
+  pub login_throttle:   LoginThrottle,
+  /// Proof of work challenges, to deter bots
+  pub proof_of_work:    ProofOfWork,
+  /// Signs anonymous posting tokens
+  pub anon_tokens:      AnonTokenSigner,
+  /// Recently computed leaderboards
+  pub leaders:          LeaderboardCache,
+  /// When sessions were last seen, to throttle recording it
+  pub session_activity: SessionActivity,
 }
 
 impl SharedState {
-  fn new(pool: DbPool) -> Self { Self { pool } }
+  fn new(pool: DbPool, config: ApiConfig) -> ApiResult<Self> {
+    let anon_tokens = AnonTokenSigner::new(config.anon_token_key.as_deref())?;
+    Ok(Self {
+      pool,
+      config,
+      login_throttle: LoginThrottle::new(),
+      proof_of_work: ProofOfWork::new(),
+      anon_tokens,
+      leaders: LeaderboardCache::new(),
+      session_activity: SessionActivity::new(),
+    })
+  }
+
+  /// Verify the proof of work for `action` by `user`, if given, or if required by the config.
+  pub(crate) fn assert_proof_of_work(
+    &self,
+    solution: Option<&PowSolution>,
+    action: PowAction,
+    user: Option<&User>,
+  ) -> ApiResult<()> {
+    match solution {
+      Some(solution) => self.proof_of_work.verify(solution, action, user),
+      None if self.config.require_proof_of_work =>
+        Err(ApiError::ForbiddenInvalidProofOfWork("required".into())),
+      None => Ok(()),
+    }
+  }
 }
@@ -22,16 +22,37 @@
 //! Derive ToSchema for Payloads and Responses.
 use axum::{routing, Json, Router};
 use db::{
-  models::{user::User, user_favorite::FavoriteStateEnum, user_vote::*},
-  Page,
+  models::{
+    karma::KarmaBucket,
+    user::User,
+    user_favorite::FavoriteStateEnum,
+    user_vote::*,
+    vote_flag::{VoteFlagKind, VoteFlagStatus},
+    webhook::{WebhookDeliveryStatus, WebhookEvent},
+  },
+  ItemContent, Page,
 };
 use utoipa::OpenApi;
 use utoipa_rapidoc::RapiDoc;
 use utoipauto::utoipauto;
 
 use super::{
+  admin::{get::*, post::*, put::*, *},
+  anon::{get::*, post::*, *},
+  comments::{delete::*, get::*, post::*, put::*, *},
   items::{delete::*, get::*, post::*, put::*, *},
-  users::{get::*, post::*, put::*, *},
+  leaders::get::*,
+  pow::get::*,
+  siwe::{get::*, post::*, *},
+  users::{delete::*, get::*, post::*, put::*, *},
+  webhooks::{delete::*, get::*, post::*, *},
+};
+use crate::{
+  anon::AnonToken,
+  config::Privilege,
+  leaders::{LeaderBoard, LeaderResponse, LeadersResponse},
+  pow::{PowAction, PowChallengeResponse, PowSolution},
+  revisions::{RevisionDiffQuery, RevisionDiffResponse, RevisionResponse, RevisionsResponse},
 };
 
 /// router fragment supplying OpenAPI documentation and ui routes
@@ -49,11 +70,22 @@ pub(super) fn docs_router() -> Router {
   info(description = "API documentation for ZKHN"),
   // Schemas that may be returned in the body by the api.
   components(schemas(
-    User, UserUpdatePayload, ChangePasswordPayload, CreateUserPayload,
+    User, UserUpdatePayload, ChangePasswordPayload, CreateUserPayload, DeleteUserPayload,
     CredentialsPayload, GetUserResponse, AuthenticateUserResponse, AuthUserResponseInternal,
-    CreateItemPayload, FavoriteStateEnum,
+    UserSessionResponse, UsernameAvailableResponse, UserExport, ExportedUser,
+    DataExportPendingResponse, NotificationsResponse, MarkNotificationsReadPayload,
+    InviteCodeResponse, InviteTreeResponse, InviteeResponse,
+    CreateItemPayload, CreateCommentPayload, EditCommentPayload, ItemContent, FavoriteStateEnum,
     GetItemResponse, ItemOrComment, ItemKind, GetItemsPageResponse, WithCommentsResponse,
-    VotePayload, VoteState, FavoritePayload))
+    VotePayload, VoteState, FavoritePayload, CreateWebhookPayload, WebhookResponse,
+    WebhookDeliveryResponse, WebhookEvent, WebhookDeliveryStatus, PowChallengeResponse,
+    PowSolution, PowAction, SiweNonceResponse, SiweVerifyPayload,
+    AnonKeyResponse, AnonTokensResponse, IssueAnonTokensPayload, AnonToken,
+    CreateAnonymousItemPayload, CreateAnonymousCommentPayload, Privilege, KarmaReconciliationResponse, KarmaDiscrepancyResponse,
+    KarmaReconciliationDetailResponse, KarmaHistoryResponse, KarmaHistoryPointResponse,
+    KarmaBucket, LeadersResponse, LeaderResponse, LeaderBoard, VoteFlagResponse,
+    ReviewVoteFlagPayload, VoteFlagKind, VoteFlagStatus, DeletedCommentResponse,
+    RevisionsResponse, RevisionResponse, RevisionDiffResponse))
   // runtime modification, e.g. for jwt: https://docs.rs/utoipa/latest/utoipa/trait.Modify.html
   // low-priority, but could gate moderator methods with an auth token.
   // modifiers(..) 
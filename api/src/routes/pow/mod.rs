@@ -0,0 +1,52 @@
+use axum::{
+  extract::{Query, State},
+  routing, Json, Router,
+};
+use tracing::trace;
+
+use super::SharedState;
+use crate::{
+  auth::{AuthSession, AuthenticationExt},
+  pow::{PowAction, PowChallengeQuery, PowChallengeResponse},
+  ApiResult,
+};
+
+/// Router to be mounted at "/pow"
+pub(super) fn pow_router(state: SharedState) -> Router {
+  Router::new().route("/challenge", routing::get(get::get_challenge)).with_state(state)
+}
+
+pub(super) mod get {
+  use super::*;
+
+  #[utoipa::path(
+      get,
+      path = "/pow/challenge",
+      params( PowChallengeQuery ),
+      responses(
+        (status = 400, description = "Invalid action"),
+        (status = 401, description = "Submissions require logging in first"),
+        (status = 403, description = "Banned"),
+        (status = 200, body = PowChallengeResponse),
+      ),
+  )]
+  /// Get a proof of work challenge for `action`: `signup`, `item`, or `comment`.
+  ///
+  /// Submission challenges are bound to the logged in account, and are easier for older and
+  /// higher-karma accounts.
+  pub async fn get_challenge(
+    State(state): State<SharedState>,
+    auth_session: AuthSession,
+    Query(query): Query<PowChallengeQuery>,
+  ) -> ApiResult<Json<PowChallengeResponse>> {
+    trace!("get_challenge called with: {query:?}");
+    let user = match query.action {
+      PowAction::Signup => None,
+      PowAction::Item | PowAction::Comment => Some(auth_session.get_assert_user_from_session()?),
+    };
+    let challenge =
+      state.proof_of_work.challenge(query.action, user.as_ref(), state.config.pow_base_difficulty);
+
+    Ok(Json(challenge))
+  }
+}
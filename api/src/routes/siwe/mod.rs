@@ -0,0 +1,82 @@
+pub(super) mod payload;
+pub(super) mod response;
+
+use axum::{
+  extract::State,
+  http::{header, HeaderMap, StatusCode},
+  routing, Json, Router,
+};
+use garde::Validate;
+use serde::{Deserialize, Serialize};
+use tracing::trace;
+use utoipa::ToSchema;
+
+pub use self::{payload::*, response::*};
+use super::SharedState;
+use crate::{
+  auth::{issue_siwe_nonce, siwe_verify_internal, AuthSession},
+  ApiResult,
+};
+
+/// Router to be mounted at "/auth/siwe"
+pub(super) fn siwe_router(state: SharedState) -> Router {
+  Router::new()
+    .route("/nonce", routing::get(get::get_nonce))
+    .route("/verify", routing::post(post::verify))
+    .with_state(state)
+}
+
+pub(super) mod get {
+  use super::*;
+
+  #[utoipa::path(
+      get,
+      path = "/auth/siwe/nonce",
+      responses(
+        (status = 200, body = SiweNonceResponse),
+      ),
+  )]
+  /// Get a nonce for Sign-In with Ethereum.
+  ///
+  /// The nonce is kept in the caller's session, and replaces any previous nonce. It may be used
+  /// once, within ten minutes.
+  pub async fn get_nonce(auth_session: AuthSession) -> ApiResult<Json<SiweNonceResponse>> {
+    let nonce = issue_siwe_nonce(&auth_session.session).await?;
+    Ok(Json(SiweNonceResponse { nonce }))
+  }
+}
+
+pub(super) mod post {
+  use super::*;
+
+  #[utoipa::path(
+      post,
+      path = "/auth/siwe/verify",
+      request_body = SiweVerifyPayload,
+      responses(
+        (status = 400, description = "Malformed message"),
+        (status = 401, description = "Invalid signature, domain, nonce, or validity window; or wallet not linked and not logged in"),
+        (status = 409, description = "Wallet already linked"),
+        (status = 422, description = "Invalid Payload"),
+        (status = 200),
+      ),
+  )]
+  /// Sign in with Ethereum.
+  ///
+  /// Verify an EIP-4361 message signed with `personal_sign`. If the signing address is linked to
+  /// an account, log in to it. Otherwise, if logged in, link the address to the caller's account,
+  /// so that it may be used to log in from then on.
+  pub async fn verify(
+    State(state): State<SharedState>,
+    auth_session: AuthSession,
+    headers: HeaderMap,
+    Json(payload): Json<SiweVerifyPayload>,
+  ) -> ApiResult<StatusCode> {
+    trace!("siwe verify called");
+    payload.validate(&())?;
+    let user_agent =
+      headers.get(header::USER_AGENT).and_then(|v| v.to_str().ok()).map(String::from);
+    siwe_verify_internal(&state.pool, &state.config.siwe_domain, auth_session, payload, user_agent)
+      .await
+  }
+}
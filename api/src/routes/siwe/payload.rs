@@ -0,0 +1,20 @@
+use super::*;
+
+/// A signed EIP-4361 message, containing a nonce from `get_nonce`.
+#[derive(Debug, Default, Clone, Serialize, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[schema(default = SiweVerifyPayload::default, example=SiweVerifyPayload::default)]
+pub struct SiweVerifyPayload {
+  /// The message, exactly as signed
+  #[garde(length(min = 1, max = 4096))]
+  pub message:   String,
+  /// The `personal_sign` signature: 65 bytes, hex encoded
+  #[garde(ascii, length(min = 130, max = 132))]
+  pub signature: String,
+}
+
+impl SiweVerifyPayload {
+  pub fn new(message: &str, signature: &str) -> Self {
+    Self { message: message.into(), signature: signature.into() }
+  }
+}
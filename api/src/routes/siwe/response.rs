@@ -0,0 +1,9 @@
+use super::*;
+
+/// A nonce to include in the EIP-4361 message to be signed.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, Default)]
+#[serde(rename_all = "camelCase")]
+#[schema(default = SiweNonceResponse::default, example=SiweNonceResponse::default)]
+pub struct SiweNonceResponse {
+  pub nonce: String,
+}
@@ -4,39 +4,67 @@ pub(super) mod payload;
 pub(super) mod response;
 
 use axum::{
-  extract::{Path, State},
-  http::StatusCode,
+  body::Body,
+  extract::{Path, Query, State},
+  http::{header, HeaderMap, StatusCode},
+  response::{IntoResponse, Response},
   routing, Json, Router,
 };
 use db::{
-  models::user::User, queries::users, About, AuthToken, Email, Password, PasswordHash,
-  ResetPasswordToken, Timestamp, Username,
+  models::{
+    data_export::DataExport, invite_code::InviteCode, karma::KarmaBucket, user::User,
+    user_session::UserSession,
+  },
+  queries::{user_sessions, users},
+  About, AuthToken, DbError, Email, Page, Password, PasswordHash, ResetPasswordToken, Timestamp,
+  Ulid, Username,
 };
 use garde::Validate;
 use serde::{Deserialize, Serialize};
 use tracing::{debug, trace};
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
 
 pub use self::{payload::*, response::*};
 use super::SharedState;
 use crate::{
-  auth::{AuthSession, AuthenticationExt, PasswordExt},
+  auth::{client_ip, AuthSession, AuthenticationExt, PasswordExt},
   error::ApiError,
-  ApiResult, MINIMUM_KARMA_TO_DOWNVOTE,
+  exports,
+  pow::{PowAction, PowSolution},
+  ApiResult, Privilege, PrivilegeTable, RegistrationMode, MAX_UNUSED_INVITE_CODES,
 };
 
+/// The most buckets a karma history may span.
+const MAX_KARMA_HISTORY_BUCKETS: i32 = 400; // todo(config)
+/// The karma history span when `from` is not given, in buckets.
+const DEFAULT_KARMA_HISTORY_BUCKETS: i32 = 30;
+
 /// Router to be mounted at "/users"
 pub(super) fn users_router(state: SharedState) -> Router {
   Router::new()
     // note - called `/users/get-user-data` in reference
     .route("/:username", routing::get(get::get_user))
-    .route("/", routing::put(put::update_user).post(post::create_user))
+    .route("/:username/karma-history", routing::get(get::get_karma_history))
+    .route(
+      "/",
+      routing::put(put::update_user).post(post::create_user).delete(delete::delete_user),
+    )
     // todo(email) - create reset-password with reset password token
     .route("/reset-password-link/:username", routing::put(put::request_password_reset_link))
     .route("/change-password", routing::put(put::change_password))
     .route("/login", routing::post(post::login))
     .route("/logout", routing::post(post::logout))
+    .route("/logout-all", routing::post(post::logout_all))
     .route("/authenticate", routing::get(get::authenticate))
+    .route("/available/:username", routing::get(get::username_available))
+    .route("/sessions", routing::get(get::get_sessions))
+    .route("/notifications", routing::get(get::get_notifications))
+    .route("/notifications/read", routing::post(post::mark_notifications_read))
+    .route("/invites", routing::get(get::get_invite_codes).post(post::create_invite_code))
+    .route("/invites/tree/:username", routing::get(get::get_invite_tree))
+    .route("/export", routing::get(get::export_user_data))
+    .route("/export/:id", routing::get(get::get_data_export))
+    .route("/sessions/:id", routing::delete(delete::delete_session))
     .with_state(state)
 }
 
@@ -68,12 +96,87 @@ pub(super) mod get {
     username.validate(&())?;
     let user = users::get_assert_user(&state.pool, &username).await?;
     let session_user = auth_session.get_user_from_session();
-    let user_response = GetUserResponse::new(user, session_user);
+    let user_response = GetUserResponse::new(user, session_user, &state.config.privileges);
 
     debug!("user response: {user_response:?}");
     Ok(Json(user_response))
   }
 
+  #[utoipa::path(
+      get,
+      path = "/users/{username}/karma-history",
+      params( ("username" = String, Path, example = "alice"), KarmaHistoryQuery ),
+      responses(
+        (status = 400, description = "Invalid or too long range"),
+        (status = 404, description = "User not found"),
+        (status = 422, description = "Invalid username"),
+        (status = 200, body = KarmaHistoryResponse),
+      ),
+  )]
+  /// Get `username`'s karma over time, from the karma ledger, one point per `day` or `week`.
+  ///
+  /// Every bucket in the range is included, so the points may be charted directly.
+  pub async fn get_karma_history(
+    State(state): State<SharedState>,
+    Path(username): Path<Username>,
+    Query(query): Query<KarmaHistoryQuery>,
+  ) -> ApiResult<Json<KarmaHistoryResponse>> {
+    trace!("get_karma_history called with: {username}, {query:?}");
+    username.validate(&())?;
+    let KarmaHistoryQuery { from, to, bucket } = query;
+    let to = to.unwrap_or_else(Timestamp::now);
+    let from = match from {
+      Some(from) => from,
+      None => to
+        .0
+        .checked_sub_signed(bucket.duration() * DEFAULT_KARMA_HISTORY_BUCKETS)
+        .map(Timestamp)
+        .ok_or_else(|| ApiError::BadRequest("to is out of range".into()))?,
+    };
+    if from > to {
+      return Err(ApiError::BadRequest("from must not be after to".into()));
+    }
+    if to.0 - from.0 > bucket.duration() * MAX_KARMA_HISTORY_BUCKETS {
+      return Err(ApiError::BadRequest(format!(
+        "range may span at most {MAX_KARMA_HISTORY_BUCKETS} buckets"
+      )));
+    }
+
+    let user = users::get_assert_user(&state.pool, &username).await?;
+    let points =
+      db::queries::karma::get_karma_history(&state.pool, &user.username, &from, &to, bucket)
+        .await?;
+
+    Ok(Json(KarmaHistoryResponse {
+      username: user.username,
+      bucket,
+      points: points.into_iter().map(KarmaHistoryPointResponse::from).collect(),
+    }))
+  }
+
+  #[utoipa::path(
+      get,
+      path = "/users/available/{username}",
+      params( ("username" = String, Path, example = "alice") ),
+      responses(
+        (status = 422, description = "Invalid or reserved username"),
+        (status = 200, body = UsernameAvailableResponse),
+      ),
+  )]
+  /// Check whether a username may be registered, for signup forms.
+  ///
+  /// Usernames are unique regardless of case.
+  pub async fn username_available(
+    State(state): State<SharedState>,
+    Path(path): Path<UsernameAvailablePath>,
+  ) -> ApiResult<Json<UsernameAvailableResponse>> {
+    trace!("username_available called with username: {}", path.username);
+    path.validate(&())?;
+    let username = path.username;
+    let available = users::username_available(&state.pool, &username).await?;
+    Ok(Json(UsernameAvailableResponse { username, available }))
+  }
+
   #[utoipa::path(
       get,
       path = "/users/authenticate",
@@ -91,19 +194,283 @@ pub(super) mod get {
   ///
   /// ref: https://github.com/thor314/zkhn/blob/main/rest-api/routes/users/api.js#L97
   pub async fn authenticate(
+    State(state): State<SharedState>,
     auth_session: AuthSession,
   ) -> ApiResult<Json<AuthenticateUserResponse>> {
     let session_user = auth_session.get_assert_user_from_session()?;
-    let authenticate_user_response = AuthenticateUserResponse::new(session_user);
+    let unread_notifications =
+      db::queries::notifications::count_unread_notifications(&state.pool, &session_user.username)
+        .await?;
+    let authenticate_user_response =
+      AuthenticateUserResponse::new(session_user, unread_notifications, &state.config.privileges);
     debug!("authenticate_user_response: {authenticate_user_response:?}");
     Ok(Json(authenticate_user_response))
   }
+
+  #[utoipa::path(
+      get,
+      path = "/users/sessions",
+      responses(
+        (status = 401, description = "Not logged in"),
+        (status = 403, description = "Banned"),
+        (status = 200, body = Vec<UserSessionResponse>),
+      ),
+  )]
+  /// List the caller's active sessions, most recently used first.
+  pub async fn get_sessions(
+    State(state): State<SharedState>,
+    auth_session: AuthSession,
+  ) -> ApiResult<Json<Vec<UserSessionResponse>>> {
+    let session_user = auth_session.get_assert_user_from_session()?;
+    let current_session_id = auth_session.session.id().map(|id| id.to_string());
+    let sessions = user_sessions::get_user_sessions(&state.pool, &session_user.username)
+      .await?
+      .into_iter()
+      .map(|session| UserSessionResponse::new(session, current_session_id.as_deref()))
+      .collect();
+
+    Ok(Json(sessions))
+  }
+
+  #[utoipa::path(
+      get,
+      path = "/users/notifications",
+      params( Page ),
+      responses(
+        (status = 401, description = "Not logged in"),
+        (status = 403, description = "Banned"),
+        (status = 422, description = "Invalid page"),
+        (status = 200, body = NotificationsResponse),
+      ),
+  )]
+  /// Get the `page` of the caller's reply notifications, newest first, with their unread count.
+  pub async fn get_notifications(
+    State(state): State<SharedState>,
+    auth_session: AuthSession,
+    Query(page): Query<Page>,
+  ) -> ApiResult<Json<NotificationsResponse>> {
+    page.validate(&())?;
+    let session_user = auth_session.get_assert_user_from_session()?;
+    let (notifications, unread_count) = tokio::try_join!(
+      db::queries::notifications::get_notifications(&state.pool, &session_user.username, &page),
+      db::queries::notifications::count_unread_notifications(&state.pool, &session_user.username),
+    )?;
+
+    Ok(Json(NotificationsResponse::new(notifications, unread_count, page)))
+  }
+
+  #[utoipa::path(
+      get,
+      path = "/users/invites",
+      responses(
+        (status = 401, description = "Not logged in"),
+        (status = 403, description = "Banned"),
+        (status = 200, body = [InviteCodeResponse]),
+      ),
+  )]
+  /// List the invite codes the caller has minted, and who used them.
+  pub async fn get_invite_codes(
+    State(state): State<SharedState>,
+    auth_session: AuthSession,
+  ) -> ApiResult<Json<Vec<InviteCodeResponse>>> {
+    let session_user = auth_session.get_assert_user_from_session()?;
+    let codes =
+      db::queries::invite_codes::get_user_invite_codes(&state.pool, &session_user.username).await?;
+
+    Ok(Json(codes.into_iter().map(InviteCodeResponse::from).collect()))
+  }
+
+  #[utoipa::path(
+      get,
+      path = "/users/invites/tree/{username}",
+      params( ("username" = String, Path, example = "alice") ),
+      responses(
+        (status = 401, description = "Not logged in"),
+        (status = 403, description = "Moderator only"),
+        (status = 404, description = "User not found"),
+        (status = 422, description = "Invalid username"),
+        (status = 200, body = InviteTreeResponse),
+      ),
+  )]
+  /// Moderators: audit who invited `username`, and everyone they invited, directly or not.
+  pub async fn get_invite_tree(
+    State(state): State<SharedState>,
+    auth_session: AuthSession,
+    Path(username): Path<Username>,
+  ) -> ApiResult<Json<InviteTreeResponse>> {
+    username.validate(&())?;
+    let session_user = auth_session.get_assert_user_from_session()?;
+    if !session_user.is_moderator {
+      return Err(ApiError::ForbiddenModeratorRequired);
+    }
+    let user = users::get_assert_user(&state.pool, &username).await?;
+    let (invited_by, invitees) = tokio::try_join!(
+      db::queries::invite_codes::get_inviter(&state.pool, &user.username),
+      db::queries::invite_codes::get_invite_tree(&state.pool, &user.username),
+    )?;
+
+    Ok(Json(InviteTreeResponse::new(user.username, invited_by, invitees)))
+  }
+
+  #[utoipa::path(
+      get,
+      path = "/users/export",
+      responses(
+        (status = 401, description = "Not logged in"),
+        (status = 403, description = "Banned"),
+        (status = 202, description = "Export is being generated", body = DataExportPendingResponse),
+        (status = 200, description = "JSON attachment", body = UserExport),
+      ),
+  )]
+  /// Export everything we hold about the caller.
+  ///
+  /// Small accounts receive the JSON bundle directly. For large accounts the export is queued for
+  /// the export worker, and a 202 is returned with a url to download it from once ready.
+  pub async fn export_user_data(
+    State(state): State<SharedState>,
+    auth_session: AuthSession,
+  ) -> ApiResult<Response> {
+    let session_user = auth_session.get_assert_user_from_session()?;
+    let size =
+      db::queries::data_exports::count_user_content(&state.pool, &session_user.username).await?;
+
+    if size <= state.config.export_inline_limit {
+      let username = session_user.username.clone();
+      let data = exports::stream_export(state.pool.clone(), session_user);
+      return Ok(export_attachment(&username, Body::from_stream(data)));
+    }
+
+    let data_export = DataExport::new(session_user.username.clone());
+    db::queries::data_exports::create_data_export(&state.pool, &data_export).await?;
+    debug!("queued data export: {}", data_export.id);
+    Ok((StatusCode::ACCEPTED, Json(DataExportPendingResponse::new(&data_export))).into_response())
+  }
+
+  #[utoipa::path(
+      get,
+      path = "/users/export/{id}",
+      params( ("id" = String, Path, example = Ulid::new) ),
+      responses(
+        (status = 401, description = "Not logged in"),
+        (status = 403, description = "Banned"),
+        (status = 404, description = "Export not found or expired"),
+        (status = 202, description = "Export is still being generated", body = DataExportPendingResponse),
+        (status = 200, description = "JSON attachment", body = UserExport),
+      ),
+  )]
+  /// Download a data export generated in the background.
+  pub async fn get_data_export(
+    State(state): State<SharedState>,
+    auth_session: AuthSession,
+    Path(id): Path<Ulid>,
+  ) -> ApiResult<Response> {
+    id.validate(&())?;
+    let session_user = auth_session.get_assert_user_from_session()?;
+    let data_export =
+      db::queries::data_exports::get_assert_data_export(&state.pool, &id, &session_user.username)
+        .await?;
+
+    if Timestamp::now() > data_export.expiration() {
+      db::queries::data_exports::delete_data_export(&state.pool, &id).await?;
+      return Err(ApiError::DbEntryNotFound("data export expired".to_string()));
+    }
+    match data_export {
+      DataExport { completed: None, .. } => Ok(
+        (StatusCode::ACCEPTED, Json(DataExportPendingResponse::new(&data_export))).into_response(),
+      ),
+      DataExport { error: Some(error), .. } => Err(ApiError::OtherISE(error)),
+      DataExport { id, .. } => {
+        let data = db::queries::data_exports::stream_data_export(
+          state.pool.clone(),
+          id,
+          exports::STORED_CHUNK_CHARS,
+        );
+        Ok(export_attachment(&session_user.username, Body::from_stream(data)))
+      },
+    }
+  }
+
+  /// Serve a serialized `UserExport` as a JSON file download.
+  fn export_attachment(username: &Username, data: Body) -> Response {
+    let disposition = format!("attachment; filename=\"zkhn-export-{username}.json\"");
+    (
+      [
+        (header::CONTENT_TYPE, "application/json".to_string()),
+        (header::CONTENT_DISPOSITION, disposition),
+      ],
+      data,
+    )
+      .into_response()
+  }
 }
 
 pub(super) mod post {
   use super::*;
   use crate::auth::{login_post_internal, logout_post_internal};
 
+  #[utoipa::path(
+      post,
+      path = "/users/invites",
+      responses(
+        (status = 401, description = "Not logged in"),
+        (status = 403, description = "Banned, insufficient karma, or too many unused codes"),
+        (status = 200, body = InviteCodeResponse),
+      ),
+  )]
+  /// Mint an invite code.
+  ///
+  /// Requires the `mint-invites` privilege. Users may hold at most `MAX_UNUSED_INVITE_CODES`
+  /// unused codes; moderators are exempt.
+  pub async fn create_invite_code(
+    State(state): State<SharedState>,
+    auth_session: AuthSession,
+  ) -> ApiResult<Json<InviteCodeResponse>> {
+    let session_user = auth_session
+      .get_assert_user_with_privilege(&state.config.privileges, Privilege::MintInvites)?;
+    let max_unused = (!session_user.is_moderator).then_some(MAX_UNUSED_INVITE_CODES);
+
+    let code = hex::encode(rand::random::<[u8; 8]>());
+    let invite_code = InviteCode::new(code, session_user.username);
+    if !db::queries::invite_codes::create_invite_code(&state.pool, &invite_code, max_unused).await?
+    {
+      return Err(ApiError::ForbiddenLimitReached(format!(
+        "at most {MAX_UNUSED_INVITE_CODES} unused invite codes may be held"
+      )));
+    }
+    debug!("minted invite code for: {}", invite_code.created_by);
+
+    Ok(Json(InviteCodeResponse::from(invite_code)))
+  }
+
+  #[utoipa::path(
+      post,
+      path = "/users/notifications/read",
+      request_body = MarkNotificationsReadPayload,
+      responses(
+        (status = 401, description = "Not logged in"),
+        (status = 403, description = "Banned"),
+        (status = 422, description = "Invalid Payload"),
+        (status = 200, description = "Success"),
+      ),
+  )]
+  /// Mark the given notifications read, or all of the caller's notifications if no ids are given.
+  pub async fn mark_notifications_read(
+    State(state): State<SharedState>,
+    auth_session: AuthSession,
+    Json(payload): Json<MarkNotificationsReadPayload>,
+  ) -> ApiResult<StatusCode> {
+    payload.validate(&())?;
+    let session_user = auth_session.get_assert_user_from_session()?;
+    db::queries::notifications::mark_notifications_read(
+      &state.pool,
+      &session_user.username,
+      payload.ids.as_deref(),
+    )
+    .await?;
+
+    Ok(StatusCode::OK)
+  }
+
   #[utoipa::path(
       post,
       path = "/users",
@@ -111,11 +478,17 @@ pub(super) mod post {
       responses(
         (status = 422, description = "Invalid Payload"),
         (status = 409, description = "Duplication Conflict"),
+        (status = 403, description = "Registration closed, invalid invite code, or invalid proof of work"),
         (status = 200),
       ),
   )]
   /// Create a new user.
   ///
+  /// Requires a solved `signup` proof of work challenge, if configured.
+  ///
+  /// Subject to the registration mode: while invite-only, an unused invite code is required, and is
+  /// consumed along with creating the user.
+  ///
   /// prod(search): tell the Algolia about the new user
   /// hack(cookie) https://github.com/thor314/zkhn/blob/main/rest-api/routes/users/index.js#L29
   pub async fn create_user(
@@ -124,8 +497,20 @@ pub(super) mod post {
   ) -> ApiResult<StatusCode> {
     trace!("create_user called with payload: {payload:?}");
     payload.validate(&())?;
-    let user: User = payload.into_user().await;
-    users::create_user(&state.pool, &user).await?;
+    state.assert_proof_of_work(payload.pow.as_ref(), PowAction::Signup, None)?;
+    match state.config.registration_mode {
+      RegistrationMode::Open => {},
+      RegistrationMode::InviteOnly if payload.invite_code.is_some() => {},
+      RegistrationMode::InviteOnly => return Err(ApiError::ForbiddenInvalidInviteCode),
+      RegistrationMode::Closed => return Err(ApiError::ForbiddenRegistrationClosed),
+    }
+
+    let invite_code = payload.invite_code.clone();
+    let user: User = payload.into_user(&state.config.argon2_params).await?;
+    users::create_user(&state.pool, &user, invite_code.as_deref()).await.map_err(|e| match e {
+      DbError::NotFound(_) => ApiError::ForbiddenInvalidInviteCode,
+      e => e.into(),
+    })?;
 
     debug!("created user: {user:?}");
     Ok(StatusCode::OK)
@@ -138,16 +523,29 @@ pub(super) mod post {
       responses(
         (status = 422, description = "Invalid Payload"),
         (status = 401, description = "Unauthorized: Incorrect Password"),
+        (status = 429, description = "Too many failed logins, see Retry-After header"),
         (status = 200),
       ),
   )]
   /// User login.
+  ///
+  /// Repeated failures for a username or ip are throttled with exponential backoff, and
+  /// eventually a temporary lockout.
   pub async fn login(
+    State(state): State<SharedState>,
     auth_session: AuthSession,
+    headers: HeaderMap,
     Json(payload): Json<CredentialsPayload>,
-  ) -> ApiResult<StatusCode> {
-    payload.validate(&())?;
-    login_post_internal(auth_session, payload).await
+  ) -> Response {
+    if let Err(e) = payload.validate(&()) {
+      return ApiError::from(e).into_response();
+    }
+    let user_agent =
+      headers.get(header::USER_AGENT).and_then(|v| v.to_str().ok()).map(String::from);
+    let ip = client_ip(&headers);
+    login_post_internal(&state.pool, &state.login_throttle, auth_session, payload, user_agent, ip)
+      .await
+      .map_or_else(ApiError::into_response_with_retry_after, IntoResponse::into_response)
   }
 
   #[utoipa::path(
@@ -158,8 +556,33 @@ pub(super) mod post {
       ),
   )]
   /// User logout.
-  pub async fn logout(auth_session: AuthSession) -> ApiResult<StatusCode> {
-    logout_post_internal(auth_session).await
+  pub async fn logout(
+    State(state): State<SharedState>,
+    auth_session: AuthSession,
+  ) -> ApiResult<StatusCode> {
+    logout_post_internal(&state.pool, auth_session).await
+  }
+
+  #[utoipa::path(
+      post,
+      path = "/users/logout-all",
+      responses(
+        (status = 401, description = "Not logged in"),
+        (status = 403, description = "Banned"),
+        (status = 200),
+      ),
+  )]
+  /// Log out of every session, on every device, including the current one.
+  pub async fn logout_all(
+    State(state): State<SharedState>,
+    mut auth_session: AuthSession,
+  ) -> ApiResult<StatusCode> {
+    let session_user = auth_session.get_assert_user_from_session()?;
+    auth_session.logout().await?;
+    user_sessions::delete_all_user_sessions(&state.pool, &session_user.username).await?;
+
+    debug!("logged out all sessions for: {}", session_user.username);
+    Ok(StatusCode::OK)
   }
 
   // hack(cookie): https://github.com/thor314/zkhn/blob/main/rest-api/routes/users/index.js#L71
@@ -298,7 +721,7 @@ pub(super) mod put {
       ));
     }
 
-    let new_hash = payload.new_password.hash().await;
+    let new_hash = payload.new_password.hash(&state.config.argon2_params).await?;
     users::update_user_password(&state.pool, &payload.username, &new_hash).await?;
     // prod(email) - send an email to the user that their password has changed
 
@@ -306,3 +729,67 @@ pub(super) mod put {
     Ok(StatusCode::OK)
   }
 }
+
+pub(super) mod delete {
+  use super::*;
+
+  #[utoipa::path(
+      delete,
+      path = "/users",
+      request_body = DeleteUserPayload,
+      responses(
+        (status = 401, description = "Not logged in"),
+        (status = 401, description = "Incorrect password"),
+        (status = 403, description = "Banned"),
+        (status = 422, description = "Invalid Payload"),
+        (status = 200),
+      ),
+  )]
+  /// Delete the caller's account, after confirming their password.
+  ///
+  /// The user's votes, favorites, and sessions are removed. Their items and comments remain, but
+  /// are attributed to `[deleted]`.
+  pub async fn delete_user(
+    State(state): State<SharedState>,
+    mut auth_session: AuthSession,
+    Json(payload): Json<DeleteUserPayload>,
+  ) -> ApiResult<StatusCode> {
+    trace!("delete_user called");
+    payload.validate(&())?;
+    let session_user = auth_session.get_assert_user_from_session()?;
+    payload.password.hash_and_verify(&session_user.password_hash).await?;
+
+    users::delete_user(&state.pool, &session_user.username).await?;
+    // the session record is already gone; this clears the session cookie
+    auth_session.logout().await?;
+
+    debug!("deleted user: {}", session_user.username);
+    Ok(StatusCode::OK)
+  }
+
+  #[utoipa::path(
+      delete,
+      path = "/users/sessions/{id}",
+      params( ("id" = String, Path, example = Ulid::new) ),
+      responses(
+        (status = 401, description = "Not logged in"),
+        (status = 403, description = "Banned"),
+        (status = 404, description = "Session not found"),
+        (status = 200),
+      ),
+  )]
+  /// Revoke one of the caller's sessions, logging that device out.
+  pub async fn delete_session(
+    State(state): State<SharedState>,
+    auth_session: AuthSession,
+    Path(id): Path<Ulid>,
+  ) -> ApiResult<StatusCode> {
+    trace!("delete_session called with id: {id}");
+    id.validate(&())?;
+    let session_user = auth_session.get_assert_user_from_session()?;
+    user_sessions::delete_user_session(&state.pool, &session_user.username, &id).await?;
+
+    debug!("revoked session {id} for: {}", session_user.username);
+    Ok(StatusCode::OK)
+  }
+}
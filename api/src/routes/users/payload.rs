@@ -5,20 +5,28 @@ use super::*;
 #[serde(rename_all = "camelCase")]
 #[schema(default = CreateUserPayload::default, example=CreateUserPayload::default)]
 pub struct CreateUserPayload {
+  #[garde(dive, custom(db::validate_new_username))]
+  pub username:    Username,
   #[garde(dive)]
-  pub username: Username,
-  #[garde(dive)]
-  pub password: Password,
+  pub password:    Password,
   #[garde(dive)]
-  pub email:    Option<Email>,
+  pub email:       Option<Email>,
   #[garde(dive)]
-  pub about:    Option<About>,
+  pub about:       Option<About>,
+  /// Required while registration is invite-only
+  #[garde(ascii, length(min = 1, max = 64))]
+  #[serde(default)]
+  pub invite_code: Option<String>,
+  /// A solved `signup` challenge, see `get_challenge`
+  #[garde(skip)]
+  #[serde(default)]
+  pub pow:         Option<PowSolution>,
 }
 
 impl CreateUserPayload {
-  pub async fn into_user(self) -> User {
-    let password_hash = self.password.hash().await;
-    User::new(self.username, password_hash, self.email, self.about)
+  pub async fn into_user(self, argon2_params: &argon2::Params) -> ApiResult<User> {
+    let password_hash = self.password.hash(argon2_params).await?;
+    Ok(User::new(self.username, password_hash, self.email, self.about))
   }
 
   /// convenience method for testing
@@ -32,11 +40,19 @@ impl CreateUserPayload {
     let password = password.into();
     let email = email.map(|s| s.into());
     let about = about.map(|s| s.into());
-    let payload = Self { username, password, email, about };
+    let payload = Self { username, password, email, about, invite_code: None, pow: None };
     payload.validate(&())?;
     Ok(payload)
   }
 
+  /// convenience method for testing
+  pub fn with_invite_code(self, invite_code: &str) -> Self {
+    Self { invite_code: Some(invite_code.into()), ..self }
+  }
+
+  /// convenience method for testing
+  pub fn with_pow(self, pow: PowSolution) -> Self { Self { pow: Some(pow), ..self } }
+
   pub fn bob() -> Self {
     Self::new("bob", "password", Some("bob@email.com"), Some("about bob")).unwrap()
   }
@@ -80,6 +96,32 @@ impl UserUpdatePayload {
   }
 }
 
+/// Password confirmation for `delete_user`
+#[derive(Debug, Default, Clone, Serialize, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[schema(default = DeleteUserPayload::default, example=DeleteUserPayload::default)]
+pub struct DeleteUserPayload {
+  #[garde(dive)]
+  pub password: Password,
+}
+
+impl DeleteUserPayload {
+  pub fn new(password: &str) -> Self { Self { password: password.into() } }
+}
+
+/// Payload for `mark_notifications_read`. Marks every notification read if `ids` is omitted.
+#[derive(Debug, Default, Clone, Serialize, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[schema(default = MarkNotificationsReadPayload::default, example=MarkNotificationsReadPayload::default)]
+pub struct MarkNotificationsReadPayload {
+  #[garde(dive)]
+  pub ids: Option<Vec<Ulid>>,
+}
+
+impl MarkNotificationsReadPayload {
+  pub fn new(ids: Option<Vec<Ulid>>) -> Self { Self { ids } }
+}
+
 /// Payload for `change_password`
 #[derive(Debug, Clone, Serialize, Deserialize, Validate, ToSchema)]
 #[serde(rename_all = "camelCase")]
@@ -137,6 +179,13 @@ pub struct CredentialsPayload {
   pub next:     Option<String>,
 }
 
+/// Path for `GET /users/available/{username}`, checked against the rules for new usernames.
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct UsernameAvailablePath {
+  #[garde(dive, custom(db::validate_new_username))]
+  pub username: Username,
+}
+
 impl CredentialsPayload {
   pub fn new(username: &str, password: &str, next: Option<String>) -> Self {
     Self { username: username.into(), password: password.into(), next }
@@ -144,3 +193,17 @@ impl CredentialsPayload {
 
   pub fn bob() -> Self { Self::new("bob", "password", None) }
 }
+
+/// Query for `GET /users/{username}/karma-history`
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, IntoParams)]
+pub struct KarmaHistoryQuery {
+  /// RFC 3339; defaults to 30 buckets before `to`
+  #[param(value_type = Option<String>)]
+  pub from:   Option<Timestamp>,
+  /// RFC 3339; defaults to now
+  #[param(value_type = Option<String>)]
+  pub to:     Option<Timestamp>,
+  /// `day` or `week`; defaults to `day`
+  #[serde(default)]
+  pub bucket: KarmaBucket,
+}
@@ -1,3 +1,18 @@
+use db::{
+  models::{
+    comment::Comment,
+    data_export::DataExport,
+    invite_code::{InviteCode, Invitee},
+    item::Item,
+    karma::KarmaHistoryPoint,
+    moderation_log::ModerationLog,
+    notification::Notification,
+    user_favorite::UserFavorite,
+    user_vote::UserVote,
+  },
+  queries, DbPool,
+};
+
 use super::*;
 
 #[derive(Debug, Serialize, Deserialize, ToSchema, Default)]
@@ -19,9 +34,9 @@ pub struct GetUserResponse {
 }
 
 impl GetUserResponse {
-  pub fn new(user: User, session_user: Option<User>) -> Self {
+  pub fn new(user: User, session_user: Option<User>, privileges: &PrivilegeTable) -> Self {
     let authentication_match = session_user.as_ref().map_or(false, |u| u.username == user.username);
-    let auth_user = AuthUserResponseInternal::new(session_user);
+    let auth_user = AuthUserResponseInternal::new(session_user, privileges);
     let email = user.email.filter(|_| authentication_match);
     let show_dead = Some(user.show_dead).filter(|_| authentication_match);
     Self {
@@ -42,19 +57,23 @@ impl GetUserResponse {
 #[serde(rename_all = "camelCase")]
 #[schema(default = AuthenticateUserResponse::default, example=AuthenticateUserResponse::default)]
 pub struct AuthenticateUserResponse {
-  pub username:       Username,
-  pub banned:         bool,
-  pub karma:          i32,
-  pub contains_email: bool,
-  pub show_dead:      bool,
-  pub is_moderator:   bool,
+  pub username:             Username,
+  pub banned:               bool,
+  pub karma:                i32,
+  pub contains_email:       bool,
+  pub show_dead:            bool,
+  pub is_moderator:         bool,
   // shadow banned removed
-  auth_user:          AuthUserResponseInternal,
+  auth_user:                AuthUserResponseInternal,
+  pub unread_notifications: i64,
+  /// The privileges the user's karma has unlocked
+  pub privileges:           Vec<Privilege>,
 }
 
 impl AuthenticateUserResponse {
-  pub fn new(session_user: User) -> Self {
-    let auth_user = AuthUserResponseInternal::new(Some(session_user.clone()));
+  pub fn new(session_user: User, unread_notifications: i64, privileges: &PrivilegeTable) -> Self {
+    let auth_user = AuthUserResponseInternal::new(Some(session_user.clone()), privileges);
+    let privileges = privileges.unlocked(&session_user);
     Self {
       username: session_user.username,
       banned: session_user.banned,
@@ -63,6 +82,8 @@ impl AuthenticateUserResponse {
       show_dead: session_user.show_dead,
       is_moderator: session_user.is_moderator,
       auth_user,
+      unread_notifications,
+      privileges,
     }
   }
 }
@@ -88,7 +109,7 @@ pub struct AuthUserResponseInternal {
 
 impl AuthUserResponseInternal {
   /// Create a new AuthLocal from a User
-  pub fn new(session_user: Option<User>) -> Self {
+  pub fn new(session_user: Option<User>, privileges: &PrivilegeTable) -> Self {
     if let Some(user) = session_user {
       Self {
         user_signed_in:   true,
@@ -96,7 +117,7 @@ impl AuthUserResponseInternal {
         karma:            Some(user.karma),
         contains_email:   Some(user.email.is_some()),
         show_dead:        user.show_dead,
-        show_downvote:    user.karma >= MINIMUM_KARMA_TO_DOWNVOTE,
+        show_downvote:    privileges.has(&user, Privilege::Downvote),
         is_moderator:     Some(user.is_moderator),
         banned:           user.banned,
         cookies_included: true,
@@ -109,3 +130,212 @@ impl AuthUserResponseInternal {
   /// Create a new AuthLocal without authentication
   pub fn new_unauthenticated(banned: bool) -> Self { Self { banned, ..Default::default() } }
 }
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Default)]
+#[serde(rename_all = "camelCase")]
+#[schema(default = UsernameAvailableResponse::default, example=UsernameAvailableResponse::default)]
+pub struct UsernameAvailableResponse {
+  pub username:  Username,
+  pub available: bool,
+}
+
+/// An active login session, as shown to its owner.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, Default)]
+#[serde(rename_all = "camelCase")]
+#[schema(default = UserSessionResponse::default, example=UserSessionResponse::default)]
+pub struct UserSessionResponse {
+  pub id:         Ulid,
+  pub user_agent: Option<String>,
+  pub created:    Timestamp,
+  pub last_seen:  Timestamp,
+  /// Whether this is the session making the request
+  pub current:    bool,
+}
+
+impl UserSessionResponse {
+  pub fn new(session: UserSession, current_session_id: Option<&str>) -> Self {
+    let current = current_session_id == Some(session.session_id.as_str());
+    Self {
+      id: session.id,
+      user_agent: session.user_agent,
+      created: session.created,
+      last_seen: session.last_seen,
+      current,
+    }
+  }
+}
+
+/// Everything we hold about a user, as returned by `export_user_data`. The bundle is streamed in
+/// this shape by `exports::stream_export`, rather than built whole.
+#[derive(Debug, Serialize, Deserialize, ToSchema, Default)]
+#[serde(rename_all = "camelCase")]
+#[schema(default = UserExport::default, example=UserExport::default)]
+pub struct UserExport {
+  pub exported:        Timestamp,
+  pub user:            ExportedUser,
+  pub items:           Vec<Item>,
+  pub comments:        Vec<Comment>,
+  pub votes:           Vec<UserVote>,
+  pub favorites:       Vec<UserFavorite>,
+  /// moderator actions taken against the user or their content
+  pub moderation_logs: Vec<ModerationLog>,
+}
+
+/// The user's own row, with credentials redacted.
+#[derive(Debug, Serialize, Deserialize, ToSchema, Default)]
+#[serde(rename_all = "camelCase")]
+#[schema(default = ExportedUser::default, example=ExportedUser::default)]
+pub struct ExportedUser {
+  pub username: Username,
+  pub email: Option<Email>,
+  pub created: Timestamp,
+  pub karma: i32,
+  pub about: Option<About>,
+  pub show_dead: bool,
+  pub is_moderator: bool,
+  pub banned: bool,
+  /// always redacted
+  pub password_hash: String,
+  /// redacted if present
+  pub reset_password_token: Option<String>,
+  pub reset_password_token_expiration: Option<Timestamp>,
+}
+
+impl From<User> for ExportedUser {
+  fn from(user: User) -> Self {
+    const REDACTED: &str = "[redacted]";
+    Self {
+      username: user.username,
+      email: user.email,
+      created: user.created,
+      karma: user.karma,
+      about: user.about,
+      show_dead: user.show_dead,
+      is_moderator: user.is_moderator,
+      banned: user.banned,
+      password_hash: REDACTED.to_string(),
+      reset_password_token: user.reset_password_token.map(|_| REDACTED.to_string()),
+      reset_password_token_expiration: user.reset_password_token_expiration,
+    }
+  }
+}
+
+/// Returned while a data export is being generated in the background.
+#[derive(Debug, Serialize, Deserialize, ToSchema, Default)]
+#[serde(rename_all = "camelCase")]
+#[schema(default = DataExportPendingResponse::default, example=DataExportPendingResponse::default)]
+pub struct DataExportPendingResponse {
+  pub id:           Ulid,
+  /// poll this url until the export is ready
+  pub download_url: String,
+}
+
+impl DataExportPendingResponse {
+  pub fn new(data_export: &DataExport) -> Self {
+    let download_url = format!("/users/export/{}", data_export.id);
+    Self { id: data_export.id.clone(), download_url }
+  }
+}
+
+/// A page of the user's reply notifications.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, Default)]
+#[serde(rename_all = "camelCase")]
+#[schema(default = NotificationsResponse::default, example=NotificationsResponse::default)]
+pub struct NotificationsResponse {
+  pub notifications: Vec<Notification>,
+  pub unread_count:  i64,
+  pub page:          i64,
+  /// Whether there may be another page
+  pub is_more:       bool,
+}
+
+impl NotificationsResponse {
+  pub fn new(notifications: Vec<Notification>, unread_count: i64, page: Page) -> Self {
+    let is_more = notifications.len() as i64 == db::queries::NOTIFICATIONS_PAGE_SIZE;
+    Self { notifications, unread_count, page: page.page, is_more }
+  }
+}
+
+/// An invite code minted by the caller.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, Default)]
+#[serde(rename_all = "camelCase")]
+#[schema(default = InviteCodeResponse::default, example=InviteCodeResponse::default)]
+pub struct InviteCodeResponse {
+  pub code:    String,
+  pub created: Timestamp,
+  /// the user who registered with the code, once used
+  pub used_by: Option<Username>,
+  pub used:    Option<Timestamp>,
+}
+
+impl From<InviteCode> for InviteCodeResponse {
+  fn from(invite_code: InviteCode) -> Self {
+    let InviteCode { code, created, used_by, used, .. } = invite_code;
+    Self { code, created, used_by, used }
+  }
+}
+
+/// Who invited a user, and who they invited in turn.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, Default)]
+#[serde(rename_all = "camelCase")]
+#[schema(default = InviteTreeResponse::default, example=InviteTreeResponse::default)]
+pub struct InviteTreeResponse {
+  pub username:   Username,
+  /// None if the user registered without an invite code
+  pub invited_by: Option<Username>,
+  /// everyone invited by the user, directly or transitively, breadth first
+  pub invitees:   Vec<InviteeResponse>,
+}
+
+impl InviteTreeResponse {
+  pub fn new(username: Username, invited_by: Option<Username>, invitees: Vec<Invitee>) -> Self {
+    let invitees = invitees.into_iter().map(InviteeResponse::from).collect();
+    Self { username, invited_by, invitees }
+  }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, Default)]
+#[serde(rename_all = "camelCase")]
+#[schema(default = InviteeResponse::default, example=InviteeResponse::default)]
+pub struct InviteeResponse {
+  pub username:   Username,
+  pub invited_by: Username,
+  /// 1 for users invited directly, 2 for those they invited, and so on
+  pub depth:      i32,
+  pub joined:     Timestamp,
+}
+
+impl From<Invitee> for InviteeResponse {
+  fn from(invitee: Invitee) -> Self {
+    let Invitee { username, invited_by, depth, joined } = invitee;
+    Self { username, invited_by, depth, joined }
+  }
+}
+
+/// A user's karma over time, one point per bucket.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, Default)]
+#[serde(rename_all = "camelCase")]
+#[schema(default = KarmaHistoryResponse::default, example=KarmaHistoryResponse::default)]
+pub struct KarmaHistoryResponse {
+  pub username: Username,
+  pub bucket:   KarmaBucket,
+  pub points:   Vec<KarmaHistoryPointResponse>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, Default)]
+#[serde(rename_all = "camelCase")]
+#[schema(default = KarmaHistoryPointResponse::default, example=KarmaHistoryPointResponse::default)]
+pub struct KarmaHistoryPointResponse {
+  /// the start of the bucket
+  pub bucket: Timestamp,
+  /// the net change over the bucket
+  pub delta:  i32,
+  /// karma at the end of the bucket
+  pub karma:  i32,
+}
+
+impl From<KarmaHistoryPoint> for KarmaHistoryPointResponse {
+  fn from(p: KarmaHistoryPoint) -> Self {
+    Self { bucket: p.bucket, delta: p.delta, karma: p.karma }
+  }
+}
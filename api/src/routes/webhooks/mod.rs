@@ -0,0 +1,169 @@
+pub(super) mod payload;
+pub(super) mod response;
+
+use axum::{
+  extract::{Path, Query, State},
+  http::StatusCode,
+  routing, Json, Router,
+};
+use db::{
+  models::{
+    user::User,
+    webhook::{Webhook, WebhookDelivery, WebhookDeliveryStatus, WebhookEvent},
+  },
+  Page, Timestamp, Ulid, Url, Username,
+};
+use garde::Validate;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, trace};
+use utoipa::ToSchema;
+
+pub use self::{payload::*, response::*};
+use super::SharedState;
+use crate::{
+  auth::{AuthSession, AuthenticationExt},
+  error::ApiError,
+  webhooks::{assert_webhook_url_allowed, generate_secret},
+  ApiResult,
+};
+
+/// Router to be mounted at "/webhooks"
+pub(super) fn webhooks_router(state: SharedState) -> Router {
+  Router::new()
+    .route("/", routing::get(get::get_webhooks).post(post::create_webhook))
+    .route("/:id", routing::delete(delete::delete_webhook))
+    .route("/:id/deliveries", routing::get(get::get_webhook_deliveries))
+    .with_state(state)
+}
+
+/// Get the webhook, if the caller registered it or is a moderator.
+async fn get_assert_owned_webhook(
+  state: &SharedState,
+  session_user: &User,
+  id: &Ulid,
+) -> ApiResult<Webhook> {
+  let webhook = db::queries::webhooks::get_assert_webhook(&state.pool, id).await?;
+  if webhook.username != session_user.username && !session_user.is_moderator {
+    // don't reveal that someone else's webhook exists
+    return Err(ApiError::DbEntryNotFound("webhook".into()));
+  }
+  Ok(webhook)
+}
+
+pub(super) mod get {
+  use super::*;
+
+  #[utoipa::path(
+      get,
+      path = "/webhooks",
+      responses(
+        (status = 401, description = "Not logged in"),
+        (status = 403, description = "Banned"),
+        (status = 200, body = [WebhookResponse]),
+      ),
+  )]
+  /// List the caller's webhooks. Moderators see every webhook.
+  pub async fn get_webhooks(
+    State(state): State<SharedState>,
+    auth_session: AuthSession,
+  ) -> ApiResult<Json<Vec<WebhookResponse>>> {
+    let session_user = auth_session.get_assert_user_from_session()?;
+    let username = (!session_user.is_moderator).then_some(&session_user.username);
+    let webhooks = db::queries::webhooks::get_webhooks(&state.pool, username).await?;
+
+    Ok(Json(webhooks.into_iter().map(WebhookResponse::new).collect()))
+  }
+
+  #[utoipa::path(
+      get,
+      path = "/webhooks/{id}/deliveries",
+      params( ("id" = String, Path, example = Ulid::new), Page ),
+      responses(
+        (status = 401, description = "Not logged in"),
+        (status = 403, description = "Banned"),
+        (status = 404, description = "Webhook not found"),
+        (status = 422, description = "Invalid id or page"),
+        (status = 200, body = [WebhookDeliveryResponse]),
+      ),
+  )]
+  /// Get the `page` of a webhook's delivery log, newest first.
+  pub async fn get_webhook_deliveries(
+    State(state): State<SharedState>,
+    auth_session: AuthSession,
+    Path(id): Path<Ulid>,
+    Query(page): Query<Page>,
+  ) -> ApiResult<Json<Vec<WebhookDeliveryResponse>>> {
+    id.validate(&())?;
+    page.validate(&())?;
+    let session_user = auth_session.get_assert_user_from_session()?;
+    let webhook = get_assert_owned_webhook(&state, &session_user, &id).await?;
+    let deliveries =
+      db::queries::webhooks::get_webhook_deliveries(&state.pool, &webhook.id, &page).await?;
+
+    Ok(Json(deliveries.into_iter().map(WebhookDeliveryResponse::from).collect()))
+  }
+}
+
+pub(super) mod post {
+  use super::*;
+
+  #[utoipa::path(
+      post,
+      path = "/webhooks",
+      request_body = CreateWebhookPayload,
+      responses(
+        (status = 400, description = "Url not allowed"),
+        (status = 401, description = "Not logged in"),
+        (status = 403, description = "Banned"),
+        (status = 422, description = "Invalid Payload"),
+        (status = 200, body = WebhookResponse),
+      ),
+  )]
+  /// Register a webhook. The response includes the signing secret, which is not shown again.
+  pub async fn create_webhook(
+    State(state): State<SharedState>,
+    auth_session: AuthSession,
+    Json(payload): Json<CreateWebhookPayload>,
+  ) -> ApiResult<Json<WebhookResponse>> {
+    payload.validate(&())?;
+    let session_user = auth_session.get_assert_user_from_session()?;
+    assert_webhook_url_allowed(&payload.url.0, state.config.allow_private_webhook_urls).await?;
+
+    let webhook =
+      Webhook::new(session_user.username, payload.url, generate_secret(), &payload.events);
+    db::queries::webhooks::create_webhook(&state.pool, &webhook).await?;
+    debug!("created webhook {} for events: {:?}", webhook.id, webhook.events);
+
+    Ok(Json(WebhookResponse::with_secret(webhook)))
+  }
+}
+
+pub(super) mod delete {
+  use super::*;
+
+  #[utoipa::path(
+      delete,
+      path = "/webhooks/{id}",
+      params( ("id" = String, Path, example = Ulid::new) ),
+      responses(
+        (status = 401, description = "Not logged in"),
+        (status = 403, description = "Banned"),
+        (status = 404, description = "Webhook not found"),
+        (status = 422, description = "Invalid id"),
+        (status = 200, description = "Success"),
+      ),
+  )]
+  /// Delete a webhook and its delivery log. Pending deliveries are dropped.
+  pub async fn delete_webhook(
+    State(state): State<SharedState>,
+    auth_session: AuthSession,
+    Path(id): Path<Ulid>,
+  ) -> ApiResult<StatusCode> {
+    id.validate(&())?;
+    let session_user = auth_session.get_assert_user_from_session()?;
+    let webhook = get_assert_owned_webhook(&state, &session_user, &id).await?;
+    db::queries::webhooks::delete_webhook(&state.pool, &webhook.id).await?;
+
+    Ok(StatusCode::OK)
+  }
+}
@@ -0,0 +1,22 @@
+use super::*;
+
+/// Register a webhook for the given events.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[schema(default = CreateWebhookPayload::default, example=CreateWebhookPayload::default)]
+pub struct CreateWebhookPayload {
+  #[garde(dive)]
+  pub url:    Url,
+  #[garde(length(min = 1))]
+  pub events: Vec<WebhookEvent>,
+}
+
+impl Default for CreateWebhookPayload {
+  fn default() -> Self { Self::new("https://example.com/webhook", &[WebhookEvent::ItemCreated]) }
+}
+
+impl CreateWebhookPayload {
+  pub fn new(url: &str, events: &[WebhookEvent]) -> Self {
+    Self { url: url.into(), events: events.to_vec() }
+  }
+}
@@ -0,0 +1,69 @@
+use super::*;
+
+/// A registered webhook. The secret is only included in the response to `create_webhook`.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, Default)]
+#[serde(rename_all = "camelCase")]
+#[schema(default = WebhookResponse::default, example=WebhookResponse::default)]
+pub struct WebhookResponse {
+  pub id:       Ulid,
+  pub username: Username,
+  pub url:      Url,
+  pub events:   Vec<String>,
+  pub active:   bool,
+  pub created:  Timestamp,
+  /// HMAC-SHA256 key for verifying payload signatures
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub secret:   Option<String>,
+}
+
+impl WebhookResponse {
+  /// The webhook, without its secret.
+  pub fn new(webhook: Webhook) -> Self {
+    let Webhook { id, username, url, events, active, created, .. } = webhook;
+    Self { id, username, url, events, active, created, secret: None }
+  }
+
+  /// The webhook, with its secret, for its owner on creation.
+  pub fn with_secret(webhook: Webhook) -> Self {
+    let secret = Some(webhook.secret.clone());
+    Self { secret, ..Self::new(webhook) }
+  }
+}
+
+/// An attempted delivery, as shown in the delivery log.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, Default)]
+#[serde(rename_all = "camelCase")]
+#[schema(default = WebhookDeliveryResponse::default, example=WebhookDeliveryResponse::default)]
+pub struct WebhookDeliveryResponse {
+  pub id:              Ulid,
+  pub event:           String,
+  /// the JSON body, as signed
+  pub payload:         String,
+  pub status:          WebhookDeliveryStatus,
+  pub attempts:        i32,
+  /// when the delivery will next be attempted, if pending
+  pub next_attempt:    Option<Timestamp>,
+  pub response_status: Option<i32>,
+  pub error:           Option<String>,
+  pub created:         Timestamp,
+  pub delivered:       Option<Timestamp>,
+}
+
+impl From<WebhookDelivery> for WebhookDeliveryResponse {
+  fn from(delivery: WebhookDelivery) -> Self {
+    let next_attempt =
+      (delivery.status == WebhookDeliveryStatus::Pending).then_some(delivery.next_attempt);
+    Self {
+      id: delivery.id,
+      event: delivery.event,
+      payload: delivery.payload,
+      status: delivery.status,
+      attempts: delivery.attempts,
+      next_attempt,
+      response_status: delivery.response_status,
+      error: delivery.error,
+      created: delivery.created,
+      delivered: delivery.delivered,
+    }
+  }
+}
@@ -1,8 +1,22 @@
 //! auth layer depends on this, don't move to server
+use std::{
+  collections::HashMap,
+  sync::{Arc, Mutex},
+  time::Instant,
+};
+
+use axum::{
+  extract::{Request, State},
+  middleware::Next,
+  response::Response,
+};
 use axum_login::tower_sessions::{cookie::time::Duration, Expiry, SessionManagerLayer};
-use db::DbPool;
+use db::{queries::user_sessions, DbPool};
 use tower_sessions::{cookie::Key, service::SignedCookie, ExpiredDeletion};
 use tower_sessions_sqlx_store::PostgresStore;
+use tracing::warn;
+
+use crate::{auth::AuthSession, routes::SharedState};
 
 pub(super) type MySessionManagerLayer = SessionManagerLayer<PostgresStore, SignedCookie>;
 
@@ -25,3 +39,52 @@ pub(super) async fn create_migrate_session_layer(pool: DbPool, key: Key) -> MySe
     .with_expiry(Expiry::OnInactivity(Duration::days(1)))
     .with_signed(key)
 }
+
+/// A session's `last_seen` is written at most this often.
+const TOUCH_INTERVAL: tokio::time::Duration = tokio::time::Duration::from_secs(60); // todo(config)
+/// Prune stale entries once this many sessions are tracked.
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// When each session's `last_seen` was last written, so that busy clients don't write it on every
+/// request.
+#[derive(Debug, Clone, Default)]
+pub struct SessionActivity {
+  touched: Arc<Mutex<HashMap<String, Instant>>>,
+}
+
+impl SessionActivity {
+  pub fn new() -> Self { Self::default() }
+
+  /// Return whether the session's `last_seen` is due to be written, and if so, count it written.
+  fn is_due(&self, session_id: &str) -> bool {
+    let now = Instant::now();
+    let mut touched = self.touched.lock().expect("session activity lock poisoned");
+    if touched.get(session_id).is_some_and(|t| now.duration_since(*t) < TOUCH_INTERVAL) {
+      return false;
+    }
+    if touched.len() >= PRUNE_THRESHOLD {
+      touched.retain(|_, t| now.duration_since(*t) < TOUCH_INTERVAL);
+    }
+    touched.insert(session_id.to_string(), now);
+    true
+  }
+}
+
+/// Middleware recording that a logged in user's session was used, so that users can tell their
+/// sessions apart when revoking them. Failing to record it doesn't fail the request.
+pub(crate) async fn touch_session(
+  State(state): State<SharedState>,
+  auth_session: AuthSession,
+  request: Request,
+  next: Next,
+) -> Response {
+  if let (Some(_), Some(session_id)) = (&auth_session.user, auth_session.session.id()) {
+    let session_id = session_id.to_string();
+    if state.session_activity.is_due(&session_id) {
+      if let Err(e) = user_sessions::touch_user_session(&state.pool, &session_id).await {
+        warn!("failed to record session activity: {e}");
+      }
+    }
+  }
+  next.run(request).await
+}
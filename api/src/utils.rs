@@ -1,6 +1,6 @@
 use anyhow::{anyhow, Context};
 use chrono::{NaiveDate, NaiveDateTime, TimeDelta, Utc};
-use db::Timestamp;
+use db::{Timestamp, Username};
 use regex::Regex;
 use tracing::trace;
 use tracing_subscriber::{
@@ -11,18 +11,27 @@ use tracing_subscriber::{
 
 use crate::error::ApiError;
 
+/// Sanitize a title: remove HTML tags, and collapse runs of whitespace.
+pub fn sanitize_title(title: &str) -> String {
+  let re_tags = Regex::new(r"<[^>]+>").unwrap();
+  let title = re_tags.replace_all(title, "");
+  title.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
 /// Sanitize text:
 /// - Trim whitespace
 /// - Remove HTML tags
 /// - Parse Markdown
-/// - Linkify URLs
+/// - Linkify URLs and resolved `mentions`
 /// - Prevent XSS attacks with `ammonia`
-pub fn sanitize_text(text: &str) -> String {
+pub fn sanitize_text(text: &str, mentions: &[Username]) -> String {
   let mut text = text.to_string();
   text = text.trim().to_string();
   // Remove HTML Tags
   let re_tags = Regex::new(r"<[^>]+>").unwrap();
   text = re_tags.replace_all(&text, "").to_string();
+  // Link mentions to profiles
+  text = db::link_mentions(&text, mentions);
   // Replace Markdown-like Italic Syntax
   let re_italic = Regex::new(r"\*([^*]+)\*").unwrap();
   text = re_italic.replace_all(&text, "<i>$1</i>").to_string();
@@ -0,0 +1,216 @@
+//! Vote-ring and sockpuppet detection.
+//!
+//! A background job periodically looks over recent upvotes for three patterns:
+//! - clusters of accounts that repeatedly upvote each other within minutes
+//! - new accounts whose first actions are upvotes for a single author
+//! - bursts of upvotes on a single item
+//!
+//! Each pattern found is recorded once, as a flag in the moderators' review queue. Optionally,
+//! flagged votes are discounted as soon as they are found, so they stop counting toward points
+//! and karma; otherwise they are discounted if a moderator confirms the flag.
+use std::{
+  collections::{BTreeSet, HashMap},
+  time::Duration,
+};
+
+use chrono::TimeDelta;
+use db::{
+  models::{
+    user_vote::ItemOrComment,
+    vote_flag::{RecentUpvote, VoteFlag, VoteFlagKind},
+  },
+  DbPool, Timestamp,
+};
+use tracing::{error, info, warn};
+
+use crate::{ApiConfig, ApiResult};
+
+/// How far back each run looks. Accounts created within this window count as new.
+const LOOKBACK: Duration = Duration::from_secs(48 * 60 * 60);
+/// Upvotes between two accounts count as mutual if cast within this long of each other...
+const MUTUAL_WINDOW: Duration = Duration::from_secs(10 * 60);
+/// ...and the pair is flagged once this many of their upvotes are mutual.
+const MUTUAL_MIN_VOTES: usize = 4; // todo(config)
+/// A new account is flagged after this many upvotes for one author, before submitting anything.
+const NEW_ACCOUNT_MIN_VOTES: usize = 3; // todo(config)
+/// An item is flagged for receiving this many upvotes within `BURST_WINDOW`.
+const BURST_MIN_VOTES: usize = 10; // todo(config)
+const BURST_WINDOW: Duration = Duration::from_secs(5 * 60);
+
+/// Spawn the vote analysis job, if enabled.
+pub(crate) fn spawn_analysis_job(pool: DbPool, config: &ApiConfig) {
+  let Some(period) = config.vote_analysis_interval else { return };
+  let discount = config.discount_flagged_votes;
+  tokio::spawn(async move {
+    let mut interval = tokio::time::interval(period);
+    loop {
+      interval.tick().await;
+      match analyze_votes(&pool, discount).await {
+        Ok(flags) if !flags.is_empty() =>
+          warn!("vote analysis flagged {} new patterns, discounted: {discount}", flags.len()),
+        Ok(_) => info!("vote analysis found no new patterns"),
+        Err(e) => error!("vote analysis failed: {e}"),
+      }
+    }
+  });
+}
+
+/// Look over recent upvotes, and record any new suspicious patterns, discounting their votes if
+/// `discount`.
+///
+/// Return the flags recorded.
+pub(crate) async fn analyze_votes(pool: &DbPool, discount: bool) -> ApiResult<Vec<VoteFlag>> {
+  let since = Timestamp(Timestamp::now().0 - delta(LOOKBACK));
+  let upvotes = db::queries::vote_flags::get_recent_upvotes(pool, &since).await?;
+
+  let mut flags = mutual_upvotes(&upvotes);
+  flags.extend(new_account_votes(&upvotes, &since));
+  flags.extend(vote_bursts(&upvotes));
+  Ok(db::queries::vote_flags::create_vote_flags(pool, &flags, discount).await?)
+}
+
+/// Flag clusters of accounts, linked by pairs that repeatedly upvote each other.
+fn mutual_upvotes(upvotes: &[RecentUpvote]) -> Vec<VoteFlag> {
+  let window = delta(MUTUAL_WINDOW);
+  let mut by_pair: HashMap<(&str, &str), Vec<&RecentUpvote>> = HashMap::new();
+  for vote in upvotes {
+    by_pair.entry((vote.voter.0.as_str(), vote.author.0.as_str())).or_default().push(vote);
+  }
+
+  // pairs with enough upvotes returned within the window, and the votes involved
+  let mut pairs: Vec<(&str, &str, Vec<&RecentUpvote>)> = Vec::new();
+  for (&(a, b), a_to_b) in &by_pair {
+    let Some(b_to_a) = by_pair.get(&(b, a)) else { continue };
+    if a >= b {
+      continue;
+    }
+    let returned = |vote: &RecentUpvote, others: &[&RecentUpvote]| {
+      others.iter().any(|other| (other.created.0 - vote.created.0).abs() <= window)
+    };
+    let mutual: Vec<&RecentUpvote> = a_to_b
+      .iter()
+      .filter(|&&v| returned(v, b_to_a.as_slice()))
+      .chain(b_to_a.iter().filter(|&&v| returned(v, a_to_b.as_slice())))
+      .copied()
+      .collect();
+    if mutual.len() >= MUTUAL_MIN_VOTES {
+      pairs.push((a, b, mutual));
+    }
+  }
+
+  // join pairs sharing an account into clusters
+  let mut clusters: Vec<(BTreeSet<&str>, Vec<&RecentUpvote>)> = Vec::new();
+  for (a, b, votes) in pairs {
+    let (linked, rest): (Vec<_>, Vec<_>) =
+      clusters.into_iter().partition(|(members, _)| members.contains(&a) || members.contains(&b));
+    let mut cluster = (BTreeSet::from([a, b]), votes);
+    for (members, votes) in linked {
+      cluster.0.extend(members);
+      cluster.1.extend(votes);
+    }
+    clusters = rest;
+    clusters.push(cluster);
+  }
+
+  clusters
+    .into_iter()
+    .map(|(members, votes)| {
+      let usernames: Vec<String> = members.into_iter().map(String::from).collect();
+      let fingerprint = usernames.join(",");
+      VoteFlag::new(
+        VoteFlagKind::MutualUpvotes,
+        usernames,
+        None,
+        None,
+        vote_ids(&votes),
+        fingerprint,
+      )
+    })
+    .collect()
+}
+
+/// Flag authors whose upvotes come from new accounts that have voted for no one else, and have
+/// yet to submit anything.
+fn new_account_votes(upvotes: &[RecentUpvote], since: &Timestamp) -> Vec<VoteFlag> {
+  let mut by_voter: HashMap<&str, Vec<&RecentUpvote>> = HashMap::new();
+  for vote in upvotes {
+    let is_new = vote.voter_created >= *since;
+    let before_submitting = vote.voter_first_submission.map_or(true, |first| vote.created < first);
+    if is_new && before_submitting {
+      by_voter.entry(vote.voter.0.as_str()).or_default().push(vote);
+    }
+  }
+
+  let mut by_author: HashMap<&str, (BTreeSet<&str>, Vec<&RecentUpvote>)> = HashMap::new();
+  for (voter, votes) in by_voter {
+    let author = votes[0].author.0.as_str();
+    if votes.len() >= NEW_ACCOUNT_MIN_VOTES && votes.iter().all(|v| v.author.0 == author) {
+      let (voters, author_votes) = by_author.entry(author).or_default();
+      voters.insert(voter);
+      author_votes.extend(votes);
+    }
+  }
+
+  by_author
+    .into_iter()
+    .map(|(author, (voters, votes))| {
+      let usernames: Vec<String> = voters.into_iter().map(String::from).collect();
+      let fingerprint = format!("{author}:{}", usernames.join(","));
+      VoteFlag::new(
+        VoteFlagKind::NewAccountVotes,
+        usernames,
+        Some(author.to_string()),
+        None,
+        vote_ids(&votes),
+        fingerprint,
+      )
+    })
+    .collect()
+}
+
+/// Flag items with a burst of upvotes, recording the votes in the densest window.
+fn vote_bursts(upvotes: &[RecentUpvote]) -> Vec<VoteFlag> {
+  let window = delta(BURST_WINDOW);
+  let mut by_item: HashMap<&str, Vec<&RecentUpvote>> = HashMap::new();
+  for vote in upvotes.iter().filter(|v| v.vote_type == ItemOrComment::Item) {
+    by_item.entry(vote.content_id.0.as_str()).or_default().push(vote);
+  }
+
+  by_item
+    .into_iter()
+    .filter_map(|(item_id, votes)| {
+      // votes are ordered by creation; slide a window over them
+      let mut start = 0;
+      let mut densest = 0..0;
+      for end in 0..votes.len() {
+        while votes[end].created.0 - votes[start].created.0 > window {
+          start += 1;
+        }
+        if end + 1 - start > densest.len() {
+          densest = start..end + 1;
+        }
+      }
+      let burst = &votes[densest];
+      (burst.len() >= BURST_MIN_VOTES).then(|| {
+        let mut usernames: Vec<String> = burst.iter().map(|v| v.voter.0.clone()).collect();
+        usernames.sort();
+        usernames.dedup();
+        VoteFlag::new(
+          VoteFlagKind::VoteBurst,
+          usernames,
+          None,
+          Some(item_id.to_string()),
+          vote_ids(burst),
+          item_id.to_string(),
+        )
+      })
+    })
+    .collect()
+}
+
+fn vote_ids(votes: &[&RecentUpvote]) -> Vec<String> {
+  let ids: BTreeSet<&str> = votes.iter().map(|v| v.id.0.as_str()).collect();
+  ids.into_iter().map(String::from).collect()
+}
+
+fn delta(duration: Duration) -> TimeDelta { TimeDelta::from_std(duration).expect("in range") }
@@ -0,0 +1,248 @@
+//! Outgoing webhook delivery.
+//!
+//! Events are queued in `webhook_deliveries` by the db transaction that makes the change. A
+//! background worker polls the queue, POSTs each payload to its webhook signed with the webhook's
+//! secret, and reschedules failed deliveries with exponential backoff until they are given up on.
+//!
+//! Receivers verify a delivery by computing the HMAC-SHA256 of the raw request body, keyed by the
+//! webhook secret, and comparing it against the `X-Zkhn-Signature` header: `sha256=<hex digest>`.
+use std::{
+  net::{IpAddr, SocketAddr},
+  sync::Arc,
+  time::Duration,
+};
+
+use db::{
+  models::webhook::{Webhook, WebhookDelivery, WebhookDeliveryStatus},
+  DbPool, Timestamp,
+};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use tracing::{debug, error, warn};
+
+use crate::{ApiConfig, ApiError, ApiResult};
+
+/// Give up on a delivery after this many attempts.
+const MAX_ATTEMPTS: i32 = 8; // todo(config)
+/// The first retry waits this long, doubling with each further attempt.
+const BASE_BACKOFF: Duration = Duration::from_secs(30);
+/// Claimed deliveries are retried after this long if the worker dies before recording them.
+const LEASE: Duration = Duration::from_secs(5 * 60);
+/// Deliveries claimed per poll.
+const BATCH_SIZE: i64 = 50;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub const SIGNATURE_HEADER: &str = "X-Zkhn-Signature";
+pub const EVENT_HEADER: &str = "X-Zkhn-Event";
+pub const DELIVERY_HEADER: &str = "X-Zkhn-Delivery";
+
+/// The `X-Zkhn-Signature` header value for `body`.
+pub fn sign(secret: &str, body: &str) -> String {
+  let mut mac =
+    Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("hmac accepts keys of any length");
+  mac.update(body.as_bytes());
+  format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Generate a webhook secret.
+pub fn generate_secret() -> String { hex::encode(rand::random::<[u8; 32]>()) }
+
+/// Reject webhook urls that aren't http(s), or, unless `allow_private`, that target loopback or
+/// private network hosts.
+///
+/// Hostnames are resolved, and rejected if any of their addresses is private. As a host may be
+/// re-pointed after registration, deliveries check its addresses again, see `PublicResolver`.
+pub async fn assert_webhook_url_allowed(url: &str, allow_private: bool) -> ApiResult<()> {
+  let url = url::Url::parse(url).map_err(|e| ApiError::BadRequest(format!("invalid url: {e}")))?;
+  if !matches!(url.scheme(), "http" | "https") {
+    return Err(ApiError::BadRequest("webhook url must be http or https".into()));
+  }
+  if allow_private {
+    return Ok(());
+  }
+
+  assert_host_not_private(&url)?;
+  if let Some(url::Host::Domain(domain)) = url.host() {
+    resolve_public(domain, url.port_or_known_default().unwrap_or(0)).await?;
+  }
+  Ok(())
+}
+
+/// Reject urls without a host, or whose host is `localhost` or a private literal address.
+/// Other hostnames must be resolved to be checked.
+fn assert_host_not_private(url: &url::Url) -> ApiResult<()> {
+  let is_private = match url.host() {
+    None => true,
+    Some(url::Host::Domain(domain)) =>
+      domain.eq_ignore_ascii_case("localhost") || domain.ends_with(".localhost"),
+    Some(url::Host::Ipv4(ip)) => is_private_ip(IpAddr::V4(ip)),
+    Some(url::Host::Ipv6(ip)) => is_private_ip(IpAddr::V6(ip)),
+  };
+  if is_private {
+    return Err(ApiError::BadRequest("webhook url may not target a private address".into()));
+  }
+  Ok(())
+}
+
+/// Resolve `host`, rejecting it if any of its addresses is private.
+async fn resolve_public(host: &str, port: u16) -> ApiResult<Vec<SocketAddr>> {
+  let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port))
+    .await
+    .map_err(|e| ApiError::BadRequest(format!("failed to resolve webhook host: {e}")))?
+    .collect();
+  if addrs.is_empty() {
+    return Err(ApiError::BadRequest("webhook host has no addresses".into()));
+  }
+  if addrs.iter().any(|addr| is_private_ip(addr.ip())) {
+    return Err(ApiError::BadRequest("webhook url may not target a private address".into()));
+  }
+  Ok(addrs)
+}
+
+/// Resolves hostnames for the delivery client, failing for any host with a private address.
+///
+/// The client connects only to the addresses checked here, so a host re-pointed at our network
+/// since registration, or answering differently on each lookup, can't be delivered to.
+struct PublicResolver;
+
+impl reqwest::dns::Resolve for PublicResolver {
+  fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+    Box::pin(async move {
+      // the client sets the port
+      let addrs = resolve_public(name.as_str(), 0).await.map_err(|e| e.to_string())?;
+      Ok(Box::new(addrs.into_iter()) as reqwest::dns::Addrs)
+    })
+  }
+}
+
+fn is_private_ip(ip: IpAddr) -> bool {
+  match ip {
+    IpAddr::V4(ip) =>
+      ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast(),
+    IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+      Some(ip) => is_private_ip(IpAddr::V4(ip)),
+      // unique local (fc00::/7) and link local (fe80::/10)
+      None =>
+        ip.is_loopback()
+          || ip.is_unspecified()
+          || (ip.segments()[0] & 0xfe00) == 0xfc00
+          || (ip.segments()[0] & 0xffc0) == 0xfe80,
+    },
+  }
+}
+
+/// Spawn the background task delivering queued webhook events.
+pub(crate) fn spawn_delivery_worker(pool: DbPool, config: &ApiConfig) {
+  let poll_interval = config.webhook_poll_interval;
+  let allow_private = config.allow_private_webhook_urls;
+  tokio::spawn(async move {
+    let mut client = reqwest::Client::builder()
+      .timeout(REQUEST_TIMEOUT)
+      // don't let a public url redirect us somewhere private
+      .redirect(reqwest::redirect::Policy::none());
+    if !allow_private {
+      client = client.dns_resolver(Arc::new(PublicResolver));
+    }
+    let client = client.build().expect("failed to build webhook client");
+
+    let mut interval = tokio::time::interval(poll_interval);
+    loop {
+      interval.tick().await;
+      if let Err(e) = deliver_due(&pool, &client, allow_private).await {
+        error!("webhook delivery failed: {e}");
+      }
+    }
+  });
+}
+
+/// Attempt every due delivery once.
+async fn deliver_due(
+  pool: &DbPool,
+  client: &reqwest::Client,
+  allow_private: bool,
+) -> ApiResult<()> {
+  let lease_until = Timestamp::now() + chrono::Duration::from_std(LEASE).unwrap();
+  let due =
+    db::queries::webhooks::claim_due_webhook_deliveries(pool, BATCH_SIZE, &lease_until).await?;
+
+  let attempts = due.into_iter().map(|(delivery, webhook)| async move {
+    let result = attempt(client, &delivery, &webhook, allow_private).await;
+    record(pool, &delivery, result).await
+  });
+  for result in futures::future::join_all(attempts).await {
+    if let Err(e) = result {
+      error!("failed to record webhook attempt: {e}");
+    }
+  }
+  Ok(())
+}
+
+/// POST the delivery. Ok with the response status on a 2xx response, otherwise Err with the
+/// response status if there was one, and the reason.
+async fn attempt(
+  client: &reqwest::Client,
+  delivery: &WebhookDelivery,
+  webhook: &Webhook,
+  allow_private: bool,
+) -> Result<i32, (Option<i32>, String)> {
+  debug!("delivering webhook {} to {}", delivery.id, webhook.url.0);
+  // hostnames are checked as they're resolved, see `PublicResolver`
+  if !allow_private {
+    let url = url::Url::parse(&webhook.url.0).map_err(|e| (None, e.to_string()))?;
+    assert_host_not_private(&url).map_err(|e| (None, e.to_string()))?;
+  }
+  let response = client
+    .post(&webhook.url.0)
+    .header(reqwest::header::CONTENT_TYPE, "application/json")
+    .header(SIGNATURE_HEADER, sign(&webhook.secret, &delivery.payload))
+    .header(EVENT_HEADER, &delivery.event)
+    .header(DELIVERY_HEADER, &delivery.id.0)
+    .body(delivery.payload.clone())
+    .send()
+    .await
+    .map_err(|e| (None, e.to_string()))?;
+
+  let status = i32::from(response.status().as_u16());
+  if response.status().is_success() {
+    Ok(status)
+  } else {
+    Err((Some(status), format!("receiver responded with {status}")))
+  }
+}
+
+async fn record(
+  pool: &DbPool,
+  delivery: &WebhookDelivery,
+  result: Result<i32, (Option<i32>, String)>,
+) -> ApiResult<()> {
+  let now = Timestamp::now();
+  let (status, response_status, error, next_attempt) = match result {
+    Ok(response_status) => (WebhookDeliveryStatus::Delivered, Some(response_status), None, now),
+    Err((response_status, error)) => {
+      let attempts = delivery.attempts + 1;
+      if attempts >= MAX_ATTEMPTS {
+        warn!("giving up on webhook delivery {} after {attempts} attempts", delivery.id);
+        (WebhookDeliveryStatus::Failed, response_status, Some(error), now)
+      } else {
+        let backoff = BASE_BACKOFF * 2u32.pow(attempts as u32 - 1);
+        let next_attempt = now + chrono::Duration::from_std(backoff).unwrap();
+        (WebhookDeliveryStatus::Pending, response_status, Some(error), next_attempt)
+      }
+    },
+  };
+
+  db::queries::webhooks::record_webhook_attempt(
+    pool,
+    &delivery.id,
+    status,
+    response_status,
+    error,
+    &next_attempt,
+  )
+  .await?;
+  Ok(())
+}
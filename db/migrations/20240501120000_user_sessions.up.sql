@@ -0,0 +1,15 @@
+-- Metadata for the sessions held in the tower-sessions store (`tower_sessions.session`).
+-- The store only keeps an opaque id, the session data, and an expiry date, so we record who owns
+-- each session and which device it was created on alongside it.
+DROP TABLE IF EXISTS user_sessions;
+CREATE TABLE user_sessions (
+    -- public identifier, so that the session id (the cookie secret) is never sent to clients
+    id VARCHAR(26) PRIMARY KEY,
+    session_id TEXT UNIQUE NOT NULL,
+    username TEXT NOT NULL,
+    user_agent TEXT,
+    created TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    last_seen TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_user_sessions_username ON user_sessions (username);
@@ -0,0 +1,3 @@
+-- Usernames are unique regardless of case, so "Alice" and "alice" cannot both register.
+-- Any existing case-insensitive duplicates must be resolved by hand before this will apply.
+CREATE UNIQUE INDEX users_username_lower_idx ON users (LOWER(username));
@@ -0,0 +1,15 @@
+-- Comments on items, mirroring `models::comment::Comment`.
+CREATE TABLE IF NOT EXISTS comments (
+    id VARCHAR(26) PRIMARY KEY,
+    username TEXT NOT NULL,
+    parent_item_id VARCHAR(26) NOT NULL,
+    parent_item_title TEXT NOT NULL,
+    comment_text TEXT NOT NULL,
+    is_parent BOOLEAN NOT NULL DEFAULT false,
+    root_comment_id VARCHAR(26) NOT NULL,
+    parent_comment_id VARCHAR(26),
+    children_count INT DEFAULT 0 NOT NULL,
+    points INT DEFAULT 1 CHECK (points >= -4) NOT NULL,
+    created TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    dead BOOLEAN DEFAULT false NOT NULL
+);
@@ -0,0 +1,5 @@
+-- Content of deleted accounts is reassigned to this placeholder, so that threads stay intact.
+-- The password hash is not a valid argon2 hash, so the placeholder can never log in.
+INSERT INTO users (username, password_hash, banned)
+VALUES ('[deleted]', '!', true)
+ON CONFLICT DO NOTHING;
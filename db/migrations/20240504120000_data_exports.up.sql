@@ -0,0 +1,41 @@
+-- Moderator actions, mirroring `models::moderation_log::ModerationLog`. Data exports include the
+-- actions taken against a user, so the table must exist even though moderation routes do not yet.
+CREATE TABLE IF NOT EXISTS moderation_logs (
+    id VARCHAR(26) PRIMARY KEY,
+    moderator_username TEXT NOT NULL,
+    action_type TEXT NOT NULL,
+    username TEXT,
+    item_id VARCHAR(26),
+    item_title TEXT,
+    item_by TEXT,
+    comment_id VARCHAR(26),
+    comment_by TEXT,
+    created TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+-- Personal data exports of large accounts, queued for the export worker to generate.
+DROP TABLE IF EXISTS data_exports;
+CREATE TABLE data_exports (
+    id VARCHAR(26) PRIMARY KEY,
+    username TEXT NOT NULL,
+    created TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    -- times the worker has started generating the export
+    attempts INT NOT NULL DEFAULT 0,
+    -- while generating, the worker leases the export until this time, so that an export
+    -- interrupted by a restart is picked up again once the lease expires
+    claimed_until TIMESTAMP WITH TIME ZONE,
+    -- set once generation finishes, successfully or not
+    completed TIMESTAMP WITH TIME ZONE,
+    -- the JSON bundle, set on success
+    data TEXT,
+    -- set on failure
+    error TEXT
+);
+
+-- stored uncompressed, so that downloads can read the bundle in slices without decompressing it
+-- whole for each
+ALTER TABLE data_exports ALTER COLUMN data SET STORAGE EXTERNAL;
+
+CREATE INDEX idx_data_exports_username ON data_exports (username);
+CREATE INDEX idx_data_exports_created ON data_exports (created);
+CREATE INDEX idx_data_exports_pending ON data_exports (created) WHERE completed IS NULL;
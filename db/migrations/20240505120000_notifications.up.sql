@@ -0,0 +1,18 @@
+-- Reply notifications: one row per comment replying to a user's item or comment.
+CREATE TABLE notifications (
+    id VARCHAR(26) PRIMARY KEY,
+    -- the user being notified
+    username TEXT NOT NULL,
+    -- the reply
+    comment_id VARCHAR(26) NOT NULL,
+    comment_by TEXT NOT NULL,
+    item_id VARCHAR(26) NOT NULL,
+    item_title TEXT NOT NULL,
+    -- set when the reply is to a comment rather than to the item itself
+    parent_comment_id VARCHAR(26),
+    read BOOLEAN NOT NULL DEFAULT false,
+    created TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_notifications_username_created ON notifications (username, created DESC);
+CREATE INDEX idx_notifications_unread ON notifications (username) WHERE NOT read;
@@ -0,0 +1,5 @@
+DELETE FROM notifications WHERE comment_id IS NULL;
+ALTER TABLE notifications
+    DROP COLUMN notification_type,
+    ALTER COLUMN comment_id SET NOT NULL;
+DROP TYPE IF EXISTS notification_type_enum;
@@ -0,0 +1,7 @@
+-- Notifications are also sent for @mentions, which may be in item text rather than a comment.
+DROP TYPE IF EXISTS notification_type_enum;
+CREATE TYPE notification_type_enum as ENUM ('reply', 'mention');
+
+ALTER TABLE notifications
+    ADD COLUMN notification_type NOTIFICATION_TYPE_ENUM NOT NULL DEFAULT 'reply',
+    ALTER COLUMN comment_id DROP NOT NULL;
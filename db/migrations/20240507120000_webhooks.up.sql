@@ -0,0 +1,39 @@
+-- Outgoing webhooks, registered by users or moderators.
+CREATE TABLE webhooks (
+    id VARCHAR(26) PRIMARY KEY,
+    -- the user who registered the webhook
+    username TEXT NOT NULL,
+    url TEXT NOT NULL,
+    -- HMAC-SHA256 key used to sign payloads
+    secret TEXT NOT NULL,
+    -- the event names subscribed to, e.g. 'item.created'
+    events TEXT[] NOT NULL,
+    active BOOLEAN NOT NULL DEFAULT true,
+    created TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_webhooks_username ON webhooks (username);
+
+DROP TYPE IF EXISTS webhook_delivery_status_enum;
+CREATE TYPE webhook_delivery_status_enum as ENUM ('pending', 'delivered', 'failed');
+
+-- The persistent delivery queue, which doubles as the delivery log.
+CREATE TABLE webhook_deliveries (
+    id VARCHAR(26) PRIMARY KEY,
+    webhook_id VARCHAR(26) NOT NULL REFERENCES webhooks (id) ON DELETE CASCADE,
+    event TEXT NOT NULL,
+    -- the signed JSON body
+    payload TEXT NOT NULL,
+    status WEBHOOK_DELIVERY_STATUS_ENUM NOT NULL DEFAULT 'pending',
+    attempts INT NOT NULL DEFAULT 0,
+    next_attempt TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    -- the HTTP status of the last attempt, if a response was received
+    response_status INT,
+    -- why the last attempt failed, if it did
+    error TEXT,
+    created TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    delivered TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX idx_webhook_deliveries_due ON webhook_deliveries (next_attempt) WHERE status = 'pending';
+CREATE INDEX idx_webhook_deliveries_webhook ON webhook_deliveries (webhook_id, created DESC);
@@ -0,0 +1,15 @@
+-- Invite codes for invite-only registration. A used code records who invited whom.
+CREATE TABLE invite_codes (
+    code TEXT PRIMARY KEY,
+    -- the user who minted the code
+    created_by TEXT NOT NULL,
+    created TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    -- the user who registered with the code, once used
+    used_by TEXT,
+    used TIMESTAMP WITH TIME ZONE
+);
+
+-- each user is invited at most once; codes of deleted accounts all point at the placeholder
+CREATE UNIQUE INDEX idx_invite_codes_used_by ON invite_codes (used_by) WHERE used_by <> '[deleted]';
+
+CREATE INDEX idx_invite_codes_created_by ON invite_codes (created_by);
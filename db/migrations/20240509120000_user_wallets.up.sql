@@ -0,0 +1,9 @@
+-- Ethereum addresses verified by Sign-In with Ethereum (EIP-4361), and the accounts they log in to.
+CREATE TABLE user_wallets (
+    -- lowercase, 0x-prefixed hex
+    address TEXT PRIMARY KEY,
+    username TEXT NOT NULL,
+    created TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_user_wallets_username ON user_wallets (username);
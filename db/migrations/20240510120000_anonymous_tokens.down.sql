@@ -0,0 +1,3 @@
+DROP TABLE IF EXISTS spent_anon_tokens;
+DROP TABLE IF EXISTS anon_token_issuances;
+DELETE FROM users WHERE username = '[anonymous]';
@@ -0,0 +1,18 @@
+-- Anonymous items and comments are authored by this placeholder, so the server never records who
+-- posted them. Like `[deleted]`, it can never log in.
+INSERT INTO users (username, password_hash, banned)
+VALUES ('[anonymous]', '!', true)
+ON CONFLICT DO NOTHING;
+
+-- How many blindly signed anonymous posting tokens each user has been issued per (UTC) day.
+CREATE TABLE anon_token_issuances (
+    username TEXT NOT NULL,
+    day DATE NOT NULL,
+    issued INT NOT NULL CHECK (issued >= 0),
+    PRIMARY KEY (username, day)
+);
+
+-- Hashes of redeemed tokens, to prevent double use. Deliberately unlinked from users and content.
+CREATE TABLE spent_anon_tokens (
+    token_hash TEXT PRIMARY KEY
+);
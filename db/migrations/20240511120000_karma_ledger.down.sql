@@ -0,0 +1,4 @@
+DROP TABLE IF EXISTS karma_discrepancies;
+DROP TABLE IF EXISTS karma_reconciliations;
+DROP TABLE IF EXISTS karma_ledger;
+DROP TYPE IF EXISTS karma_reason_enum;
@@ -0,0 +1,50 @@
+-- Every change to `users.karma`, and what caused it. The sum of a user's entries is their karma.
+DROP TYPE IF EXISTS karma_reason_enum;
+CREATE TYPE karma_reason_enum as ENUM (
+    'item_created',
+    'comment_created',
+    'vote',
+    'item_deleted',
+    'voter_deleted',
+    'reconciliation'
+);
+
+CREATE TABLE karma_ledger (
+    id BIGSERIAL PRIMARY KEY,
+    username TEXT NOT NULL,
+    -- the change actually applied; karma is never taken below zero
+    delta INT NOT NULL,
+    -- the change called for, which the floor at zero may have cut short
+    requested INT NOT NULL,
+    reason KARMA_REASON_ENUM NOT NULL,
+    -- the item or comment responsible, if any
+    content_id VARCHAR(26),
+    created TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_karma_ledger_username_created ON karma_ledger (username, created);
+
+-- Seed the ledger with each user's karma as of this migration, so the sums start out right.
+INSERT INTO karma_ledger (username, delta, requested, reason)
+SELECT username, karma, karma, 'reconciliation' FROM users WHERE karma <> 0;
+
+-- Runs of the reconciliation job, which recomputes karma from content and votes.
+CREATE TABLE karma_reconciliations (
+    id VARCHAR(26) PRIMARY KEY,
+    -- whether drift found was corrected, or only reported
+    corrected BOOLEAN NOT NULL,
+    users_checked INT NOT NULL,
+    discrepancy_count INT NOT NULL,
+    created TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+-- Users whose stored karma differed from the recomputed karma.
+CREATE TABLE karma_discrepancies (
+    reconciliation_id VARCHAR(26) NOT NULL,
+    username TEXT NOT NULL,
+    stored INT NOT NULL,
+    expected INT NOT NULL,
+    -- the sum of the user's ledger entries
+    ledger INT NOT NULL,
+    PRIMARY KEY (reconciliation_id, username)
+);
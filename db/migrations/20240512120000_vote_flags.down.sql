@@ -0,0 +1,6 @@
+DROP TABLE IF EXISTS vote_flags;
+DROP TYPE IF EXISTS vote_flag_status_enum;
+DROP TYPE IF EXISTS vote_flag_kind_enum;
+-- postgres can't drop an enum value, so 'vote_discounted' stays in karma_reason_enum. Points
+-- taken by discounted votes are not restored; karma reconciliation will report the drift.
+ALTER TABLE user_votes DROP COLUMN IF EXISTS discounted;
@@ -0,0 +1,33 @@
+-- Discounted votes no longer count toward points or karma. They are kept, and shown to their
+-- voter as cast.
+ALTER TABLE user_votes ADD COLUMN discounted BOOLEAN NOT NULL DEFAULT false;
+
+ALTER TYPE karma_reason_enum ADD VALUE 'vote_discounted';
+
+DROP TYPE IF EXISTS vote_flag_kind_enum;
+CREATE TYPE vote_flag_kind_enum AS ENUM ('mutual_upvotes', 'new_account_votes', 'vote_burst');
+DROP TYPE IF EXISTS vote_flag_status_enum;
+CREATE TYPE vote_flag_status_enum AS ENUM ('pending', 'confirmed', 'dismissed');
+
+-- Suspicious voting patterns found by the vote analysis job, for moderators to review.
+CREATE TABLE vote_flags (
+    id VARCHAR(26) PRIMARY KEY,
+    kind VOTE_FLAG_KIND_ENUM NOT NULL,
+    -- the voting accounts involved
+    usernames TEXT[] NOT NULL,
+    -- the author voted for, for new account votes
+    target_username TEXT,
+    -- the item voted on, for bursts
+    content_id VARCHAR(26),
+    vote_ids TEXT[] NOT NULL,
+    -- identifies the pattern, so that later runs don't flag it again
+    fingerprint TEXT NOT NULL,
+    status VOTE_FLAG_STATUS_ENUM NOT NULL DEFAULT 'pending',
+    discounted BOOLEAN NOT NULL DEFAULT false,
+    created TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    reviewed_by TEXT,
+    reviewed TIMESTAMP WITH TIME ZONE,
+    UNIQUE (kind, fingerprint)
+);
+
+CREATE INDEX idx_vote_flags_status_created ON vote_flags (status, created);
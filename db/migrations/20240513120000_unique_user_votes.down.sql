@@ -0,0 +1 @@
+ALTER TABLE user_votes DROP CONSTRAINT IF EXISTS user_votes_username_content_id_key;
@@ -0,0 +1,27 @@
+-- Concurrent votes could record a user's vote on an item more than once, counting it twice. Keep
+-- each user's newest vote on each item or comment, and recompute the points of items and comments
+-- that had duplicates, keeping comments at or above MIN_COMMENT_POINTS (-4). Karma drift from the
+-- duplicates is reported by the karma reconciliation job.
+CREATE TEMPORARY TABLE duplicated_votes ON COMMIT DROP AS
+SELECT DISTINCT content_id FROM user_votes
+GROUP BY username, content_id HAVING COUNT(*) > 1;
+
+DELETE FROM user_votes v
+USING user_votes newer
+WHERE newer.username = v.username AND newer.content_id = v.content_id
+  AND (newer.created, newer.id) > (v.created, v.id);
+
+UPDATE items SET points = 1 + COALESCE((
+  SELECT SUM(CASE vote_state WHEN 'upvote' THEN 1 WHEN 'downvote' THEN -1 ELSE 0 END)
+  FROM user_votes WHERE content_id = items.id AND NOT discounted
+), 0)
+WHERE id IN (SELECT content_id FROM duplicated_votes);
+
+UPDATE comments SET points = GREATEST(-4, 1 + COALESCE((
+  SELECT SUM(CASE vote_state WHEN 'upvote' THEN 1 WHEN 'downvote' THEN -1 ELSE 0 END)
+  FROM user_votes WHERE content_id = comments.id AND NOT discounted
+), 0))
+WHERE id IN (SELECT content_id FROM duplicated_votes);
+
+ALTER TABLE user_votes
+ADD CONSTRAINT user_votes_username_content_id_key UNIQUE (username, content_id);
@@ -0,0 +1,44 @@
+DROP INDEX IF EXISTS idx_items_username;
+DROP INDEX IF EXISTS idx_items_created;
+DROP INDEX IF EXISTS idx_comments_username;
+DROP INDEX IF EXISTS idx_comments_parent_item_id;
+DROP INDEX IF EXISTS idx_comments_created;
+DROP INDEX IF EXISTS idx_user_votes_content_id;
+DROP INDEX IF EXISTS idx_user_votes_item_id;
+DROP INDEX IF EXISTS idx_user_votes_comment_id;
+DROP INDEX IF EXISTS idx_user_votes_parent_item_id;
+DROP INDEX IF EXISTS idx_user_votes_created;
+DROP INDEX IF EXISTS idx_user_favorites_username;
+DROP INDEX IF EXISTS idx_user_favorites_item_id;
+DROP INDEX IF EXISTS idx_user_favorites_favorite_item_id;
+DROP INDEX IF EXISTS idx_user_favorites_favorite_comment_id;
+DROP INDEX IF EXISTS idx_notifications_item_id;
+
+ALTER TABLE karma_ledger DROP CONSTRAINT IF EXISTS fk_karma_ledger_users;
+ALTER TABLE anon_token_issuances DROP CONSTRAINT IF EXISTS fk_anon_token_issuances_users;
+ALTER TABLE user_wallets DROP CONSTRAINT IF EXISTS fk_user_wallets_users;
+ALTER TABLE data_exports DROP CONSTRAINT IF EXISTS fk_data_exports_users;
+ALTER TABLE webhooks DROP CONSTRAINT IF EXISTS fk_webhooks_users;
+ALTER TABLE notifications
+DROP CONSTRAINT IF EXISTS fk_notifications_users,
+DROP CONSTRAINT IF EXISTS fk_notifications_items;
+ALTER TABLE user_sessions DROP CONSTRAINT IF EXISTS fk_user_sessions_users;
+
+ALTER TABLE user_favorites
+DROP CONSTRAINT IF EXISTS fk_user_favorites_users,
+DROP CONSTRAINT IF EXISTS fk_user_favorites_items,
+DROP CONSTRAINT IF EXISTS fk_user_favorites_comments,
+DROP COLUMN IF EXISTS favorite_item_id,
+DROP COLUMN IF EXISTS favorite_comment_id;
+ALTER TABLE user_votes
+DROP CONSTRAINT IF EXISTS fk_user_votes_users,
+DROP CONSTRAINT IF EXISTS fk_user_votes_items,
+DROP CONSTRAINT IF EXISTS fk_user_votes_comments,
+DROP CONSTRAINT IF EXISTS fk_user_votes_parent_items,
+DROP COLUMN IF EXISTS item_id,
+DROP COLUMN IF EXISTS comment_id;
+
+ALTER TABLE comments
+DROP CONSTRAINT IF EXISTS fk_comments_users,
+DROP CONSTRAINT IF EXISTS fk_comments_items;
+ALTER TABLE items DROP CONSTRAINT IF EXISTS fk_items_users;
@@ -0,0 +1,106 @@
+-- Clean up rows left behind by deletes before foreign keys existed, then add the foreign keys.
+-- Content of users that no longer exist goes to the `[deleted]` placeholder, like `delete_user`
+-- does; everything else that belonged to a missing user or piece of content is dropped.
+UPDATE items SET username = '[deleted]'
+WHERE username NOT IN (SELECT username FROM users);
+UPDATE comments SET username = '[deleted]'
+WHERE username NOT IN (SELECT username FROM users);
+DELETE FROM comments WHERE parent_item_id NOT IN (SELECT id FROM items);
+
+DELETE FROM user_votes
+WHERE username NOT IN (SELECT username FROM users)
+  OR (vote_type = 'item' AND content_id NOT IN (SELECT id FROM items))
+  OR (vote_type = 'comment' AND content_id NOT IN (SELECT id FROM comments))
+  OR parent_item_id NOT IN (SELECT id FROM items);
+DELETE FROM user_favorites
+WHERE username NOT IN (SELECT username FROM users)
+  OR (item_type = 'item' AND item_id NOT IN (SELECT id FROM items))
+  OR (item_type = 'comment' AND item_id NOT IN (SELECT id FROM comments));
+
+DELETE FROM user_sessions WHERE username NOT IN (SELECT username FROM users);
+DELETE FROM notifications
+WHERE username NOT IN (SELECT username FROM users) OR item_id NOT IN (SELECT id FROM items);
+DELETE FROM webhooks WHERE username NOT IN (SELECT username FROM users);
+DELETE FROM data_exports WHERE username NOT IN (SELECT username FROM users);
+DELETE FROM user_wallets WHERE username NOT IN (SELECT username FROM users);
+DELETE FROM anon_token_issuances WHERE username NOT IN (SELECT username FROM users);
+DELETE FROM karma_ledger WHERE username NOT IN (SELECT username FROM users);
+
+-- Content is reassigned rather than deleted along with its author, so deleting a user who still
+-- owns items or comments is an error. Everything else that belongs to a user goes with them.
+ALTER TABLE items
+ADD CONSTRAINT fk_items_users FOREIGN KEY (username) REFERENCES users(username)
+  ON UPDATE CASCADE;
+ALTER TABLE comments
+ADD CONSTRAINT fk_comments_users FOREIGN KEY (username) REFERENCES users(username)
+  ON UPDATE CASCADE,
+ADD CONSTRAINT fk_comments_items FOREIGN KEY (parent_item_id) REFERENCES items(id)
+  ON DELETE CASCADE;
+
+-- Votes and favorites point at either an item or a comment, so each gets a generated column per
+-- content type for the foreign key to reference.
+ALTER TABLE user_votes
+ADD COLUMN item_id VARCHAR(26)
+  GENERATED ALWAYS AS (CASE WHEN vote_type = 'item' THEN content_id END) STORED,
+ADD COLUMN comment_id VARCHAR(26)
+  GENERATED ALWAYS AS (CASE WHEN vote_type = 'comment' THEN content_id END) STORED,
+ADD CONSTRAINT fk_user_votes_users FOREIGN KEY (username) REFERENCES users(username)
+  ON UPDATE CASCADE ON DELETE CASCADE,
+ADD CONSTRAINT fk_user_votes_items FOREIGN KEY (item_id) REFERENCES items(id) ON DELETE CASCADE,
+ADD CONSTRAINT fk_user_votes_comments FOREIGN KEY (comment_id) REFERENCES comments(id)
+  ON DELETE CASCADE,
+ADD CONSTRAINT fk_user_votes_parent_items FOREIGN KEY (parent_item_id) REFERENCES items(id)
+  ON DELETE CASCADE;
+ALTER TABLE user_favorites
+ADD COLUMN favorite_item_id VARCHAR(26)
+  GENERATED ALWAYS AS (CASE WHEN item_type = 'item' THEN item_id END) STORED,
+ADD COLUMN favorite_comment_id VARCHAR(26)
+  GENERATED ALWAYS AS (CASE WHEN item_type = 'comment' THEN item_id END) STORED,
+ADD CONSTRAINT fk_user_favorites_users FOREIGN KEY (username) REFERENCES users(username)
+  ON UPDATE CASCADE ON DELETE CASCADE,
+ADD CONSTRAINT fk_user_favorites_items FOREIGN KEY (favorite_item_id) REFERENCES items(id)
+  ON DELETE CASCADE,
+ADD CONSTRAINT fk_user_favorites_comments FOREIGN KEY (favorite_comment_id)
+  REFERENCES comments(id) ON DELETE CASCADE;
+
+ALTER TABLE user_sessions
+ADD CONSTRAINT fk_user_sessions_users FOREIGN KEY (username) REFERENCES users(username)
+  ON UPDATE CASCADE ON DELETE CASCADE;
+ALTER TABLE notifications
+ADD CONSTRAINT fk_notifications_users FOREIGN KEY (username) REFERENCES users(username)
+  ON UPDATE CASCADE ON DELETE CASCADE,
+ADD CONSTRAINT fk_notifications_items FOREIGN KEY (item_id) REFERENCES items(id)
+  ON DELETE CASCADE;
+ALTER TABLE webhooks
+ADD CONSTRAINT fk_webhooks_users FOREIGN KEY (username) REFERENCES users(username)
+  ON UPDATE CASCADE ON DELETE CASCADE;
+ALTER TABLE data_exports
+ADD CONSTRAINT fk_data_exports_users FOREIGN KEY (username) REFERENCES users(username)
+  ON UPDATE CASCADE ON DELETE CASCADE;
+ALTER TABLE user_wallets
+ADD CONSTRAINT fk_user_wallets_users FOREIGN KEY (username) REFERENCES users(username)
+  ON UPDATE CASCADE ON DELETE CASCADE;
+ALTER TABLE anon_token_issuances
+ADD CONSTRAINT fk_anon_token_issuances_users FOREIGN KEY (username) REFERENCES users(username)
+  ON UPDATE CASCADE ON DELETE CASCADE;
+ALTER TABLE karma_ledger
+ADD CONSTRAINT fk_karma_ledger_users FOREIGN KEY (username) REFERENCES users(username)
+  ON UPDATE CASCADE ON DELETE CASCADE;
+
+CREATE INDEX idx_items_username ON items (username);
+CREATE INDEX idx_items_created ON items (created);
+CREATE INDEX idx_comments_username ON comments (username);
+CREATE INDEX idx_comments_parent_item_id ON comments (parent_item_id);
+CREATE INDEX idx_comments_created ON comments (created);
+CREATE INDEX idx_user_votes_content_id ON user_votes (content_id);
+CREATE INDEX idx_user_votes_item_id ON user_votes (item_id) WHERE item_id IS NOT NULL;
+CREATE INDEX idx_user_votes_comment_id ON user_votes (comment_id) WHERE comment_id IS NOT NULL;
+CREATE INDEX idx_user_votes_parent_item_id ON user_votes (parent_item_id);
+CREATE INDEX idx_user_votes_created ON user_votes (created);
+CREATE INDEX idx_user_favorites_username ON user_favorites (username);
+CREATE INDEX idx_user_favorites_item_id ON user_favorites (item_id);
+CREATE INDEX idx_user_favorites_favorite_item_id ON user_favorites (favorite_item_id)
+  WHERE favorite_item_id IS NOT NULL;
+CREATE INDEX idx_user_favorites_favorite_comment_id ON user_favorites (favorite_comment_id)
+  WHERE favorite_comment_id IS NOT NULL;
+CREATE INDEX idx_notifications_item_id ON notifications (item_id);
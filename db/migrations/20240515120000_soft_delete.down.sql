@@ -0,0 +1,9 @@
+-- postgres can't drop an enum value, so the new karma reasons stay in karma_reason_enum. Deleted
+-- items and comments are removed for good, as they were before soft deletes.
+DROP INDEX IF EXISTS idx_items_deleted_at;
+DROP INDEX IF EXISTS idx_comments_deleted_at;
+
+DELETE FROM items WHERE deleted_at IS NOT NULL;
+DELETE FROM comments WHERE deleted_at IS NOT NULL;
+ALTER TABLE items DROP COLUMN IF EXISTS deleted_at;
+ALTER TABLE comments DROP COLUMN IF EXISTS deleted_at;
@@ -0,0 +1,11 @@
+-- Deleted items and comments keep their row, so links and threads still resolve, and moderators
+-- may restore them.
+ALTER TABLE items ADD COLUMN deleted_at TIMESTAMP WITH TIME ZONE;
+ALTER TABLE comments ADD COLUMN deleted_at TIMESTAMP WITH TIME ZONE;
+
+CREATE INDEX idx_items_deleted_at ON items (deleted_at) WHERE deleted_at IS NOT NULL;
+CREATE INDEX idx_comments_deleted_at ON comments (deleted_at) WHERE deleted_at IS NOT NULL;
+
+ALTER TYPE karma_reason_enum ADD VALUE 'comment_deleted';
+ALTER TYPE karma_reason_enum ADD VALUE 'item_restored';
+ALTER TYPE karma_reason_enum ADD VALUE 'comment_restored';
@@ -0,0 +1,16 @@
+-- Every version of an edited item or comment. The original version is recorded by the first
+-- edit, so content that was never edited has no revisions.
+CREATE TABLE revisions (
+    content_id VARCHAR(26) NOT NULL,
+    content_type ITEM_OR_COMMENT_ENUM NOT NULL,
+    -- counts from 1, the original version
+    revision INT NOT NULL,
+    -- items only
+    title TEXT,
+    -- items only
+    item_category ITEM_CATEGORY_ENUM,
+    text TEXT,
+    edited_by TEXT NOT NULL,
+    created TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (content_id, revision)
+);
@@ -0,0 +1,2 @@
+-- Items with both a url and text are left as they are.
+ALTER TABLE items DROP CONSTRAINT IF EXISTS items_url_or_text;
@@ -0,0 +1,7 @@
+-- Items may now have both a url and text, but must have at least one. Empty values are stored as
+-- NULL, and any item left with neither gets its title as text.
+UPDATE items SET url = NULL, domain = NULL WHERE url = '';
+UPDATE items SET text = NULL WHERE text = '';
+UPDATE items SET text = title WHERE url IS NULL AND text IS NULL;
+
+ALTER TABLE items ADD CONSTRAINT items_url_or_text CHECK (url IS NOT NULL OR text IS NOT NULL);
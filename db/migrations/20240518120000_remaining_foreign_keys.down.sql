@@ -0,0 +1,21 @@
+DROP INDEX IF EXISTS idx_notifications_comment_id;
+DROP INDEX IF EXISTS idx_vote_flags_content_id;
+DROP INDEX IF EXISTS idx_revisions_comment_id;
+
+ALTER TABLE revisions
+DROP CONSTRAINT IF EXISTS fk_revisions_edited_by,
+DROP CONSTRAINT IF EXISTS fk_revisions_items,
+DROP CONSTRAINT IF EXISTS fk_revisions_comments,
+DROP COLUMN IF EXISTS item_id,
+DROP COLUMN IF EXISTS comment_id;
+ALTER TABLE vote_flags
+DROP CONSTRAINT IF EXISTS fk_vote_flags_target_username,
+DROP CONSTRAINT IF EXISTS fk_vote_flags_reviewed_by,
+DROP CONSTRAINT IF EXISTS fk_vote_flags_items;
+ALTER TABLE notifications
+DROP CONSTRAINT IF EXISTS fk_notifications_comment_by,
+DROP CONSTRAINT IF EXISTS fk_notifications_comments,
+DROP CONSTRAINT IF EXISTS fk_notifications_parent_comments;
+ALTER TABLE invite_codes
+DROP CONSTRAINT IF EXISTS fk_invite_codes_created_by,
+DROP CONSTRAINT IF EXISTS fk_invite_codes_used_by;
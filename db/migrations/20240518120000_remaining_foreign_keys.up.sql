@@ -0,0 +1,65 @@
+-- Foreign keys for the tables added alongside or after the first set. Rows naming a user or piece
+-- of content that no longer exists are cleaned up first, like the first set does.
+UPDATE invite_codes SET created_by = '[deleted]'
+WHERE created_by NOT IN (SELECT username FROM users);
+UPDATE invite_codes SET used_by = '[deleted]'
+WHERE used_by NOT IN (SELECT username FROM users);
+UPDATE notifications SET comment_by = '[deleted]'
+WHERE comment_by NOT IN (SELECT username FROM users);
+DELETE FROM notifications
+WHERE comment_id NOT IN (SELECT id FROM comments)
+  OR parent_comment_id NOT IN (SELECT id FROM comments);
+UPDATE vote_flags SET target_username = '[deleted]'
+WHERE target_username NOT IN (SELECT username FROM users);
+UPDATE vote_flags SET reviewed_by = '[deleted]'
+WHERE reviewed_by NOT IN (SELECT username FROM users);
+DELETE FROM vote_flags WHERE content_id NOT IN (SELECT id FROM items);
+UPDATE revisions SET edited_by = '[deleted]'
+WHERE edited_by NOT IN (SELECT username FROM users);
+DELETE FROM revisions
+WHERE (content_type = 'item' AND content_id NOT IN (SELECT id FROM items))
+  OR (content_type = 'comment' AND content_id NOT IN (SELECT id FROM comments));
+
+-- As with content, `delete_user` hands these over to the `[deleted]` placeholder, so they only
+-- follow renames.
+ALTER TABLE invite_codes
+ADD CONSTRAINT fk_invite_codes_created_by FOREIGN KEY (created_by) REFERENCES users(username)
+  ON UPDATE CASCADE,
+ADD CONSTRAINT fk_invite_codes_used_by FOREIGN KEY (used_by) REFERENCES users(username)
+  ON UPDATE CASCADE;
+ALTER TABLE notifications
+ADD CONSTRAINT fk_notifications_comment_by FOREIGN KEY (comment_by) REFERENCES users(username)
+  ON UPDATE CASCADE,
+ADD CONSTRAINT fk_notifications_comments FOREIGN KEY (comment_id) REFERENCES comments(id)
+  ON DELETE CASCADE,
+ADD CONSTRAINT fk_notifications_parent_comments FOREIGN KEY (parent_comment_id)
+  REFERENCES comments(id) ON DELETE CASCADE;
+
+-- A flag's `usernames` and `vote_ids` are arrays, which foreign keys can't reference. The voters'
+-- names are replaced by `delete_user`; their votes are deleted with them, and the ids left behind
+-- match no vote when the flag is later confirmed or dismissed.
+ALTER TABLE vote_flags
+ADD CONSTRAINT fk_vote_flags_target_username FOREIGN KEY (target_username)
+  REFERENCES users(username) ON UPDATE CASCADE,
+ADD CONSTRAINT fk_vote_flags_reviewed_by FOREIGN KEY (reviewed_by) REFERENCES users(username)
+  ON UPDATE CASCADE,
+ADD CONSTRAINT fk_vote_flags_items FOREIGN KEY (content_id) REFERENCES items(id)
+  ON DELETE CASCADE;
+
+-- Revisions are of either an item or a comment, so get a generated column per content type, like
+-- votes and favorites.
+ALTER TABLE revisions
+ADD COLUMN item_id VARCHAR(26)
+  GENERATED ALWAYS AS (CASE WHEN content_type = 'item' THEN content_id END) STORED,
+ADD COLUMN comment_id VARCHAR(26)
+  GENERATED ALWAYS AS (CASE WHEN content_type = 'comment' THEN content_id END) STORED,
+ADD CONSTRAINT fk_revisions_edited_by FOREIGN KEY (edited_by) REFERENCES users(username)
+  ON UPDATE CASCADE,
+ADD CONSTRAINT fk_revisions_items FOREIGN KEY (item_id) REFERENCES items(id) ON DELETE CASCADE,
+ADD CONSTRAINT fk_revisions_comments FOREIGN KEY (comment_id) REFERENCES comments(id)
+  ON DELETE CASCADE;
+
+CREATE INDEX idx_notifications_comment_id ON notifications (comment_id)
+  WHERE comment_id IS NOT NULL;
+CREATE INDEX idx_vote_flags_content_id ON vote_flags (content_id) WHERE content_id IS NOT NULL;
+CREATE INDEX idx_revisions_comment_id ON revisions (comment_id) WHERE comment_id IS NOT NULL;
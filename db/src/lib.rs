@@ -11,7 +11,11 @@ pub mod queries;
 mod types;
 mod utils;
 
-pub use crate::{error::*, types::*};
+pub use crate::{
+  error::*,
+  types::*,
+  utils::{extract_mentions, link_mentions, validate_new_username},
+};
 
 pub type DbPool = sqlx::postgres::PgPool;
 pub type DbResult<T> = Result<T, DbError>;
@@ -19,4 +23,16 @@ pub type DbResult<T> = Result<T, DbError>;
 /// the minimum points a comment can have
 pub const MIN_COMMENT_POINTS: i32 = -4;
 
+/// the most users a single comment or item may notify by `@mention`
+pub const MAX_MENTIONS: usize = 5; // todo(config)
+
+/// placeholder author for the items and comments of deleted accounts
+pub const DELETED_USERNAME: &str = "[deleted]";
+
+/// shown in place of the title, text, and author of deleted items and comments
+pub const DELETED_CONTENT: &str = "[deleted]";
+
+/// placeholder author for anonymous items and comments
+pub const ANONYMOUS_USERNAME: &str = "[anonymous]";
+
 pub async fn migrate(pool: &DbPool) { sqlx::migrate!("../db/migrations").run(pool).await.unwrap(); }
@@ -30,6 +30,9 @@ pub struct Comment {
   /// Dead comments cannot be commented on, and are not displayed by default.
   /// Comments submitted by shadow-banned users are dead.
   pub dead:              bool,
+  /// when the comment was deleted, if it was. Deleted comments keep their place in the thread,
+  /// but are shown as `[deleted]` to everyone but moderators.
+  pub deleted_at:        Option<Timestamp>,
 }
 
 impl Default for Comment {
@@ -47,6 +50,7 @@ impl Default for Comment {
       points:            1,
       created:           now(),
       dead:              false,
+      deleted_at:        None,
     }
   }
 }
@@ -62,11 +66,12 @@ impl Comment {
     comment_text: CommentText,
     dead: bool,
   ) -> Self {
+    let id = Ulid::new();
     // if root_comment_id is None, then this is the root comment
-    let root_comment_id = root_comment_id.unwrap_or_default();
-    // let text = crate::utils::sanitize_text(&text); // todo
+    let root_comment_id = root_comment_id.unwrap_or_else(|| id.clone());
 
     Comment {
+      id,
       username,
       parent_item_id: parent_item_id.clone(),
       parent_item_title: parent_item_title.clone(),
@@ -101,7 +106,20 @@ impl Comment {
     comment
   }
 
+  pub fn is_deleted(&self) -> bool { self.deleted_at.is_some() }
+
+  /// Hide the text and author of a deleted comment, keeping its place in the thread.
+  pub fn redacted(self) -> Self {
+    if !self.is_deleted() {
+      return self;
+    }
+    Comment { username: DELETED_CONTENT.into(), comment_text: DELETED_CONTENT.into(), ..self }
+  }
+
   pub fn is_editable(&self) -> bool {
+    if self.is_deleted() {
+      return false;
+    }
     if self.created + chrono::Duration::try_hours(1).unwrap() < now() || self.children_count > 0 {
       return false;
     }
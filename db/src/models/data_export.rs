@@ -0,0 +1,38 @@
+use super::*;
+
+/// A personal data export, generated in the background by the export worker for accounts too
+/// large to export inline.
+#[derive(sqlx::FromRow, Debug, Clone, Serialize, Deserialize)]
+pub struct DataExport {
+  pub id:        Ulid,
+  /// The user whose data is exported
+  pub username:  Username,
+  pub created:   Timestamp,
+  /// Times the worker has started generating the export
+  pub attempts:  i32,
+  /// When generation finished, successfully or not. None while pending.
+  pub completed: Option<Timestamp>,
+  /// The JSON bundle, once generated
+  pub data:      Option<String>,
+  /// Why generation failed, if it did
+  pub error:     Option<String>,
+}
+
+impl DataExport {
+  pub fn new(username: Username) -> Self {
+    Self {
+      id: Ulid::new(),
+      username,
+      created: now(),
+      attempts: 0,
+      completed: None,
+      data: None,
+      error: None,
+    }
+  }
+
+  /// Exports are deleted once they are older than this.
+  pub fn ttl() -> chrono::Duration { chrono::Duration::try_days(7).unwrap() }
+
+  pub fn expiration(&self) -> Timestamp { self.created + Self::ttl() }
+}
@@ -0,0 +1,29 @@
+use super::*;
+
+/// A single-use code allowing registration while registration is invite-only.
+#[derive(sqlx::FromRow, Debug, Clone, Serialize, Deserialize)]
+pub struct InviteCode {
+  pub code:       String,
+  /// the user who minted the code
+  pub created_by: Username,
+  pub created:    Timestamp,
+  /// the user who registered with the code, once used
+  pub used_by:    Option<Username>,
+  pub used:       Option<Timestamp>,
+}
+
+impl InviteCode {
+  pub fn new(code: String, created_by: Username) -> Self {
+    Self { code, created_by, created: now(), used_by: None, used: None }
+  }
+}
+
+/// A user in an invite tree, and who invited them.
+#[derive(sqlx::FromRow, Debug, Clone, Serialize, Deserialize)]
+pub struct Invitee {
+  pub username:   Username,
+  pub invited_by: Username,
+  /// 1 for users invited by the root of the tree, 2 for those they invited, and so on
+  pub depth:      i32,
+  pub joined:     Timestamp,
+}
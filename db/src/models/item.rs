@@ -1,8 +1,7 @@
 use super::*;
 
 /// A single post on the site.
-/// Note that an item either has a url and domain, or text, but not both.
-/// Comments on a post
+/// An item has a url and domain, text, or both.
 #[derive(sqlx::FromRow, Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[schema(example = Item::default, default = Item::default)]
 #[serde(rename_all = "camelCase")]
@@ -22,6 +21,9 @@ pub struct Item {
   pub item_category: ItemCategory,
   pub created:       Timestamp,
   pub dead:          bool,
+  /// when the item was deleted, if it was. Deleted items stay resolvable, but are shown as
+  /// `[deleted]` to everyone but moderators.
+  pub deleted_at:    Option<Timestamp>,
 }
 
 impl Default for Item {
@@ -31,8 +33,6 @@ impl Default for Item {
       username:      Username::default(),
       title:         Title::default(),
       item_type:     ItemType::default(),
-      // note that an item either has a url and domain, or text, but not both
-      // but it's convenient to have both for testing and documentation purposes
       url:           Some(Url::default()),
       domain:        Some(Domain::default()),
       text:          Some(Text::default()),
@@ -42,6 +42,7 @@ impl Default for Item {
       item_category: ItemCategory::default(),
       created:       now(),
       dead:          false,
+      deleted_at:    None,
     }
   }
 }
@@ -51,10 +52,10 @@ impl Item {
     username: Username,
     title: Title,
     item_type: ItemType,
-    text_or_url_content: TextOrUrl,
+    content: ItemContent,
     item_category: ItemCategory,
   ) -> Self {
-    let (url, domain, text) = text_or_url_content.url_domain_text();
+    let (url, domain, text) = content.url_domain_text();
 
     Item { username, title, item_type, url, domain, text, item_category, ..Default::default() }
   }
@@ -62,12 +63,15 @@ impl Item {
   // todo(refactor) - should not be async, store a `number comments` or `has comments` field on the
   // struct
   pub fn is_editable(&self) -> bool {
-    self.comment_count > 0 || now() > self.modification_expiration()
+    !self.is_deleted() && (self.comment_count > 0 || now() > self.modification_expiration())
   }
 
-  /// An item is editable if it was created less than 1 hour ago, and has no comments.
+  /// An item is editable if it was created less than 1 hour ago, has no comments, and has not
+  /// been deleted.
   pub async fn assert_is_editable(&self, pool: &DbPool) -> DbResult<()> {
-    if crate::queries::items::item_has_comments(pool, &self.id).await {
+    if self.is_deleted() {
+      return Err(DbError::NotEditable("deleted".into()));
+    } else if crate::queries::items::item_has_comments(pool, &self.id).await {
       return Err(DbError::NotEditable("has comments".into()));
     } else if now() > self.modification_expiration() {
       return Err(DbError::NotEditable("expired".into()));
@@ -78,6 +82,23 @@ impl Item {
   pub fn modification_expiration(&self) -> Timestamp {
     self.created + chrono::Duration::try_hours(1).unwrap()
   }
+
+  pub fn is_deleted(&self) -> bool { self.deleted_at.is_some() }
+
+  /// Hide the content and author of a deleted item, keeping its id, points, and comment count.
+  pub fn redacted(self) -> Self {
+    if !self.is_deleted() {
+      return self;
+    }
+    Item {
+      username: DELETED_CONTENT.into(),
+      title: DELETED_CONTENT.into(),
+      url: None,
+      domain: None,
+      text: None,
+      ..self
+    }
+  }
 }
 
 #[derive(
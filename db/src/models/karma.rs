@@ -0,0 +1,102 @@
+use super::*;
+
+/// A change to a user's karma, and its cause.
+#[derive(sqlx::FromRow, Debug, Clone, Serialize, Deserialize)]
+pub struct KarmaEntry {
+  pub id:         i64,
+  pub username:   Username,
+  /// the change actually applied; karma is never taken below zero
+  pub delta:      i32,
+  /// the change called for, which the floor at zero may have cut short
+  pub requested:  i32,
+  pub reason:     KarmaReason,
+  /// the item or comment responsible, if any
+  pub content_id: Option<String>,
+  pub created:    Timestamp,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, sqlx::Type, Deserialize, Serialize, ToSchema)]
+#[sqlx(type_name = "karma_reason_enum", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum KarmaReason {
+  ItemCreated,
+  CommentCreated,
+  /// a vote on the user's item or comment was cast, changed, or retracted
+  Vote,
+  /// the user's item was deleted, taking its points with it
+  ItemDeleted,
+  /// a voter's account was deleted, taking their votes with it
+  VoterDeleted,
+  /// a correction by the reconciliation job, or the ledger's opening balance
+  Reconciliation,
+  /// a vote flagged by vote analysis stopped counting
+  VoteDiscounted,
+  /// the user's comment was deleted, taking its points with it
+  CommentDeleted,
+  /// a moderator restored the user's deleted item, and its points
+  ItemRestored,
+  /// a moderator restored the user's deleted comment, and its points
+  CommentRestored,
+}
+
+/// A run of the karma reconciliation job.
+#[derive(sqlx::FromRow, Debug, Clone, Serialize, Deserialize)]
+pub struct KarmaReconciliation {
+  pub id:                Ulid,
+  /// whether drift found was corrected, or only reported
+  pub corrected:         bool,
+  pub users_checked:     i32,
+  pub discrepancy_count: i32,
+  pub created:           Timestamp,
+}
+
+/// A user whose stored karma differed from the karma recomputed from their content and votes.
+#[derive(sqlx::FromRow, Debug, Clone, Serialize, Deserialize)]
+pub struct KarmaDiscrepancy {
+  pub reconciliation_id: Ulid,
+  pub username:          Username,
+  /// `users.karma` when the job ran
+  pub stored:            i32,
+  /// one per item and comment, plus the net votes on them
+  pub expected:          i32,
+  /// the sum of the user's ledger entries
+  pub ledger:            i32,
+}
+
+/// The span of each point in a karma history.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum KarmaBucket {
+  #[default]
+  Day,
+  /// weeks start on Monday
+  Week,
+}
+
+impl KarmaBucket {
+  /// The postgres `date_trunc` field.
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      KarmaBucket::Day => "day",
+      KarmaBucket::Week => "week",
+    }
+  }
+
+  pub fn duration(&self) -> chrono::TimeDelta {
+    match self {
+      KarmaBucket::Day => chrono::TimeDelta::days(1),
+      KarmaBucket::Week => chrono::TimeDelta::weeks(1),
+    }
+  }
+}
+
+/// A user's karma over one bucket of their karma history.
+#[derive(sqlx::FromRow, Debug, Clone, Serialize, Deserialize)]
+pub struct KarmaHistoryPoint {
+  /// the start of the bucket
+  pub bucket: Timestamp,
+  /// the net change over the bucket
+  pub delta:  i32,
+  /// karma at the end of the bucket
+  pub karma:  i32,
+}
@@ -0,0 +1,9 @@
+use super::*;
+
+/// A user's place on a leaderboard.
+#[derive(sqlx::FromRow, Debug, Clone, Serialize, Deserialize)]
+pub struct Leader {
+  pub username: Username,
+  /// karma, karma gained, or submissions counted, depending on the leaderboard
+  pub score:    i32,
+}
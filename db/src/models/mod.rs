@@ -1,9 +1,19 @@
 pub mod comment;
+pub mod data_export;
+pub mod invite_code;
 pub mod item;
+pub mod karma;
+pub mod leader;
 pub mod moderation_log;
+pub mod notification;
+pub mod revision;
 pub mod user;
 pub mod user_favorite;
+pub mod user_session;
 pub mod user_vote;
+pub mod user_wallet;
+pub mod vote_flag;
+pub mod webhook;
 
 use std::fmt;
 
@@ -16,5 +26,6 @@ use utoipa::{ToResponse, ToSchema};
 
 use crate::{
   error::DbError, types::*, utils::now, About, AuthToken, CommentText, DbPool, DbResult, Email,
-  PasswordHash, ResetPasswordToken, Timestamp, Title, Username, MIN_COMMENT_POINTS,
+  PasswordHash, ResetPasswordToken, Timestamp, Title, Username, DELETED_CONTENT,
+  MIN_COMMENT_POINTS,
 };
@@ -0,0 +1,85 @@
+use super::{comment::Comment, item::Item, *};
+
+/// A notice that someone replied to, or mentioned, the user.
+#[derive(sqlx::FromRow, Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[schema(example = Notification::default, default = Notification::default)]
+#[serde(rename_all = "camelCase")]
+pub struct Notification {
+  pub id:                Ulid,
+  /// the user being notified
+  pub username:          Username,
+  pub notification_type: NotificationType,
+  /// the reply or mentioning comment. None for mentions in item text.
+  pub comment_id:        Option<String>,
+  /// the author of the reply or mention
+  pub comment_by:        Username,
+  /// the item the reply or mention was placed on
+  pub item_id:           Ulid,
+  pub item_title:        Title,
+  /// the comment replied to, if the reply is not to the item itself
+  pub parent_comment_id: Option<String>,
+  pub read:              bool,
+  pub created:           Timestamp,
+}
+
+impl Default for Notification {
+  fn default() -> Self {
+    Notification {
+      id:                Ulid::new(),
+      username:          Username::default(),
+      notification_type: NotificationType::default(),
+      comment_id:        Some(Ulid::new().0),
+      comment_by:        Username::default(),
+      item_id:           Ulid::new(),
+      item_title:        Title::default(),
+      parent_comment_id: None,
+      read:              false,
+      created:           now(),
+    }
+  }
+}
+
+impl Notification {
+  /// Notify `username` of `comment`, which replies to or mentions them.
+  pub fn new(username: Username, notification_type: NotificationType, comment: &Comment) -> Self {
+    Notification {
+      id: Ulid::new(),
+      username,
+      notification_type,
+      comment_id: Some(comment.id.0.clone()),
+      comment_by: comment.username.clone(),
+      item_id: comment.parent_item_id.clone(),
+      item_title: comment.parent_item_title.clone(),
+      parent_comment_id: comment.parent_comment_id.clone(),
+      read: false,
+      created: now(),
+    }
+  }
+
+  /// Notify `username` that they were mentioned in the text of `item`.
+  pub fn item_mention(username: Username, item: &Item) -> Self {
+    Notification {
+      id: Ulid::new(),
+      username,
+      notification_type: NotificationType::Mention,
+      comment_id: None,
+      comment_by: item.username.clone(),
+      item_id: item.id.clone(),
+      item_title: item.title.clone(),
+      parent_comment_id: None,
+      read: false,
+      created: now(),
+    }
+  }
+}
+
+#[derive(Default, Clone, Copy, Debug, PartialEq, sqlx::Type, Deserialize, Serialize, ToSchema)]
+#[sqlx(type_name = "notification_type_enum", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationType {
+  /// a reply to the user's item or comment
+  #[default]
+  Reply,
+  /// an `@username` mention in a comment or item text
+  Mention,
+}
@@ -0,0 +1,18 @@
+use super::{item::ItemCategory, user_vote::ItemOrComment, *};
+
+/// A version of an edited item or comment.
+#[derive(sqlx::FromRow, Debug, Clone, Serialize, Deserialize)]
+pub struct Revision {
+  pub content_id:    String,
+  pub content_type:  ItemOrComment,
+  /// counts from 1, the original version
+  pub revision:      i32,
+  /// items only
+  pub title:         Option<String>,
+  /// items only
+  pub item_category: Option<ItemCategory>,
+  pub text:          Option<String>,
+  /// the author, or whoever made the edit
+  pub edited_by:     Username,
+  pub created:       Timestamp,
+}
@@ -0,0 +1,24 @@
+use super::*;
+
+/// Metadata recorded for a login session held in the tower-sessions store.
+#[derive(sqlx::FromRow, Debug, Clone, Serialize, Deserialize)]
+pub struct UserSession {
+  /// Public identifier for the session, safe to return to the client
+  pub id:         Ulid,
+  /// The tower-sessions session id. This is the cookie secret, never return it to the client.
+  pub session_id: String,
+  /// The user who owns the session
+  pub username:   Username,
+  /// The `User-Agent` header submitted on login, if any
+  pub user_agent: Option<String>,
+  /// When the user logged in
+  pub created:    Timestamp,
+  /// When the session was last used to authenticate
+  pub last_seen:  Timestamp,
+}
+
+impl UserSession {
+  pub fn new(session_id: String, username: Username, user_agent: Option<String>) -> Self {
+    Self { id: Ulid::new(), session_id, username, user_agent, created: now(), last_seen: now() }
+  }
+}
@@ -0,0 +1,16 @@
+use super::*;
+
+/// An Ethereum address verified by Sign-In with Ethereum, linked to the account it logs in to.
+#[derive(sqlx::FromRow, Debug, Clone, Serialize, Deserialize)]
+pub struct UserWallet {
+  /// Lowercase, 0x-prefixed hex address
+  pub address:  String,
+  pub username: Username,
+  pub created:  Timestamp,
+}
+
+impl UserWallet {
+  pub fn new(address: String, username: Username) -> Self {
+    Self { address: address.to_lowercase(), username, created: now() }
+  }
+}
@@ -0,0 +1,91 @@
+use super::{user_vote::ItemOrComment, *};
+
+/// A suspicious voting pattern, found by the vote analysis job, for moderators to review.
+#[derive(sqlx::FromRow, Debug, Clone, Serialize, Deserialize)]
+pub struct VoteFlag {
+  pub id:              Ulid,
+  pub kind:            VoteFlagKind,
+  /// the voting accounts involved
+  pub usernames:       Vec<String>,
+  /// the author voted for, for new account votes
+  pub target_username: Option<String>,
+  /// the item voted on, for bursts
+  pub content_id:      Option<String>,
+  pub vote_ids:        Vec<String>,
+  /// identifies the pattern, so that later runs don't flag it again
+  pub fingerprint:     String,
+  pub status:          VoteFlagStatus,
+  /// whether the flagged votes have stopped counting
+  pub discounted:      bool,
+  pub created:         Timestamp,
+  pub reviewed_by:     Option<String>,
+  pub reviewed:        Option<Timestamp>,
+}
+
+impl VoteFlag {
+  pub fn new(
+    kind: VoteFlagKind,
+    usernames: Vec<String>,
+    target_username: Option<String>,
+    content_id: Option<String>,
+    vote_ids: Vec<String>,
+    fingerprint: String,
+  ) -> Self {
+    Self {
+      id: Ulid::new(),
+      kind,
+      usernames,
+      target_username,
+      content_id,
+      vote_ids,
+      fingerprint,
+      status: VoteFlagStatus::Pending,
+      discounted: false,
+      created: now(),
+      reviewed_by: None,
+      reviewed: None,
+    }
+  }
+}
+
+#[derive(
+  Clone, Copy, Debug, Default, PartialEq, Eq, sqlx::Type, Deserialize, Serialize, ToSchema,
+)]
+#[sqlx(type_name = "vote_flag_kind_enum", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum VoteFlagKind {
+  #[default]
+  /// accounts that repeatedly upvote each other within minutes
+  MutualUpvotes,
+  /// new accounts whose first actions are upvotes for one author
+  NewAccountVotes,
+  /// many upvotes on one item within minutes
+  VoteBurst,
+}
+
+#[derive(
+  Clone, Copy, Debug, Default, PartialEq, Eq, sqlx::Type, Deserialize, Serialize, ToSchema,
+)]
+#[sqlx(type_name = "vote_flag_status_enum", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum VoteFlagStatus {
+  #[default]
+  Pending,
+  Confirmed,
+  Dismissed,
+}
+
+/// A recent upvote, as seen by vote analysis.
+#[derive(sqlx::FromRow, Debug, Clone, Serialize, Deserialize)]
+pub struct RecentUpvote {
+  pub id:                     Ulid,
+  pub voter:                  Username,
+  /// the author of the content voted on
+  pub author:                 Username,
+  pub content_id:             Ulid,
+  pub vote_type:              ItemOrComment,
+  pub created:                Timestamp,
+  pub voter_created:          Timestamp,
+  /// when the voter first submitted an item or comment, if ever
+  pub voter_first_submission: Option<Timestamp>,
+}
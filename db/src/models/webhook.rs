@@ -0,0 +1,79 @@
+use super::*;
+
+/// An outgoing webhook, notified of `events` with HMAC-signed JSON payloads.
+#[derive(sqlx::FromRow, Debug, Clone, Serialize, Deserialize)]
+pub struct Webhook {
+  pub id:       Ulid,
+  /// the user who registered the webhook
+  pub username: Username,
+  pub url:      Url,
+  /// HMAC-SHA256 key used to sign payloads. Only returned to the owner on creation.
+  pub secret:   String,
+  /// the names of the subscribed events, see `WebhookEvent`
+  pub events:   Vec<String>,
+  pub active:   bool,
+  pub created:  Timestamp,
+}
+
+impl Webhook {
+  pub fn new(username: Username, url: Url, secret: String, events: &[WebhookEvent]) -> Self {
+    let events = events.iter().map(|e| e.to_string()).collect();
+    Self { id: Ulid::new(), username, url, secret, events, active: true, created: now() }
+  }
+}
+
+/// An event that webhooks may subscribe to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub enum WebhookEvent {
+  #[serde(rename = "item.created")]
+  ItemCreated,
+  #[serde(rename = "item.deleted")]
+  ItemDeleted,
+  #[serde(rename = "item.voted")]
+  ItemVoted,
+  #[serde(rename = "comment.created")]
+  CommentCreated,
+}
+
+impl fmt::Display for WebhookEvent {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      WebhookEvent::ItemCreated => write!(f, "item.created"),
+      WebhookEvent::ItemDeleted => write!(f, "item.deleted"),
+      WebhookEvent::ItemVoted => write!(f, "item.voted"),
+      WebhookEvent::CommentCreated => write!(f, "comment.created"),
+    }
+  }
+}
+
+/// A delivery of one event to one webhook. Pending deliveries form the retry queue; the rest form
+/// the delivery log.
+#[derive(sqlx::FromRow, Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookDelivery {
+  pub id:              Ulid,
+  pub webhook_id:      Ulid,
+  pub event:           String,
+  /// the JSON body, as signed
+  pub payload:         String,
+  pub status:          WebhookDeliveryStatus,
+  pub attempts:        i32,
+  pub next_attempt:    Timestamp,
+  /// the HTTP status of the last attempt, if a response was received
+  pub response_status: Option<i32>,
+  /// why the last attempt failed, if it did
+  pub error:           Option<String>,
+  pub created:         Timestamp,
+  pub delivered:       Option<Timestamp>,
+}
+
+#[derive(Default, Clone, Copy, Debug, PartialEq, sqlx::Type, Deserialize, Serialize, ToSchema)]
+#[sqlx(type_name = "webhook_delivery_status_enum", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookDeliveryStatus {
+  /// waiting for its next attempt
+  #[default]
+  Pending,
+  Delivered,
+  /// gave up after too many attempts
+  Failed,
+}
@@ -0,0 +1,74 @@
+use super::*;
+
+/// Record that `count` more anonymous posting tokens are being issued to `username` today.
+///
+/// Ok(Some(issued)), the total issued today, if that is within `daily_limit`. Ok(None), recording
+/// nothing, otherwise.
+pub async fn reserve_anon_tokens(
+  pool: &DbPool,
+  username: &Username,
+  count: i32,
+  daily_limit: i32,
+) -> DbResult<Option<i32>> {
+  trace!("reserve_anon_tokens {count} for: {username}");
+  if count > daily_limit {
+    return Ok(None);
+  }
+
+  sqlx::query_scalar!(
+    "INSERT INTO anon_token_issuances (username, day, issued)
+     VALUES ($1, (NOW() AT TIME ZONE 'UTC')::date, $2)
+     ON CONFLICT (username, day) DO UPDATE SET issued = anon_token_issuances.issued + $2
+     WHERE anon_token_issuances.issued + $2 <= $3
+     RETURNING issued",
+    username.0,
+    count,
+    daily_limit
+  )
+  .fetch_optional(pool)
+  .await
+  .map_err(DbError::from)
+}
+
+/// How many anonymous posting tokens `username` has been issued today.
+pub async fn get_anon_tokens_issued_today(pool: &DbPool, username: &Username) -> DbResult<i32> {
+  sqlx::query_scalar!(
+    "SELECT issued FROM anon_token_issuances
+     WHERE username = $1 AND day = (NOW() AT TIME ZONE 'UTC')::date",
+    username.0
+  )
+  .fetch_optional(pool)
+  .await
+  .map(|issued| issued.unwrap_or(0))
+  .map_err(DbError::from)
+}
+
+/// Create an item authored by `[anonymous]`, spending the token with hash `token_hash`.
+///
+/// Err(UniqueViolation) if the token has already been spent.
+pub async fn create_anonymous_item(pool: &DbPool, item: &Item, token_hash: &str) -> DbResult<()> {
+  debug!("create_anonymous_item with: {item:?}");
+  let mut tx = pool.begin().await?;
+  sqlx::query!("INSERT INTO spent_anon_tokens (token_hash) VALUES ($1)", token_hash)
+    .execute(&mut *tx)
+    .await?;
+  super::items::create_item_with(&mut tx, item).await?;
+  Ok(tx.commit().await?)
+}
+
+/// Create a comment authored by `[anonymous]`, spending the token with hash `token_hash`.
+///
+/// Err(UniqueViolation) if the token has already been spent.
+pub async fn create_anonymous_comment(
+  pool: &DbPool,
+  comment: &Comment,
+  token_hash: &str,
+) -> DbResult<()> {
+  debug!("create_anonymous_comment with: {comment:?}");
+  let mut tx = pool.begin().await?;
+  sqlx::query!("INSERT INTO spent_anon_tokens (token_hash) VALUES ($1)", token_hash)
+    .execute(&mut *tx)
+    .await?;
+  super::comments::create_comment_with(&mut tx, comment).await?;
+  Ok(tx.commit().await?)
+}
@@ -1,5 +1,8 @@
 use super::*;
 
+// backlog: move this to a config file
+pub const COMMENTS_PAGE_SIZE: i64 = 30;
+
 pub async fn get_comments_page(
   pool: &DbPool,
   item_id: &Ulid,
@@ -70,6 +73,286 @@ pub async fn get_user_comments(
              // .await?
 }
 
+/// Stream every comment written by `username`, on any item, newest first.
+pub fn get_comments_by_user<'a>(
+  pool: &'a DbPool,
+  username: &Username,
+) -> BoxStream<'a, DbResult<Comment>> {
+  trace!("get_comments_by_user with: {username}");
+  sqlx::query_as!(
+    Comment,
+    "SELECT
+      id,
+      username,
+      parent_item_id,
+      parent_item_title,
+      comment_text as \"comment_text: CommentText\",
+      is_parent,
+      root_comment_id,
+      parent_comment_id,
+      children_count,
+      points,
+      created,
+      dead,
+      deleted_at
+    FROM comments WHERE username = $1
+    ORDER BY created DESC",
+    username.0
+  )
+  .fetch(pool)
+  .map_err(DbError::from)
+  .boxed()
+}
+
+/// Create a comment. In one transaction:
+/// - insert the comment
+/// - increment the author's karma and the item's comment count
+/// - increment the parent comment's children count, if replying to a comment
+/// - notify the author of the item or comment replied to, and any users `@mentioned`
+pub async fn create_comment(pool: &DbPool, comment: &Comment) -> DbResult<()> {
+  debug!("create_comment with: {comment:?}");
+  let mut tx = pool.begin().await?;
+  create_comment_with(&mut tx, comment).await?;
+  Ok(tx.commit().await?)
+}
+
+/// Create a comment, as part of a larger transaction.
+pub(crate) async fn create_comment_with(
+  conn: &mut PgConnection,
+  comment: &Comment,
+) -> DbResult<()> {
+  let Comment {
+    id,
+    username,
+    parent_item_id,
+    parent_item_title,
+    comment_text,
+    is_parent,
+    root_comment_id,
+    parent_comment_id,
+    children_count,
+    points,
+    created,
+    dead,
+    ..
+  } = comment.clone();
+
+  sqlx::query!(
+    "INSERT INTO comments
+    ( id,
+      username,
+      parent_item_id,
+      parent_item_title,
+      comment_text,
+      is_parent,
+      root_comment_id,
+      parent_comment_id,
+      children_count,
+      points,
+      created,
+      dead )
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)",
+    id.0,
+    username.0,
+    parent_item_id.0,
+    parent_item_title.0,
+    comment_text.0,
+    is_parent,
+    root_comment_id.0,
+    parent_comment_id,
+    children_count,
+    points,
+    created.0,
+    dead
+  )
+  .execute(&mut *conn)
+  .await?;
+
+  adjust_karma(&mut *conn, &username, 1, KarmaReason::CommentCreated, Some(&id.0)).await?;
+
+  let item_author = sqlx::query_scalar!(
+    "UPDATE items SET comment_count = comment_count + 1
+     WHERE id = $1 AND deleted_at IS NULL
+     RETURNING username",
+    parent_item_id.0
+  )
+  .fetch_optional(&mut *conn)
+  .await?
+  .ok_or(DbError::NotFound("item".into()))?;
+
+  // the author of whatever is being replied to
+  let recipient: Username = match &parent_comment_id {
+    Some(parent_comment_id) => sqlx::query_scalar!(
+      "UPDATE comments SET children_count = children_count + 1
+       WHERE id = $1 AND deleted_at IS NULL
+       RETURNING username",
+      parent_comment_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or(DbError::NotFound("parent comment".into()))?
+    .into(),
+    None => item_author.into(),
+  };
+
+  // don't notify users of their own replies, or the placeholders for deleted and anonymous authors
+  if recipient != username && recipient.0 != DELETED_USERNAME && recipient.0 != ANONYMOUS_USERNAME {
+    let notification = Notification::new(recipient.clone(), NotificationType::Reply, comment);
+    insert_notification(&mut *conn, &notification).await?;
+  }
+
+  // the reply notification suffices for a recipient who is also mentioned
+  insert_mention_notifications(
+    &mut *conn,
+    &comment_text.0,
+    &username,
+    Some(&recipient),
+    |mentioned| Notification::new(mentioned, NotificationType::Mention, comment),
+  )
+  .await?;
+  enqueue_webhook_event(conn, WebhookEvent::CommentCreated, comment).await
+}
+
+pub async fn get_assert_comment(pool: &DbPool, comment_id: &Ulid) -> DbResult<Comment> {
+  get_comment(pool, comment_id).await?.ok_or(DbError::NotFound("comment".into()))
+}
+
+pub async fn get_comment(pool: &DbPool, comment_id: &Ulid) -> DbResult<Option<Comment>> {
+  debug!("get_comment with: {comment_id:?}");
+  sqlx::query_as!(
+    Comment,
+    "SELECT
+      id,
+      username,
+      parent_item_id,
+      parent_item_title,
+      comment_text as \"comment_text: CommentText\",
+      is_parent,
+      root_comment_id,
+      parent_comment_id,
+      children_count,
+      points,
+      created,
+      dead,
+      deleted_at
+    FROM comments WHERE id = $1",
+    comment_id.0
+  )
+  .fetch_optional(pool)
+  .await
+  .map_err(DbError::from)
+}
+
+/// Edit a comment's text, recording the edit as a revision in the same transaction.
+pub async fn edit_comment(
+  pool: &DbPool,
+  comment_id: &Ulid,
+  text: &CommentText,
+  edited_by: &Username,
+) -> DbResult<()> {
+  debug!("edit_comment with: {comment_id}");
+  let mut tx = pool.begin().await?;
+  record_original_comment_with(&mut tx, comment_id).await?;
+
+  let edited = sqlx::query!(
+    "UPDATE comments SET comment_text = $1 WHERE id = $2 AND deleted_at IS NULL",
+    text.0,
+    comment_id.0
+  )
+  .execute(&mut *tx)
+  .await?;
+  if edited.rows_affected() == 0 {
+    return Err(DbError::NotFound("comment".into()));
+  }
+
+  record_comment_revision_with(&mut tx, comment_id, edited_by).await?;
+  Ok(tx.commit().await?)
+}
+
+/// Soft delete a comment: it keeps its place in the thread, but is shown as `[deleted]`. Take the
+/// comment's points from its author's karma, without taking it below zero. Votes on the comment
+/// are kept, so that a restored comment gets its points back.
+pub async fn delete_comment(pool: &DbPool, comment: &Comment) -> DbResult<()> {
+  debug!("delete_comment with: {}", comment.id);
+  let mut tx = pool.begin().await?;
+  let deleted = sqlx::query!(
+    "UPDATE comments SET deleted_at = NOW() WHERE id = $1 AND deleted_at IS NULL",
+    comment.id.0
+  )
+  .execute(&mut *tx)
+  .await?;
+  if deleted.rows_affected() == 0 {
+    return Err(DbError::NotEditable("deleted".into()));
+  }
+
+  adjust_karma(
+    &mut tx,
+    &comment.username,
+    -comment.points,
+    KarmaReason::CommentDeleted,
+    Some(&comment.id.0),
+  )
+  .await?;
+
+  Ok(tx.commit().await?)
+}
+
+/// Restore a deleted comment, giving its author back the karma its deletion took.
+pub async fn restore_comment(pool: &DbPool, comment_id: &Ulid) -> DbResult<()> {
+  debug!("restore_comment with: {comment_id}");
+  let mut tx = pool.begin().await?;
+  let comment = sqlx::query!(
+    "UPDATE comments SET deleted_at = NULL WHERE id = $1 AND deleted_at IS NOT NULL
+     RETURNING username, points",
+    comment_id.0
+  )
+  .fetch_optional(&mut *tx)
+  .await?
+  .ok_or(DbError::NotFound("deleted comment".into()))?;
+
+  let username: Username = comment.username.into();
+  give_back_karma(
+    &mut tx,
+    &username,
+    comment.points,
+    KarmaReason::CommentDeleted,
+    KarmaReason::CommentRestored,
+    &comment_id.0,
+  )
+  .await?;
+
+  Ok(tx.commit().await?)
+}
+
+/// Get the `page` of deleted comments, most recently deleted first.
+pub async fn get_deleted_comments(pool: &DbPool, page: &Page) -> DbResult<Vec<Comment>> {
+  sqlx::query_as!(
+    Comment,
+    "SELECT
+      id,
+      username,
+      parent_item_id,
+      parent_item_title,
+      comment_text as \"comment_text: CommentText\",
+      is_parent,
+      root_comment_id,
+      parent_comment_id,
+      children_count,
+      points,
+      created,
+      dead,
+      deleted_at
+    FROM comments WHERE deleted_at IS NOT NULL
+    ORDER BY deleted_at DESC
+    LIMIT $1 OFFSET $2",
+    COMMENTS_PAGE_SIZE,
+    (page.page - 1) * COMMENTS_PAGE_SIZE
+  )
+  .fetch_all(pool)
+  .await
+  .map_err(DbError::from)
+}
+
 // pub async fn get_comment(pool: &DbPool, comment_id: Uuid) -> DbResult<Option<Comment>> {
 //   sqlx::query_as!(
 //     Comment,
@@ -0,0 +1,141 @@
+use super::*;
+
+/// Count the rows a data export for `username` would contain.
+pub async fn count_user_content(pool: &DbPool, username: &Username) -> DbResult<i64> {
+  sqlx::query_scalar!(
+    "SELECT
+      (SELECT COUNT(*) FROM items WHERE username = $1)
+      + (SELECT COUNT(*) FROM comments WHERE username = $1)
+      + (SELECT COUNT(*) FROM user_votes WHERE username = $1)
+      + (SELECT COUNT(*) FROM user_favorites WHERE username = $1) as \"count!\"",
+    username.0
+  )
+  .fetch_one(pool)
+  .await
+  .map_err(DbError::from)
+}
+
+pub async fn create_data_export(pool: &DbPool, data_export: &DataExport) -> DbResult<()> {
+  trace!("create_data_export for: {}", data_export.username);
+  sqlx::query!(
+    "INSERT INTO data_exports (id, username, created) VALUES ($1, $2, $3)",
+    data_export.id.0,
+    data_export.username.0,
+    data_export.created.0,
+  )
+  .execute(pool)
+  .await?;
+
+  Ok(())
+}
+
+/// Get the data export `id`, if it belongs to `username`. Its `data` is left out, to be streamed
+/// with `stream_data_export`.
+pub async fn get_assert_data_export(
+  pool: &DbPool,
+  id: &Ulid,
+  username: &Username,
+) -> DbResult<DataExport> {
+  sqlx::query_as!(
+    DataExport,
+    "SELECT id, username, created, attempts, completed, NULL::TEXT as data, error
+     FROM data_exports WHERE id = $1 AND username = $2",
+    id.0,
+    username.0
+  )
+  .fetch_optional(pool)
+  .await?
+  .ok_or(DbError::NotFound("data export".into()))
+}
+
+/// Claim up to `limit` pending exports, oldest first, counting an attempt at each.
+///
+/// Claimed exports are not claimed again until `lease_until`, so that concurrent workers don't
+/// generate them twice, and so that an export interrupted by a restart is not lost.
+pub async fn claim_pending_data_exports(
+  pool: &DbPool,
+  limit: i64,
+  lease_until: &Timestamp,
+) -> DbResult<Vec<DataExport>> {
+  sqlx::query_as!(
+    DataExport,
+    "UPDATE data_exports SET claimed_until = $2, attempts = attempts + 1
+     WHERE id IN (
+       SELECT id FROM data_exports
+       WHERE completed IS NULL AND (claimed_until IS NULL OR claimed_until <= NOW())
+       ORDER BY created
+       LIMIT $1
+       FOR UPDATE SKIP LOCKED
+     )
+     RETURNING id, username, created, attempts, completed, data, error",
+    limit,
+    lease_until.0
+  )
+  .fetch_all(pool)
+  .await
+  .map_err(DbError::from)
+}
+
+/// Store the generated bundle, or the reason generation failed.
+pub async fn complete_data_export(
+  pool: &DbPool,
+  id: &Ulid,
+  result: Result<String, String>,
+) -> DbResult<()> {
+  let (data, error) = match result {
+    Ok(data) => (Some(data), None),
+    Err(error) => (None, Some(error)),
+  };
+  sqlx::query!(
+    "UPDATE data_exports SET completed = $1, data = $2, error = $3 WHERE id = $4",
+    now().0,
+    data,
+    error,
+    id.0
+  )
+  .execute(pool)
+  .await?;
+
+  Ok(())
+}
+
+/// Stream the bundle of the data export `id` in chunks of at most `chunk_chars` characters, so
+/// that it is never held in memory whole. Empty if the export has no bundle.
+pub fn stream_data_export(
+  pool: DbPool,
+  id: Ulid,
+  chunk_chars: i32,
+) -> impl futures::Stream<Item = DbResult<String>> {
+  futures::stream::try_unfold(1, move |start| {
+    let (pool, id) = (pool.clone(), id.clone());
+    async move {
+      let chunk = sqlx::query_scalar!(
+        "SELECT SUBSTR(data, $2, $3) FROM data_exports WHERE id = $1",
+        id.0,
+        start,
+        chunk_chars
+      )
+      .fetch_optional(&pool)
+      .await?
+      .flatten();
+
+      Ok(chunk.filter(|c| !c.is_empty()).map(|c| (c, start + chunk_chars)))
+    }
+  })
+}
+
+pub async fn delete_data_export(pool: &DbPool, id: &Ulid) -> DbResult<()> {
+  sqlx::query!("DELETE FROM data_exports WHERE id = $1", id.0).execute(pool).await?;
+  Ok(())
+}
+
+/// Delete the exports created before `expired_before`. Return how many were deleted.
+pub async fn delete_expired_data_exports(
+  pool: &DbPool,
+  expired_before: &Timestamp,
+) -> DbResult<u64> {
+  let deleted = sqlx::query!("DELETE FROM data_exports WHERE created < $1", expired_before.0)
+    .execute(pool)
+    .await?;
+  Ok(deleted.rows_affected())
+}
@@ -0,0 +1,120 @@
+use super::*;
+
+/// Mint `invite_code`, unless its creator already holds `max_unused` unused codes. Return whether
+/// it was minted.
+///
+/// The creator's row is locked while counting, so that concurrent mints can't both pass the limit.
+pub async fn create_invite_code(
+  pool: &DbPool,
+  invite_code: &InviteCode,
+  max_unused: Option<i64>,
+) -> DbResult<bool> {
+  trace!("create_invite_code for: {}", invite_code.created_by);
+  let mut tx = pool.begin().await?;
+  sqlx::query!(
+    "SELECT username FROM users WHERE username = $1 FOR NO KEY UPDATE",
+    invite_code.created_by.0
+  )
+  .fetch_optional(&mut *tx)
+  .await?
+  .ok_or(DbError::NotFound("user".into()))?;
+
+  let minted = sqlx::query!(
+    "INSERT INTO invite_codes (code, created_by, created)
+     SELECT $1, $2, $3
+     WHERE $4::BIGINT IS NULL
+       OR (SELECT COUNT(*) FROM invite_codes WHERE created_by = $2 AND used_by IS NULL) < $4",
+    invite_code.code,
+    invite_code.created_by.0,
+    invite_code.created.0,
+    max_unused,
+  )
+  .execute(&mut *tx)
+  .await?
+  .rows_affected()
+    > 0;
+
+  tx.commit().await?;
+  Ok(minted)
+}
+
+/// Get the codes minted by `username`, newest first.
+pub async fn get_user_invite_codes(
+  pool: &DbPool,
+  username: &Username,
+) -> DbResult<Vec<InviteCode>> {
+  trace!("get_user_invite_codes for: {username}");
+  sqlx::query_as!(
+    InviteCode,
+    "SELECT code, created_by, created, used_by as \"used_by: Username\", used
+     FROM invite_codes WHERE created_by = $1
+     ORDER BY created DESC",
+    username.0
+  )
+  .fetch_all(pool)
+  .await
+  .map_err(DbError::from)
+}
+
+/// Mark `code` used by `username`, within the transaction creating the user.
+///
+/// Err(NotFound) if the code does not exist or was already used.
+pub(crate) async fn consume_invite_code(
+  conn: &mut PgConnection,
+  code: &str,
+  username: &Username,
+) -> DbResult<()> {
+  trace!("consume_invite_code for: {username}");
+  sqlx::query!(
+    "UPDATE invite_codes SET used_by = $1, used = NOW()
+     WHERE code = $2 AND used_by IS NULL
+     RETURNING code",
+    username.0,
+    code
+  )
+  .fetch_optional(conn)
+  .await?
+  .ok_or(DbError::NotFound("invite code".into()))?;
+
+  Ok(())
+}
+
+/// Who invited `username`, if they registered with an invite code.
+pub async fn get_inviter(pool: &DbPool, username: &Username) -> DbResult<Option<Username>> {
+  let inviter =
+    sqlx::query_scalar!("SELECT created_by FROM invite_codes WHERE used_by = $1", username.0)
+      .fetch_optional(pool)
+      .await?;
+
+  Ok(inviter.map(Username::from))
+}
+
+/// Every user invited by `username`, directly or transitively, breadth first.
+///
+/// The tree is not followed through deleted accounts, whose invites all belong to the placeholder.
+pub async fn get_invite_tree(pool: &DbPool, username: &Username) -> DbResult<Vec<Invitee>> {
+  trace!("get_invite_tree for: {username}");
+  sqlx::query_as!(
+    Invitee,
+    "WITH RECURSIVE tree (username, invited_by, depth, joined) AS (
+       SELECT used_by, created_by, 1, used FROM invite_codes
+       WHERE created_by = $1 AND used_by IS NOT NULL
+       UNION ALL
+       SELECT i.used_by, i.created_by, tree.depth + 1, i.used
+       FROM invite_codes i JOIN tree ON i.created_by = tree.username
+       WHERE i.used_by IS NOT NULL AND tree.username <> $2
+     )
+     SELECT
+       username as \"username!: Username\",
+       invited_by as \"invited_by!: Username\",
+       depth as \"depth!\",
+       joined as \"joined!: Timestamp\"
+     FROM tree
+     ORDER BY depth, joined",
+    username.0,
+    DELETED_USERNAME
+  )
+  .fetch_all(pool)
+  .await
+  .map_err(DbError::from)
+}
@@ -3,11 +3,16 @@ use super::*;
 // backlog: move this to a config file
 pub const ITEM_PAGE_SIZE: i64 = 30;
 
-/// Create a new item in the database.
+/// Create a new item in the database, notifying any users `@mentioned` in its text.
 pub async fn create_item(pool: &DbPool, item: &Item) -> DbResult<()> {
   debug!("create_item with: {item:?}");
   let mut tx = pool.begin().await?;
+  create_item_with(&mut tx, item).await?;
+  Ok(tx.commit().await?)
+}
 
+/// Create an item, as part of a larger transaction.
+pub(crate) async fn create_item_with(conn: &mut PgConnection, item: &Item) -> DbResult<()> {
   let Item { id, username, title, item_type, url, domain, text, item_category, .. } = item.clone();
 
   sqlx::query!(
@@ -30,14 +35,20 @@ pub async fn create_item(pool: &DbPool, item: &Item) -> DbResult<()> {
     text.map(|s| s.0),
     item_category as ItemCategory,
   )
-  .execute(&mut *tx)
+  .execute(&mut *conn)
   .await?;
 
-  sqlx::query!("UPDATE users SET karma = karma + 1 WHERE username = $1", username.0)
-    .execute(&mut *tx)
+  adjust_karma(&mut *conn, &username, 1, KarmaReason::ItemCreated, Some(&item.id.0)).await?;
+
+  if let Some(text) = &item.text {
+    insert_mention_notifications(&mut *conn, &text.0, &username, None, |mentioned| {
+      Notification::item_mention(mentioned, item)
+    })
     .await?;
+  }
+  enqueue_webhook_event(&mut *conn, WebhookEvent::ItemCreated, item).await?;
 
-  Ok(tx.commit().await?)
+  Ok(())
 }
 
 pub async fn get_assert_item(pool: &DbPool, item_id: &Ulid) -> DbResult<Item> {
@@ -62,7 +73,8 @@ pub async fn get_item(pool: &DbPool, item_id: &Ulid) -> DbResult<Option<Item>> {
       score,
       item_category as \"item_category: ItemCategory\",
       created,
-      dead
+      dead,
+      deleted_at
     FROM items WHERE id = $1",
     item_id.to_string()
   )
@@ -71,6 +83,35 @@ pub async fn get_item(pool: &DbPool, item_id: &Ulid) -> DbResult<Option<Item>> {
   .map_err(DbError::from)
 }
 
+/// Stream every item submitted by `username`, newest first.
+pub fn get_user_items<'a>(pool: &'a DbPool, username: &Username) -> BoxStream<'a, DbResult<Item>> {
+  trace!("get_user_items with: {username}");
+  sqlx::query_as!(
+    Item,
+    "SELECT
+      id,
+      username,
+      title,
+      item_type as \"item_type: ItemType\",
+      url as \"url: Url\",
+      domain as \"domain: Domain\",
+      text as \"text: Text\",
+      comment_count,
+      points,
+      score,
+      item_category as \"item_category: ItemCategory\",
+      created,
+      dead,
+      deleted_at
+    FROM items WHERE username = $1
+    ORDER BY created DESC",
+    username.0
+  )
+  .fetch(pool)
+  .map_err(DbError::from)
+  .boxed()
+}
+
 /// Return whether the item has any comments.
 pub(crate) async fn item_has_comments(pool: &DbPool, id: &Ulid) -> bool {
   item_comment_count(pool, id).await > 0
@@ -87,28 +128,97 @@ pub(crate) async fn item_comment_count(pool: &DbPool, id: &Ulid) -> usize {
   count
 }
 
-/// Delete an item from the database. Adjust user karma accordingly.
+/// Soft delete an item: it stays resolvable, but is shown as `[deleted]`. Take the item's points
+/// from its author's karma, without taking it below zero. Votes on the item are kept, so that a
+/// restored item gets its points back.
 pub async fn delete_item(pool: &DbPool, item: &Item, username: &Username) -> DbResult<()> {
   let mut tx = pool.begin().await?;
-  sqlx::query!("UPDATE users SET karma = karma - $1 WHERE username = $2", item.points, username.0)
-    .execute(&mut *tx)
-    .await?;
+  let deleted = sqlx::query!(
+    "UPDATE items SET deleted_at = NOW() WHERE id = $1 AND deleted_at IS NULL",
+    item.id.to_string()
+  )
+  .execute(&mut *tx)
+  .await?;
+  if deleted.rows_affected() == 0 {
+    return Err(DbError::NotEditable("deleted".into()));
+  }
+
+  adjust_karma(&mut tx, username, -item.points, KarmaReason::ItemDeleted, Some(&item.id.0)).await?;
+  // the deleted content is no longer public; webhook owners only learn which item it was
+  let data = serde_json::json!({ "itemId": item.id });
+  enqueue_webhook_event(&mut tx, WebhookEvent::ItemDeleted, &data).await?;
+
+  Ok(tx.commit().await?)
+}
+
+/// Restore a deleted item, giving its author back the karma its deletion took.
+pub async fn restore_item(pool: &DbPool, item_id: &Ulid) -> DbResult<()> {
+  debug!("restore_item with: {item_id}");
+  let mut tx = pool.begin().await?;
+  let item = sqlx::query!(
+    "UPDATE items SET deleted_at = NULL WHERE id = $1 AND deleted_at IS NOT NULL
+     RETURNING username, points",
+    item_id.0
+  )
+  .fetch_optional(&mut *tx)
+  .await?
+  .ok_or(DbError::NotFound("deleted item".into()))?;
 
-  sqlx::query!("DELETE FROM items WHERE id = $1", item.id.to_string()).execute(&mut *tx).await?;
+  let username: Username = item.username.into();
+  give_back_karma(
+    &mut tx,
+    &username,
+    item.points,
+    KarmaReason::ItemDeleted,
+    KarmaReason::ItemRestored,
+    &item_id.0,
+  )
+  .await?;
 
   Ok(tx.commit().await?)
 }
 
-/// Get the `page` of items created after `start_date`
+/// Get the `page` of deleted items, most recently deleted first.
+pub async fn get_deleted_items(pool: &DbPool, page: &Page) -> DbResult<Vec<Item>> {
+  sqlx::query_as!(
+    Item,
+    "SELECT
+      id,
+      username,
+      title,
+      item_type as \"item_type: ItemType\",
+      url as \"url: Url\",
+      domain as \"domain: Domain\",
+      text as \"text: Text\",
+      comment_count,
+      points,
+      score,
+      item_category as \"item_category: ItemCategory\",
+      created,
+      dead,
+      deleted_at
+    FROM items WHERE deleted_at IS NOT NULL
+    ORDER BY deleted_at DESC
+    LIMIT $1 OFFSET $2",
+    ITEM_PAGE_SIZE,
+    (page.page - 1) * ITEM_PAGE_SIZE
+  )
+  .fetch_all(pool)
+  .await
+  .map_err(DbError::from)
+}
+
+/// Get the `page` of items created after `start_date`, leaving out deleted items
 pub async fn get_items_created_after(
   pool: &DbPool,
   start_date: &Timestamp,
   page: &Page,
 ) -> DbResult<(Vec<Item>, usize)> {
-  let count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM items WHERE created > $1")
-    .bind(start_date.0)
-    .fetch_one(pool)
-    .await?;
+  let count: (i64,) =
+    sqlx::query_as("SELECT COUNT(*) FROM items WHERE created > $1 AND deleted_at IS NULL")
+      .bind(start_date.0)
+      .fetch_one(pool)
+      .await?;
 
   let items = sqlx::query_as!(
     Item,
@@ -125,8 +235,9 @@ pub async fn get_items_created_after(
       score,
       item_category as \"item_category: ItemCategory\",
       created,
-      dead
-      FROM items WHERE created > $1 
+      dead,
+      deleted_at
+      FROM items WHERE created > $1 AND deleted_at IS NULL
       ORDER BY score DESC
       LIMIT $2 OFFSET $3",
     start_date.0,
@@ -140,24 +251,36 @@ pub async fn get_items_created_after(
   Ok((items, count.0 as usize))
 }
 
+/// Edit an item, recording the edit as a revision in the same transaction. Fields given as `None`
+/// are left unchanged.
 pub async fn edit_item(
   pool: &DbPool,
   item_id: &Ulid,
-  title: &Title,
-  category: ItemCategory,
-  text: &Text,
+  title: Option<&Title>,
+  category: Option<ItemCategory>,
+  text: Option<&Text>,
+  item_type: ItemType,
+  edited_by: &Username,
 ) -> DbResult<()> {
+  let mut tx = pool.begin().await?;
+  record_original_item_with(&mut tx, item_id).await?;
+
   sqlx::query!(
     "UPDATE items
-    SET title = $1, item_category = $2, text = $3
-    WHERE id = $4",
-    title.0,
-    category as ItemCategory,
-    text.0,
+    SET title = COALESCE($1, title),
+      item_category = COALESCE($2, item_category),
+      text = COALESCE($3, text),
+      item_type = $4
+    WHERE id = $5",
+    title.map(|t| t.0.clone()),
+    category as Option<ItemCategory>,
+    text.map(|t| t.0.clone()),
+    item_type as ItemType,
     item_id.to_string()
   )
-  .execute(pool)
+  .execute(&mut *tx)
   .await?;
 
-  Ok(())
+  record_item_revision_with(&mut tx, item_id, edited_by).await?;
+  Ok(tx.commit().await?)
 }
@@ -0,0 +1,320 @@
+use super::*;
+
+// todo(config)
+pub const KARMA_RECONCILIATIONS_PAGE_SIZE: i64 = 30;
+
+/// Apply `delta` to `username`'s karma and record the change in the ledger, as part of the
+/// transaction making the change.
+///
+/// Karma is never taken below zero, so the change actually applied may be less than `delta`. Both
+/// are recorded, so that reconciliation can account for the difference.
+///
+/// The `[deleted]` and `[anonymous]` placeholders earn no karma; their content isn't theirs.
+pub(crate) async fn adjust_karma(
+  conn: &mut PgConnection,
+  username: &Username,
+  delta: i32,
+  reason: KarmaReason,
+  content_id: Option<&str>,
+) -> DbResult<()> {
+  if delta == 0 || username.0 == DELETED_USERNAME || username.0 == ANONYMOUS_USERNAME {
+    return Ok(());
+  }
+  let applied = apply_karma(conn, username, delta).await?;
+  insert_karma_entry(conn, username, applied, delta, reason, content_id).await
+}
+
+/// Give `username` back the karma taken from them by the latest `taken_by` entry for
+/// `content_id`, e.g. when restoring deleted content, recording `requested` as the change called
+/// for.
+///
+/// What was taken may be less than the content's points, if the floor at zero cut it short;
+/// giving back the points instead would mint karma.
+pub(crate) async fn give_back_karma(
+  conn: &mut PgConnection,
+  username: &Username,
+  requested: i32,
+  taken_by: KarmaReason,
+  reason: KarmaReason,
+  content_id: &str,
+) -> DbResult<()> {
+  if username.0 == DELETED_USERNAME || username.0 == ANONYMOUS_USERNAME {
+    return Ok(());
+  }
+  let taken = sqlx::query_scalar!(
+    "SELECT delta FROM karma_ledger
+     WHERE username = $1 AND content_id = $2 AND reason = $3
+     ORDER BY id DESC LIMIT 1",
+    username.0,
+    content_id,
+    taken_by as KarmaReason
+  )
+  .fetch_optional(&mut *conn)
+  .await?
+  .unwrap_or(0);
+
+  let applied = apply_karma(conn, username, -taken).await?;
+  insert_karma_entry(conn, username, applied, requested, reason, Some(content_id)).await
+}
+
+/// Apply `delta` to `username`'s karma, without taking it below zero. Return the change applied.
+async fn apply_karma(conn: &mut PgConnection, username: &Username, delta: i32) -> DbResult<i32> {
+  if delta == 0 {
+    return Ok(0);
+  }
+  let applied = sqlx::query_scalar!(
+    "WITH old AS (SELECT karma FROM users WHERE username = $2 FOR UPDATE)
+     UPDATE users SET karma = GREATEST(users.karma + $1, 0) FROM old
+     WHERE users.username = $2
+     RETURNING users.karma - old.karma",
+    delta,
+    username.0
+  )
+  .fetch_optional(&mut *conn)
+  .await?
+  .flatten()
+  .unwrap_or(0);
+
+  Ok(applied)
+}
+
+/// Record a change in the ledger, without touching `users.karma`.
+async fn insert_karma_entry(
+  conn: &mut PgConnection,
+  username: &Username,
+  delta: i32,
+  requested: i32,
+  reason: KarmaReason,
+  content_id: Option<&str>,
+) -> DbResult<()> {
+  if delta == 0 && requested == 0 {
+    return Ok(());
+  }
+  sqlx::query!(
+    "INSERT INTO karma_ledger (username, delta, requested, reason, content_id, created)
+     VALUES ($1, $2, $3, $4, $5, $6)",
+    username.0,
+    delta,
+    requested,
+    reason as KarmaReason,
+    content_id,
+    now().0,
+  )
+  .execute(conn)
+  .await?;
+
+  Ok(())
+}
+
+/// Recompute every user's karma from their content and the votes on it: one per item and
+/// comment, plus the net counted votes, and compare it to the stored karma and the ledger.
+///
+/// As karma is never taken below zero, it depends on the order of past changes, not just on the
+/// content and votes now: a downvote at zero karma takes nothing, but a later upvote still gives
+/// one. Rather than replay every change in order, the recomputed karma is reduced by what the
+/// floor has cut from changes so far, the difference between the requested and applied deltas
+/// in the ledger.
+///
+/// Users whose stored karma or ledger differs are recorded as discrepancies. If `correct`, their
+/// karma is set to the recomputed value and the ledger is brought in line, with `reconciliation`
+/// entries. Corrections are applied as deltas, so votes cast while the job runs are not lost.
+///
+/// The `[deleted]` and `[anonymous]` placeholders are skipped: karma goes with the user, not the
+/// content.
+pub async fn reconcile_karma(pool: &DbPool, correct: bool) -> DbResult<KarmaReconciliation> {
+  debug!("reconcile_karma with correct: {correct}");
+  let mut tx = pool.begin().await?;
+
+  let rows = sqlx::query!(
+    "SELECT
+       u.username,
+       u.karma AS stored,
+       (COALESCE(c.content, 0) + COALESCE(v.votes, 0) - COALESCE(l.floored, 0))::INT
+         AS \"expected!\",
+       COALESCE(l.ledger, 0)::INT AS \"ledger!\"
+     FROM users u
+     LEFT JOIN (
+       SELECT username, COUNT(*) AS content FROM (
+         SELECT username FROM items WHERE deleted_at IS NULL
+         UNION ALL SELECT username FROM comments WHERE deleted_at IS NULL
+       ) authored GROUP BY username
+     ) c ON c.username = u.username
+     LEFT JOIN (
+       SELECT content.username, SUM(CASE v.vote_state
+         WHEN 'upvote' THEN 1 WHEN 'downvote' THEN -1 ELSE 0 END) AS votes
+       FROM user_votes v
+       JOIN (
+         SELECT id, username FROM items WHERE deleted_at IS NULL
+         UNION ALL SELECT id, username FROM comments WHERE deleted_at IS NULL
+       ) content ON content.id = v.content_id
+       WHERE NOT v.discounted
+       GROUP BY content.username
+     ) v ON v.username = u.username
+     LEFT JOIN (
+       SELECT username, SUM(delta) AS ledger, SUM(requested - delta) AS floored
+       FROM karma_ledger GROUP BY username
+     ) l ON l.username = u.username
+     WHERE u.username NOT IN ($1, $2)",
+    DELETED_USERNAME,
+    ANONYMOUS_USERNAME
+  )
+  .fetch_all(&mut *tx)
+  .await?;
+
+  let reconciliation_id = Ulid::new();
+  let users_checked = rows.len() as i32;
+  let discrepancies: Vec<KarmaDiscrepancy> = rows
+    .into_iter()
+    .filter(|row| row.stored != row.expected || row.ledger != row.stored)
+    .map(|row| KarmaDiscrepancy {
+      reconciliation_id: reconciliation_id.clone(),
+      username:          row.username.into(),
+      stored:            row.stored,
+      expected:          row.expected,
+      ledger:            row.ledger,
+    })
+    .collect();
+  if !discrepancies.is_empty() {
+    warn!("karma drift found for {} users", discrepancies.len());
+  }
+
+  for d in &discrepancies {
+    sqlx::query!(
+      "INSERT INTO karma_discrepancies (reconciliation_id, username, stored, expected, ledger)
+       VALUES ($1, $2, $3, $4, $5)",
+      d.reconciliation_id.0,
+      d.username.0,
+      d.stored,
+      d.expected,
+      d.ledger,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    if correct {
+      adjust_karma(&mut tx, &d.username, d.expected - d.stored, KarmaReason::Reconciliation, None)
+        .await?;
+      // entries for any change the ledger missed
+      let missed = d.stored - d.ledger;
+      insert_karma_entry(&mut tx, &d.username, missed, missed, KarmaReason::Reconciliation, None)
+        .await?;
+    }
+  }
+
+  let reconciliation = KarmaReconciliation {
+    id: reconciliation_id,
+    corrected: correct,
+    users_checked,
+    discrepancy_count: discrepancies.len() as i32,
+    created: now(),
+  };
+  sqlx::query!(
+    "INSERT INTO karma_reconciliations (id, corrected, users_checked, discrepancy_count, created)
+     VALUES ($1, $2, $3, $4, $5)",
+    reconciliation.id.0,
+    reconciliation.corrected,
+    reconciliation.users_checked,
+    reconciliation.discrepancy_count,
+    reconciliation.created.0,
+  )
+  .execute(&mut *tx)
+  .await?;
+
+  tx.commit().await?;
+  Ok(reconciliation)
+}
+
+/// Get the `page` of reconciliation runs, newest first.
+pub async fn get_karma_reconciliations(
+  pool: &DbPool,
+  page: &Page,
+) -> DbResult<Vec<KarmaReconciliation>> {
+  sqlx::query_as!(
+    KarmaReconciliation,
+    "SELECT id, corrected, users_checked, discrepancy_count, created
+     FROM karma_reconciliations
+     ORDER BY created DESC
+     LIMIT $1 OFFSET $2",
+    KARMA_RECONCILIATIONS_PAGE_SIZE,
+    (page.page - 1) * KARMA_RECONCILIATIONS_PAGE_SIZE
+  )
+  .fetch_all(pool)
+  .await
+  .map_err(DbError::from)
+}
+
+pub async fn get_assert_karma_reconciliation(
+  pool: &DbPool,
+  id: &Ulid,
+) -> DbResult<KarmaReconciliation> {
+  sqlx::query_as!(
+    KarmaReconciliation,
+    "SELECT id, corrected, users_checked, discrepancy_count, created
+     FROM karma_reconciliations WHERE id = $1",
+    id.0
+  )
+  .fetch_optional(pool)
+  .await?
+  .ok_or(DbError::NotFound("karma reconciliation".into()))
+}
+
+/// Get the discrepancies found by a reconciliation run, largest drift first.
+pub async fn get_karma_discrepancies(
+  pool: &DbPool,
+  reconciliation_id: &Ulid,
+) -> DbResult<Vec<KarmaDiscrepancy>> {
+  sqlx::query_as!(
+    KarmaDiscrepancy,
+    "SELECT reconciliation_id, username, stored, expected, ledger
+     FROM karma_discrepancies WHERE reconciliation_id = $1
+     ORDER BY ABS(stored - expected) DESC, username",
+    reconciliation_id.0
+  )
+  .fetch_all(pool)
+  .await
+  .map_err(DbError::from)
+}
+
+/// Get `username`'s karma history from the ledger: one point per `bucket` from the bucket
+/// containing `from`, up to `to`, including buckets without changes.
+pub async fn get_karma_history(
+  pool: &DbPool,
+  username: &Username,
+  from: &Timestamp,
+  to: &Timestamp,
+  bucket: KarmaBucket,
+) -> DbResult<Vec<KarmaHistoryPoint>> {
+  trace!("get_karma_history for: {username}, from: {from:?}, to: {to:?}, bucket: {bucket:?}");
+  sqlx::query_as!(
+    KarmaHistoryPoint,
+    "WITH buckets AS (
+       SELECT generate_series(
+         date_trunc($2, $3::TIMESTAMPTZ), $4::TIMESTAMPTZ, ('1 ' || $2)::INTERVAL
+       ) AS bucket
+     ), deltas AS (
+       SELECT date_trunc($2, created) AS bucket, SUM(delta) AS delta
+       FROM karma_ledger
+       WHERE username = $1 AND created >= date_trunc($2, $3::TIMESTAMPTZ) AND created < $4
+       GROUP BY 1
+     ), opening AS (
+       SELECT COALESCE(SUM(delta), 0) AS karma
+       FROM karma_ledger
+       WHERE username = $1 AND created < date_trunc($2, $3::TIMESTAMPTZ)
+     )
+     SELECT
+       b.bucket AS \"bucket!\",
+       COALESCE(d.delta, 0)::INT AS \"delta!\",
+       (o.karma + SUM(COALESCE(d.delta, 0)) OVER (ORDER BY b.bucket))::INT AS \"karma!\"
+     FROM buckets b
+     LEFT JOIN deltas d ON d.bucket = b.bucket
+     CROSS JOIN opening o
+     ORDER BY b.bucket",
+    username.0,
+    bucket.as_str(),
+    from.0,
+    to.0,
+  )
+  .fetch_all(pool)
+  .await
+  .map_err(DbError::from)
+}
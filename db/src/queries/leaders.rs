@@ -0,0 +1,84 @@
+//! Leaderboards. Moderators, banned and shadow banned users, and the `[deleted]` and
+//! `[anonymous]` placeholders are left off every board.
+use super::*;
+
+/// The top `limit` users by karma.
+pub async fn get_karma_leaders(pool: &DbPool, limit: i64) -> DbResult<Vec<Leader>> {
+  trace!("get_karma_leaders with limit: {limit}");
+  sqlx::query_as!(
+    Leader,
+    "SELECT username, karma AS score
+     FROM users
+     WHERE NOT is_moderator AND NOT banned AND NOT shadow_banned
+       AND username NOT IN ($1, $2)
+     ORDER BY karma DESC, username
+     LIMIT $3",
+    DELETED_USERNAME,
+    ANONYMOUS_USERNAME,
+    limit
+  )
+  .fetch_all(pool)
+  .await
+  .map_err(DbError::from)
+}
+
+/// The top `limit` users by net karma gained since `since`, from the karma ledger.
+///
+/// Reconciliation entries are corrections rather than karma earned, and aren't counted.
+pub async fn get_karma_gained_leaders(
+  pool: &DbPool,
+  since: &Timestamp,
+  limit: i64,
+) -> DbResult<Vec<Leader>> {
+  trace!("get_karma_gained_leaders since: {since:?}, limit: {limit}");
+  sqlx::query_as!(
+    Leader,
+    "SELECT u.username, SUM(l.delta)::INT AS \"score!\"
+     FROM karma_ledger l
+     JOIN users u ON u.username = l.username
+     WHERE l.created >= $1 AND l.reason <> 'reconciliation'
+       AND NOT u.is_moderator AND NOT u.banned AND NOT u.shadow_banned
+       AND u.username NOT IN ($2, $3)
+     GROUP BY u.username
+     HAVING SUM(l.delta) > 0
+     ORDER BY 2 DESC, u.username
+     LIMIT $4",
+    since.0,
+    DELETED_USERNAME,
+    ANONYMOUS_USERNAME,
+    limit
+  )
+  .fetch_all(pool)
+  .await
+  .map_err(DbError::from)
+}
+
+/// The top `limit` users by items submitted since `since` with at least `min_points` points.
+pub async fn get_submission_leaders(
+  pool: &DbPool,
+  since: &Timestamp,
+  min_points: i32,
+  limit: i64,
+) -> DbResult<Vec<Leader>> {
+  trace!("get_submission_leaders since: {since:?}, min_points: {min_points}, limit: {limit}");
+  sqlx::query_as!(
+    Leader,
+    "SELECT u.username, COUNT(*)::INT AS \"score!\"
+     FROM items i
+     JOIN users u ON u.username = i.username
+     WHERE i.created >= $1 AND i.points >= $2 AND NOT i.dead AND i.deleted_at IS NULL
+       AND NOT u.is_moderator AND NOT u.banned AND NOT u.shadow_banned
+       AND u.username NOT IN ($3, $4)
+     GROUP BY u.username
+     ORDER BY 2 DESC, u.username
+     LIMIT $5",
+    since.0,
+    min_points,
+    DELETED_USERNAME,
+    ANONYMOUS_USERNAME,
+    limit
+  )
+  .fetch_all(pool)
+  .await
+  .map_err(DbError::from)
+}
@@ -1,28 +1,56 @@
+pub mod anon_tokens;
 pub mod comments;
+pub mod data_exports;
+pub mod invite_codes;
 pub mod items;
+pub mod karma;
+pub mod leaders;
+pub mod moderation_logs;
+pub mod notifications;
+pub mod revisions;
 pub mod user_favorites;
+pub mod user_sessions;
 pub mod user_votes;
+pub mod user_wallets;
 pub mod users;
+pub mod vote_flags;
+pub mod webhooks;
 
 use std::collections::HashSet;
 
-use futures::{future::join_all, TryFutureExt};
+use futures::{future::join_all, stream::BoxStream, StreamExt, TryFutureExt, TryStreamExt};
 use rayon::prelude::*;
-use sqlx::{postgres::PgQueryResult, Pool, Postgres, QueryBuilder, Transaction};
+use serde::Serialize;
+use sqlx::{postgres::PgQueryResult, PgConnection, Pool, Postgres, QueryBuilder, Transaction};
 use tracing::{debug, error, info, instrument, trace, warn};
 
-pub use self::{comments::*, items::*, user_favorites::*, user_votes::*, users::*};
+pub use self::{
+  anon_tokens::*, comments::*, data_exports::*, invite_codes::*, items::*, karma::*, leaders::*,
+  moderation_logs::*, notifications::*, revisions::*, user_favorites::*, user_sessions::*,
+  user_votes::*, user_wallets::*, users::*, vote_flags::*, webhooks::*,
+};
 use crate::{
   error::DbError,
   models::{
     comment::{self, Comment},
+    data_export::DataExport,
+    invite_code::{InviteCode, Invitee},
     item::{Item, *},
+    karma::{KarmaBucket, KarmaDiscrepancy, KarmaHistoryPoint, KarmaReason, KarmaReconciliation},
+    leader::Leader,
+    moderation_log::ModerationLog,
+    notification::{Notification, NotificationType},
+    revision::Revision,
     user::User,
     user_favorite::UserFavorite,
+    user_session::UserSession,
     user_vote::{UserVote, VoteState, *},
+    user_wallet::UserWallet,
+    vote_flag::{RecentUpvote, VoteFlag, VoteFlagKind, VoteFlagStatus},
+    webhook::{Webhook, WebhookDelivery, WebhookDeliveryStatus, WebhookEvent},
   },
   types::*,
   utils::now,
   About, AuthToken, CommentText, DbPool, DbResult, Email, Page, Password, PasswordHash,
-  ResetPasswordToken, Timestamp, Title, Username,
+  ResetPasswordToken, Timestamp, Title, Username, ANONYMOUS_USERNAME, DELETED_USERNAME,
 };
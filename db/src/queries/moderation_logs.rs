@@ -0,0 +1,28 @@
+use super::*;
+
+/// Stream the moderator actions taken against `username` or their content, newest first.
+pub fn get_moderation_logs_for_user<'a>(
+  pool: &'a DbPool,
+  username: &Username,
+) -> BoxStream<'a, DbResult<ModerationLog>> {
+  sqlx::query_as!(
+    ModerationLog,
+    "SELECT
+      id,
+      moderator_username,
+      action_type,
+      username,
+      item_id,
+      item_title,
+      item_by,
+      comment_id,
+      comment_by,
+      created
+    FROM moderation_logs WHERE username = $1 OR item_by = $1 OR comment_by = $1
+    ORDER BY created DESC",
+    username.0
+  )
+  .fetch(pool)
+  .map_err(DbError::from)
+  .boxed()
+}
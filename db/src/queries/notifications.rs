@@ -0,0 +1,155 @@
+use super::*;
+
+// todo(config)
+pub const NOTIFICATIONS_PAGE_SIZE: i64 = 30;
+
+/// Insert a notification as part of the transaction creating the reply.
+pub(crate) async fn insert_notification(
+  conn: &mut PgConnection,
+  notification: &Notification,
+) -> DbResult<()> {
+  trace!("insert_notification for: {}", notification.username);
+  let Notification {
+    id,
+    username,
+    notification_type,
+    comment_id,
+    comment_by,
+    item_id,
+    item_title,
+    parent_comment_id,
+    read,
+    created,
+  } = notification.clone();
+
+  sqlx::query!(
+    "INSERT INTO notifications
+    ( id,
+      username,
+      notification_type,
+      comment_id,
+      comment_by,
+      item_id,
+      item_title,
+      parent_comment_id,
+      read,
+      created )
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)",
+    id.0,
+    username.0,
+    notification_type as NotificationType,
+    comment_id,
+    comment_by.0,
+    item_id.0,
+    item_title.0,
+    parent_comment_id,
+    read,
+    created.0,
+  )
+  .execute(conn)
+  .await?;
+
+  Ok(())
+}
+
+/// Notify the existing users `@mentioned` in `text`, other than its author and `skip`, up to
+/// `MAX_MENTIONS` of them. `notification` builds the notification for each mentioned user.
+///
+/// Returns the mentions that resolved to users, for the sanitizer to link.
+pub(crate) async fn insert_mention_notifications(
+  conn: &mut PgConnection,
+  text: &str,
+  author: &Username,
+  skip: Option<&Username>,
+  notification: impl Fn(Username) -> Notification,
+) -> DbResult<Vec<Username>> {
+  let mentions = crate::extract_mentions(text);
+  if mentions.is_empty() {
+    return Ok(mentions);
+  }
+  let mentions = super::users::resolve_mentions_with(&mut *conn, &mentions).await?;
+  trace!("insert_mention_notifications for: {mentions:?}");
+
+  for username in &mentions {
+    if username == author || Some(username) == skip {
+      continue;
+    }
+    insert_notification(&mut *conn, &notification(username.clone())).await?;
+  }
+
+  Ok(mentions)
+}
+
+/// Get the `page` of notifications for `username`, newest first.
+pub async fn get_notifications(
+  pool: &DbPool,
+  username: &Username,
+  page: &Page,
+) -> DbResult<Vec<Notification>> {
+  trace!("get_notifications for: {username}, page: {}", page.page);
+  sqlx::query_as!(
+    Notification,
+    "SELECT
+      id,
+      username,
+      notification_type as \"notification_type: NotificationType\",
+      comment_id,
+      comment_by,
+      item_id,
+      item_title,
+      parent_comment_id,
+      read,
+      created
+    FROM notifications WHERE username = $1
+    ORDER BY created DESC
+    LIMIT $2 OFFSET $3",
+    username.0,
+    NOTIFICATIONS_PAGE_SIZE,
+    (page.page - 1) * NOTIFICATIONS_PAGE_SIZE
+  )
+  .fetch_all(pool)
+  .await
+  .map_err(DbError::from)
+}
+
+/// Count the notifications `username` has not yet read.
+pub async fn count_unread_notifications(pool: &DbPool, username: &Username) -> DbResult<i64> {
+  trace!("count_unread_notifications for: {username}");
+  let count = sqlx::query_scalar!(
+    "SELECT COUNT(*) FROM notifications WHERE username = $1 AND NOT read",
+    username.0
+  )
+  .fetch_one(pool)
+  .await?;
+
+  Ok(count.unwrap_or(0))
+}
+
+/// Mark the notifications with `ids` as read, or all of them if `ids` is None.
+///
+/// Ids belonging to other users are ignored.
+pub async fn mark_notifications_read(
+  pool: &DbPool,
+  username: &Username,
+  ids: Option<&[Ulid]>,
+) -> DbResult<()> {
+  trace!("mark_notifications_read for: {username}");
+  match ids {
+    Some(ids) => {
+      let ids: Vec<String> = ids.iter().map(|id| id.0.clone()).collect();
+      sqlx::query!(
+        "UPDATE notifications SET read = true WHERE username = $1 AND id = ANY($2)",
+        username.0,
+        &ids
+      )
+      .execute(pool)
+      .await?
+    },
+    None =>
+      sqlx::query!("UPDATE notifications SET read = true WHERE username = $1", username.0)
+        .execute(pool)
+        .await?,
+  };
+
+  Ok(())
+}
@@ -0,0 +1,102 @@
+use super::*;
+
+/// Record the item's current version as its original, if no revisions of it have been recorded.
+/// Call before editing the item, in the edit's transaction.
+pub(crate) async fn record_original_item_with(
+  conn: &mut PgConnection,
+  item_id: &Ulid,
+) -> DbResult<()> {
+  sqlx::query!(
+    "INSERT INTO revisions
+       (content_id, content_type, revision, title, item_category, text, edited_by, created)
+     SELECT id, 'item', 1, title, item_category, text, username, created
+     FROM items WHERE id = $1
+     ON CONFLICT (content_id, revision) DO NOTHING",
+    item_id.0
+  )
+  .execute(&mut *conn)
+  .await?;
+  Ok(())
+}
+
+/// Record the item's current version as its latest revision. Call after editing the item, in the
+/// edit's transaction.
+pub(crate) async fn record_item_revision_with(
+  conn: &mut PgConnection,
+  item_id: &Ulid,
+  edited_by: &Username,
+) -> DbResult<()> {
+  sqlx::query!(
+    "INSERT INTO revisions
+       (content_id, content_type, revision, title, item_category, text, edited_by)
+     SELECT id, 'item', (SELECT MAX(revision) FROM revisions WHERE content_id = $1) + 1,
+       title, item_category, text, $2
+     FROM items WHERE id = $1",
+    item_id.0,
+    edited_by.0
+  )
+  .execute(&mut *conn)
+  .await?;
+  Ok(())
+}
+
+/// Record the comment's current version as its original, if no revisions of it have been
+/// recorded. Call before editing the comment, in the edit's transaction.
+pub(crate) async fn record_original_comment_with(
+  conn: &mut PgConnection,
+  comment_id: &Ulid,
+) -> DbResult<()> {
+  sqlx::query!(
+    "INSERT INTO revisions (content_id, content_type, revision, text, edited_by, created)
+     SELECT id, 'comment', 1, comment_text, username, created
+     FROM comments WHERE id = $1
+     ON CONFLICT (content_id, revision) DO NOTHING",
+    comment_id.0
+  )
+  .execute(&mut *conn)
+  .await?;
+  Ok(())
+}
+
+/// Record the comment's current version as its latest revision. Call after editing the comment,
+/// in the edit's transaction.
+pub(crate) async fn record_comment_revision_with(
+  conn: &mut PgConnection,
+  comment_id: &Ulid,
+  edited_by: &Username,
+) -> DbResult<()> {
+  sqlx::query!(
+    "INSERT INTO revisions (content_id, content_type, revision, text, edited_by)
+     SELECT id, 'comment', (SELECT MAX(revision) FROM revisions WHERE content_id = $1) + 1,
+       comment_text, $2
+     FROM comments WHERE id = $1",
+    comment_id.0,
+    edited_by.0
+  )
+  .execute(&mut *conn)
+  .await?;
+  Ok(())
+}
+
+/// Get every revision of an item or comment, oldest first. Empty if it was never edited.
+pub async fn get_revisions(pool: &DbPool, content_id: &Ulid) -> DbResult<Vec<Revision>> {
+  trace!("get_revisions with: {content_id}");
+  sqlx::query_as!(
+    Revision,
+    "SELECT
+      content_id,
+      content_type as \"content_type: ItemOrComment\",
+      revision,
+      title,
+      item_category as \"item_category: ItemCategory\",
+      text,
+      edited_by,
+      created
+    FROM revisions WHERE content_id = $1
+    ORDER BY revision",
+    content_id.0
+  )
+  .fetch_all(pool)
+  .await
+  .map_err(DbError::from)
+}
@@ -26,6 +26,23 @@ pub async fn get_favorite(
   .map_err(DbError::from)
 }
 
+/// Stream every favorite of `username`, newest first.
+pub fn get_user_favorites<'a>(
+  pool: &'a DbPool,
+  username: &Username,
+) -> BoxStream<'a, DbResult<UserFavorite>> {
+  sqlx::query_as!(
+    UserFavorite,
+    "SELECT id as \"id: Ulid\",  username, item_type, item_id as \"item_id: Ulid\", date
+       FROM user_favorites WHERE username = $1
+       ORDER BY date DESC",
+    username.0
+  )
+  .fetch(pool)
+  .map_err(DbError::from)
+  .boxed()
+}
+
 /// get the favorite from the db
 /// - if one exists and delete it
 /// - else, create it
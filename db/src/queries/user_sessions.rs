@@ -0,0 +1,129 @@
+use super::*;
+
+/// The table in which `tower_sessions_sqlx_store::PostgresStore` keeps session records.
+///
+/// The store migrates this table at runtime, after our own migrations have run, so queries
+/// touching it are not checked at compile time.
+const SESSION_STORE_TABLE: &str = "tower_sessions.session";
+
+/// Record the metadata for a freshly created login session.
+pub async fn create_user_session(pool: &DbPool, user_session: &UserSession) -> DbResult<()> {
+  trace!("create_user_session for: {}", user_session.username);
+  let UserSession { id, session_id, username, user_agent, created, last_seen } =
+    user_session.clone();
+
+  sqlx::query!(
+    "INSERT INTO user_sessions (id, session_id, username, user_agent, created, last_seen)
+     VALUES ($1, $2, $3, $4, $5, $6)
+     ON CONFLICT (session_id) DO UPDATE SET last_seen = $6",
+    id.0,
+    session_id,
+    username.0,
+    user_agent,
+    created.0,
+    last_seen.0,
+  )
+  .execute(pool)
+  .await?;
+
+  Ok(())
+}
+
+/// Get the live sessions for `username`, most recently used first.
+///
+/// Metadata for sessions that have expired out of the session store is removed first.
+pub async fn get_user_sessions(pool: &DbPool, username: &Username) -> DbResult<Vec<UserSession>> {
+  trace!("get_user_sessions for: {username}");
+  sqlx::query(&format!(
+    "DELETE FROM user_sessions WHERE username = $1 AND session_id NOT IN
+     (SELECT id FROM {SESSION_STORE_TABLE} WHERE expiry_date > NOW())"
+  ))
+  .bind(&username.0)
+  .execute(pool)
+  .await?;
+
+  sqlx::query_as!(
+    UserSession,
+    "SELECT id, session_id, username, user_agent, created, last_seen
+     FROM user_sessions WHERE username = $1
+     ORDER BY last_seen DESC",
+    username.0
+  )
+  .fetch_all(pool)
+  .await
+  .map_err(DbError::from)
+}
+
+/// Update the time at which the session was last used.
+pub async fn touch_user_session(pool: &DbPool, session_id: &str) -> DbResult<()> {
+  sqlx::query!(
+    "UPDATE user_sessions SET last_seen = $1 WHERE session_id = $2",
+    now().0,
+    session_id
+  )
+  .execute(pool)
+  .await?;
+
+  Ok(())
+}
+
+/// Revoke the session with public id `id` belonging to `username`.
+///
+/// Deleting the record from the session store invalidates the session cookie.
+pub async fn delete_user_session(pool: &DbPool, username: &Username, id: &Ulid) -> DbResult<()> {
+  trace!("delete_user_session {id} for: {username}");
+  let mut tx = pool.begin().await?;
+
+  let session_id = sqlx::query!(
+    "DELETE FROM user_sessions WHERE id = $1 AND username = $2 RETURNING session_id",
+    id.0,
+    username.0
+  )
+  .fetch_optional(&mut *tx)
+  .await?
+  .ok_or(DbError::NotFound("session".into()))?
+  .session_id;
+
+  sqlx::query(&format!("DELETE FROM {SESSION_STORE_TABLE} WHERE id = $1"))
+    .bind(session_id)
+    .execute(&mut *tx)
+    .await?;
+
+  Ok(tx.commit().await?)
+}
+
+/// Remove the metadata for a session that has been logged out.
+pub async fn delete_user_session_by_session_id(pool: &DbPool, session_id: &str) -> DbResult<()> {
+  sqlx::query!("DELETE FROM user_sessions WHERE session_id = $1", session_id).execute(pool).await?;
+
+  Ok(())
+}
+
+/// Revoke every session belonging to `username`.
+pub async fn delete_all_user_sessions(pool: &DbPool, username: &Username) -> DbResult<()> {
+  trace!("delete_all_user_sessions for: {username}");
+  let mut tx = pool.begin().await?;
+  delete_all_user_sessions_with(&mut tx, username).await?;
+  Ok(tx.commit().await?)
+}
+
+/// Revoke every session belonging to `username`, as part of a larger transaction.
+pub(crate) async fn delete_all_user_sessions_with(
+  conn: &mut PgConnection,
+  username: &Username,
+) -> DbResult<()> {
+  let session_ids: Vec<String> =
+    sqlx::query!("DELETE FROM user_sessions WHERE username = $1 RETURNING session_id", username.0)
+      .fetch_all(&mut *conn)
+      .await?
+      .into_iter()
+      .map(|row| row.session_id)
+      .collect();
+
+  sqlx::query(&format!("DELETE FROM {SESSION_STORE_TABLE} WHERE id = ANY($1)"))
+    .bind(session_ids)
+    .execute(&mut *conn)
+    .await?;
+
+  Ok(())
+}
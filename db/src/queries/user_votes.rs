@@ -63,13 +63,16 @@ pub async fn get_user_related_votes_for_item(
   .map_err(DbError::from)
 }
 
-/// Submit an vote on an item.
+/// Submit an vote on an item. In one transaction:
 ///
-/// - delete the old vote if one exists
-/// - insert the vote into the database
+/// - lock the item, so that concurrent votes on it, e.g. from a double click, apply one at a time
+/// - replace the user's vote on the item, if any, or remove it if the vote is undone
 /// - update the submitter's karma
 /// - update the item's points
 ///
+/// A discounted vote stays discounted when changed, and never moves points or karma. Deleted items
+/// may not be voted on.
+///
 /// return the new vote state
 pub async fn vote_item(
   pool: &DbPool,
@@ -79,26 +82,46 @@ pub async fn vote_item(
 ) -> DbResult<VoteState> {
   let mut tx = pool.begin().await?;
 
-  let (vote_state, increment_value) = match get_item_vote(pool, username, item_id).await? {
-    None => (vote_state, i32::from(vote_state)),
-    Some(preexisting) => {
-      // remove the previous vote from the db
-      sqlx::query!("DELETE FROM user_votes WHERE id = $1", preexisting.id.to_string())
-        .execute(&mut *tx)
-        .await?;
+  let submitter: Username = sqlx::query_scalar!(
+    "SELECT username FROM items WHERE id = $1 AND deleted_at IS NULL FOR UPDATE",
+    item_id.0
+  )
+  .fetch_optional(&mut *tx)
+  .await?
+  .ok_or(DbError::NotFound("item".into()))?
+  .into();
+  let preexisting = sqlx::query!(
+    "SELECT vote_state as \"vote_state: VoteState\", discounted
+     FROM user_votes WHERE username = $1 AND content_id = $2",
+    username.0,
+    item_id.0
+  )
+  .fetch_optional(&mut *tx)
+  .await?;
 
-      // compute the new vote state
-      if preexisting.vote_state == vote_state {
-        (VoteState::None, -i32::from(vote_state))
-      } else {
-        (vote_state, i32::from(vote_state) - i32::from(preexisting.vote_state))
-      }
-    },
+  // compute the new vote state
+  let (vote_state, increment_value, discounted) = match preexisting {
+    None => (vote_state, i32::from(vote_state), false),
+    Some(preexisting) if preexisting.vote_state == vote_state =>
+      (VoteState::None, -i32::from(vote_state), preexisting.discounted),
+    Some(preexisting) => (
+      vote_state,
+      i32::from(vote_state) - i32::from(preexisting.vote_state),
+      preexisting.discounted,
+    ),
   };
+  let increment_value = if discounted { 0 } else { increment_value };
 
-  // insert the vote into the votes table
-  if vote_state != VoteState::None {
-    let id = Ulid::new().to_string();
+  if vote_state == VoteState::None {
+    sqlx::query!(
+      "DELETE FROM user_votes WHERE username = $1 AND content_id = $2",
+      username.0,
+      item_id.0
+    )
+    .execute(&mut *tx)
+    .await?;
+  } else {
+    // the vote keeps its id, and whether it is discounted, when changed
     sqlx::query!(
       "INSERT INTO user_votes (
       id,
@@ -107,46 +130,70 @@ pub async fn vote_item(
       content_id, 
       vote_state, 
       created 
-      ) VALUES ($1, $2, $3, $4, $5, $6)",
+      ) VALUES ($1, $2, $3, $4, $5, $6)
+      ON CONFLICT (username, content_id)
+      DO UPDATE SET vote_state = EXCLUDED.vote_state, created = EXCLUDED.created",
       Ulid::new().to_string(),
       username.0,
       ItemOrComment::Item as ItemOrComment,
       item_id.0,
       vote_state.clone() as VoteState,
-      now().0
+      now().0,
     )
     .execute(&mut *tx)
     .await?;
   }
 
   if increment_value == 0 {
-    warn!("neutral points increment in vote_on_item, should be unreachable");
+    if !discounted {
+      warn!("neutral points increment in vote_on_item, should be unreachable");
+    }
     tx.commit().await?;
     return Ok(vote_state);
   }
 
   // update the item's points and the submitter's karma
-  let submitter = sqlx::query!(
-    "UPDATE items SET points = points + $1 WHERE id = $2 
-        RETURNING username as \"username: Username\"",
-    increment_value,
-    item_id.to_string()
-  )
-  .fetch_one(&mut *tx)
-  .await?
-  .username;
   sqlx::query!(
-    "UPDATE users SET karma = karma + $1 WHERE username = $2",
+    "UPDATE items SET points = points + $1 WHERE id = $2",
     increment_value,
-    submitter.0
+    item_id.to_string()
   )
   .execute(&mut *tx)
   .await?;
+  adjust_karma(&mut tx, &submitter, increment_value, KarmaReason::Vote, Some(&item_id.0)).await?;
+
+  // votes are private; webhook owners may only learn that one was cast
+  let vote = serde_json::json!({ "itemId": item_id, "voteState": vote_state });
+  enqueue_webhook_event(&mut tx, WebhookEvent::ItemVoted, &vote).await?;
 
   tx.commit().await?;
   Ok(vote_state)
 }
 
+/// Stream every vote cast by `username`, newest first.
+pub fn get_user_votes<'a>(
+  pool: &'a DbPool,
+  username: &Username,
+) -> BoxStream<'a, DbResult<UserVote>> {
+  sqlx::query_as!(
+    UserVote,
+    "SELECT 
+    id,
+    username, 
+    vote_type as \"vote_type: ItemOrComment\", 
+    content_id, 
+    parent_item_id, 
+    vote_state as \"vote_state: VoteState\", 
+    created 
+    FROM user_votes WHERE username = $1
+    ORDER BY created DESC",
+    username.0
+  )
+  .fetch(pool)
+  .map_err(DbError::from)
+  .boxed()
+}
+
 pub async fn get_user_votes_on_items_after(
   pool: &DbPool,
   username: &Username,
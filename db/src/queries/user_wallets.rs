@@ -0,0 +1,49 @@
+use super::*;
+
+/// Link a verified wallet address to a user.
+///
+/// Err(UniqueViolation) if the address is already linked to an account.
+pub async fn create_user_wallet(pool: &DbPool, user_wallet: &UserWallet) -> DbResult<()> {
+  trace!("create_user_wallet {} for: {}", user_wallet.address, user_wallet.username);
+  let UserWallet { address, username, created } = user_wallet.clone();
+
+  sqlx::query!(
+    "INSERT INTO user_wallets (address, username, created) VALUES ($1, $2, $3)",
+    address,
+    username.0,
+    created.0,
+  )
+  .execute(pool)
+  .await?;
+
+  Ok(())
+}
+
+/// Get the user linked to the wallet `address`, if any.
+pub async fn get_user_by_wallet(pool: &DbPool, address: &str) -> DbResult<Option<User>> {
+  trace!("get_user_by_wallet with: {address}");
+  let username = sqlx::query_scalar!(
+    "SELECT username FROM user_wallets WHERE address = $1",
+    address.to_lowercase()
+  )
+  .fetch_optional(pool)
+  .await?;
+
+  match username {
+    Some(username) => super::users::get_user(pool, &username.into()).await,
+    None => Ok(None),
+  }
+}
+
+/// Get the wallets linked to `username`, oldest first.
+pub async fn get_user_wallets(pool: &DbPool, username: &Username) -> DbResult<Vec<UserWallet>> {
+  trace!("get_user_wallets for: {username}");
+  sqlx::query_as!(
+    UserWallet,
+    "SELECT address, username, created FROM user_wallets WHERE username = $1 ORDER BY created",
+    username.0
+  )
+  .fetch_all(pool)
+  .await
+  .map_err(DbError::from)
+}
@@ -30,8 +30,26 @@ pub async fn get_assert_user(pool: &DbPool, username: &Username) -> DbResult<Use
   get_user(pool, username).await?.ok_or(DbError::NotFound("user".into()))
 }
 
-/// Create a new user in the database.
-pub async fn create_user(pool: &DbPool, new_user: &User) -> DbResult<()> {
+/// Return whether `username` is free to register, ignoring case.
+pub async fn username_available(pool: &DbPool, username: &Username) -> DbResult<bool> {
+  trace!("username_available called w username: {username}");
+  sqlx::query_scalar!(
+    "SELECT NOT EXISTS (SELECT 1 FROM users WHERE LOWER(username) = LOWER($1)) as \"available!\"",
+    username.0
+  )
+  .fetch_one(pool)
+  .await
+  .map_err(DbError::from)
+}
+
+/// Create a new user in the database, consuming `invite_code` if given.
+///
+/// Err(NotFound) if the invite code does not exist or was already used.
+pub async fn create_user(
+  pool: &DbPool,
+  new_user: &User,
+  invite_code: Option<&str>,
+) -> DbResult<()> {
   trace!("create_user with: {new_user:?}");
   let mut tx = pool.begin().await?;
 
@@ -58,6 +76,10 @@ pub async fn create_user(pool: &DbPool, new_user: &User) -> DbResult<()> {
   .execute(&mut *tx)
   .await?;
 
+  if let Some(code) = invite_code {
+    super::invite_codes::consume_invite_code(&mut tx, code, &username).await?;
+  }
+
   tx.commit().await?;
   Ok(())
 }
@@ -90,6 +112,148 @@ pub async fn update_user(
   Ok(tx.commit().await?)
 }
 
+/// Delete a user's account in a single transaction:
+/// - reverse the points and karma awarded by the user's counted votes, then delete the votes
+/// - delete the user's favorites, sessions, notifications, webhooks, karma ledger, and unused
+///   invite codes
+/// - reassign the user's items and comments to the `[deleted]` placeholder, so threads stay intact
+/// - delete the user
+///
+/// Points the user's content received stay on the content, but the karma goes with the user.
+pub async fn delete_user(pool: &DbPool, username: &Username) -> DbResult<()> {
+  trace!("delete_user with: {username}");
+  let mut tx = pool.begin().await?;
+
+  // take back the karma the user's votes gave to other users; deleted content already gave its
+  // karma back
+  let recipients = sqlx::query!(
+    "SELECT content.username, SUM(CASE v.vote_state
+       WHEN 'upvote' THEN 1 WHEN 'downvote' THEN -1 ELSE 0 END)::INT AS \"delta!\"
+     FROM user_votes v
+     JOIN (
+       SELECT id, username FROM items WHERE deleted_at IS NULL
+       UNION ALL SELECT id, username FROM comments WHERE deleted_at IS NULL
+     ) content ON content.id = v.content_id
+     WHERE v.username = $1 AND content.username <> $1 AND NOT v.discounted
+     GROUP BY content.username",
+    username.0
+  )
+  .fetch_all(&mut *tx)
+  .await?;
+  for recipient in recipients {
+    let Some(recipient_username) = recipient.username else { continue };
+    let recipient_username: Username = recipient_username.into();
+    adjust_karma(&mut tx, &recipient_username, -recipient.delta, KarmaReason::VoterDeleted, None)
+      .await?;
+  }
+
+  // take back the points the user's votes gave to items and comments
+  sqlx::query!(
+    "UPDATE items SET points = items.points - votes.delta
+     FROM (
+       SELECT content_id, SUM(CASE vote_state
+         WHEN 'upvote' THEN 1 WHEN 'downvote' THEN -1 ELSE 0 END) AS delta
+       FROM user_votes WHERE username = $1 AND vote_type = 'item' AND NOT discounted
+       GROUP BY content_id
+     ) votes
+     WHERE items.id = votes.content_id",
+    username.0
+  )
+  .execute(&mut *tx)
+  .await?;
+  sqlx::query!(
+    "UPDATE comments SET points = GREATEST(comments.points - votes.delta, $2)
+     FROM (
+       SELECT content_id, SUM(CASE vote_state
+         WHEN 'upvote' THEN 1 WHEN 'downvote' THEN -1 ELSE 0 END) AS delta
+       FROM user_votes WHERE username = $1 AND vote_type = 'comment' AND NOT discounted
+       GROUP BY content_id
+     ) votes
+     WHERE comments.id = votes.content_id",
+    username.0,
+    crate::MIN_COMMENT_POINTS
+  )
+  .execute(&mut *tx)
+  .await?;
+
+  sqlx::query!("DELETE FROM user_votes WHERE username = $1", username.0).execute(&mut *tx).await?;
+  sqlx::query!("DELETE FROM user_favorites WHERE username = $1", username.0)
+    .execute(&mut *tx)
+    .await?;
+  super::user_sessions::delete_all_user_sessions_with(&mut tx, username).await?;
+  sqlx::query!("DELETE FROM notifications WHERE username = $1", username.0)
+    .execute(&mut *tx)
+    .await?;
+  sqlx::query!("DELETE FROM webhooks WHERE username = $1", username.0).execute(&mut *tx).await?;
+  sqlx::query!("DELETE FROM user_wallets WHERE username = $1", username.0)
+    .execute(&mut *tx)
+    .await?;
+  sqlx::query!("DELETE FROM anon_token_issuances WHERE username = $1", username.0)
+    .execute(&mut *tx)
+    .await?;
+  sqlx::query!("DELETE FROM karma_ledger WHERE username = $1", username.0)
+    .execute(&mut *tx)
+    .await?;
+  // unused invites are revoked; used ones stay, so invite trees remain intact
+  sqlx::query!("DELETE FROM invite_codes WHERE created_by = $1 AND used_by IS NULL", username.0)
+    .execute(&mut *tx)
+    .await?;
+  sqlx::query!(
+    "UPDATE invite_codes SET created_by = $1 WHERE created_by = $2",
+    DELETED_USERNAME,
+    username.0
+  )
+  .execute(&mut *tx)
+  .await?;
+  sqlx::query!(
+    "UPDATE invite_codes SET used_by = $1 WHERE used_by = $2",
+    DELETED_USERNAME,
+    username.0
+  )
+  .execute(&mut *tx)
+  .await?;
+  sqlx::query!(
+    "UPDATE notifications SET comment_by = $1 WHERE comment_by = $2",
+    DELETED_USERNAME,
+    username.0
+  )
+  .execute(&mut *tx)
+  .await?;
+  sqlx::query!(
+    "UPDATE vote_flags SET
+       usernames = array_replace(usernames, $2, $1),
+       target_username = CASE WHEN target_username = $2 THEN $1 ELSE target_username END,
+       reviewed_by = CASE WHEN reviewed_by = $2 THEN $1 ELSE reviewed_by END
+     WHERE $2 = ANY(usernames) OR target_username = $2 OR reviewed_by = $2",
+    DELETED_USERNAME,
+    username.0
+  )
+  .execute(&mut *tx)
+  .await?;
+  sqlx::query!(
+    "UPDATE revisions SET edited_by = $1 WHERE edited_by = $2",
+    DELETED_USERNAME,
+    username.0
+  )
+  .execute(&mut *tx)
+  .await?;
+
+  sqlx::query!("UPDATE items SET username = $1 WHERE username = $2", DELETED_USERNAME, username.0)
+    .execute(&mut *tx)
+    .await?;
+  sqlx::query!(
+    "UPDATE comments SET username = $1 WHERE username = $2",
+    DELETED_USERNAME,
+    username.0
+  )
+  .execute(&mut *tx)
+  .await?;
+
+  sqlx::query!("DELETE FROM users WHERE username = $1", username.0).execute(&mut *tx).await?;
+
+  Ok(tx.commit().await?)
+}
+
 pub async fn update_user_password_token(
   pool: &DbPool,
   username: &Username,
@@ -175,3 +339,26 @@ pub async fn update_user_password(
 //   .fetch_all(pool)
 //   .await?
 // }
+
+/// The users `@mentioned` in `text` that exist, as stored, e.g. to link them when sanitizing.
+pub async fn resolve_mentions(pool: &DbPool, text: &str) -> DbResult<Vec<Username>> {
+  let mentions = crate::extract_mentions(text);
+  if mentions.is_empty() {
+    return Ok(mentions);
+  }
+  resolve_mentions_with(&mut *pool.acquire().await?, &mentions).await
+}
+
+/// Of the `mentions`, the usernames of those that exist, as stored. Matching is case-insensitive.
+pub(crate) async fn resolve_mentions_with(
+  conn: &mut PgConnection,
+  mentions: &[Username],
+) -> DbResult<Vec<Username>> {
+  let lowercase: Vec<String> = mentions.iter().map(|m| m.0.to_lowercase()).collect();
+  let usernames =
+    sqlx::query_scalar!("SELECT username FROM users WHERE LOWER(username) = ANY($1)", &lowercase)
+      .fetch_all(conn)
+      .await?;
+
+  Ok(usernames.into_iter().map(Username::from).collect())
+}
@@ -0,0 +1,228 @@
+use super::*;
+
+// todo(config)
+pub const VOTE_FLAGS_PAGE_SIZE: i64 = 30;
+
+/// Get the upvotes cast since `since` that still count, oldest first, excluding votes on the
+/// voter's own content.
+pub async fn get_recent_upvotes(pool: &DbPool, since: &Timestamp) -> DbResult<Vec<RecentUpvote>> {
+  trace!("get_recent_upvotes since: {since:?}");
+  sqlx::query_as!(
+    RecentUpvote,
+    "SELECT
+       v.id,
+       v.username AS voter,
+       content.username AS \"author!\",
+       v.content_id,
+       v.vote_type AS \"vote_type: ItemOrComment\",
+       v.created,
+       u.created AS voter_created,
+       (SELECT MIN(s.created) FROM (
+         SELECT created FROM items WHERE username = v.username
+         UNION ALL SELECT created FROM comments WHERE username = v.username
+       ) s) AS voter_first_submission
+     FROM user_votes v
+     JOIN (SELECT id, username FROM items UNION ALL SELECT id, username FROM comments) content
+       ON content.id = v.content_id
+     JOIN users u ON u.username = v.username
+     WHERE v.vote_state = 'upvote' AND NOT v.discounted AND v.created >= $1
+       AND v.username <> content.username
+     ORDER BY v.created",
+    since.0
+  )
+  .fetch_all(pool)
+  .await
+  .map_err(DbError::from)
+}
+
+/// Record `flags`, skipping any whose pattern was already flagged. If `discount`, the newly
+/// flagged votes stop counting.
+///
+/// Return the flags recorded.
+pub async fn create_vote_flags(
+  pool: &DbPool,
+  flags: &[VoteFlag],
+  discount: bool,
+) -> DbResult<Vec<VoteFlag>> {
+  debug!("create_vote_flags with {} flags, discount: {discount}", flags.len());
+  let mut tx = pool.begin().await?;
+  let mut created = Vec::new();
+  for flag in flags {
+    let inserted = sqlx::query!(
+      "INSERT INTO vote_flags
+       (id, kind, usernames, target_username, content_id, vote_ids, fingerprint, discounted, \
+       created)
+       VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+       ON CONFLICT (kind, fingerprint) DO NOTHING",
+      flag.id.0,
+      flag.kind as VoteFlagKind,
+      &flag.usernames,
+      flag.target_username,
+      flag.content_id,
+      &flag.vote_ids,
+      flag.fingerprint,
+      discount,
+      flag.created.0,
+    )
+    .execute(&mut *tx)
+    .await?
+    .rows_affected()
+      > 0;
+    if !inserted {
+      continue;
+    }
+    if discount {
+      set_votes_discounted_with(&mut tx, &flag.vote_ids, true).await?;
+    }
+    created.push(VoteFlag { discounted: discount, ..flag.clone() });
+  }
+
+  tx.commit().await?;
+  Ok(created)
+}
+
+/// Get the `page` of vote flags, oldest first, optionally only those with `status`.
+pub async fn get_vote_flags(
+  pool: &DbPool,
+  status: Option<VoteFlagStatus>,
+  page: &Page,
+) -> DbResult<Vec<VoteFlag>> {
+  sqlx::query_as!(
+    VoteFlag,
+    "SELECT
+       id,
+       kind AS \"kind: VoteFlagKind\",
+       usernames,
+       target_username,
+       content_id,
+       vote_ids,
+       fingerprint,
+       status AS \"status: VoteFlagStatus\",
+       discounted,
+       created,
+       reviewed_by,
+       reviewed
+     FROM vote_flags
+     WHERE $1::vote_flag_status_enum IS NULL OR status = $1
+     ORDER BY created
+     LIMIT $2 OFFSET $3",
+    status as Option<VoteFlagStatus>,
+    VOTE_FLAGS_PAGE_SIZE,
+    (page.page - 1) * VOTE_FLAGS_PAGE_SIZE
+  )
+  .fetch_all(pool)
+  .await
+  .map_err(DbError::from)
+}
+
+pub async fn get_assert_vote_flag(pool: &DbPool, id: &Ulid) -> DbResult<VoteFlag> {
+  sqlx::query_as!(
+    VoteFlag,
+    "SELECT
+       id,
+       kind AS \"kind: VoteFlagKind\",
+       usernames,
+       target_username,
+       content_id,
+       vote_ids,
+       fingerprint,
+       status AS \"status: VoteFlagStatus\",
+       discounted,
+       created,
+       reviewed_by,
+       reviewed
+     FROM vote_flags WHERE id = $1",
+    id.0
+  )
+  .fetch_optional(pool)
+  .await?
+  .ok_or(DbError::NotFound("vote flag".into()))
+}
+
+/// Record a moderator's review of a vote flag, and set whether its votes count.
+///
+/// Votes discounted by another flag are discounted again if restored here; whichever flag is
+/// reviewed last decides.
+pub async fn review_vote_flag(
+  pool: &DbPool,
+  id: &Ulid,
+  moderator: &Username,
+  status: VoteFlagStatus,
+  discount: bool,
+) -> DbResult<()> {
+  debug!("review_vote_flag {id} by {moderator}: {status:?}, discount: {discount}");
+  let mut tx = pool.begin().await?;
+  let vote_ids = sqlx::query_scalar!(
+    "UPDATE vote_flags SET status = $2, discounted = $3, reviewed_by = $4, reviewed = $5
+     WHERE id = $1
+     RETURNING vote_ids",
+    id.0,
+    status as VoteFlagStatus,
+    discount,
+    moderator.0,
+    now().0,
+  )
+  .fetch_optional(&mut *tx)
+  .await?
+  .ok_or(DbError::NotFound("vote flag".into()))?;
+  set_votes_discounted_with(&mut tx, &vote_ids, discount).await?;
+
+  Ok(tx.commit().await?)
+}
+
+/// Discount or restore the votes with `vote_ids`, taking their points off, or putting them back
+/// on, the content voted on and its author's karma. Votes already in that state are skipped.
+pub(crate) async fn set_votes_discounted_with(
+  conn: &mut PgConnection,
+  vote_ids: &[String],
+  discounted: bool,
+) -> DbResult<()> {
+  let votes = sqlx::query!(
+    "UPDATE user_votes SET discounted = $2
+     WHERE id = ANY($1) AND discounted <> $2
+     RETURNING
+       vote_type AS \"vote_type: ItemOrComment\",
+       content_id,
+       vote_state AS \"vote_state: VoteState\"",
+    vote_ids,
+    discounted
+  )
+  .fetch_all(&mut *conn)
+  .await?;
+
+  for vote in votes {
+    let delta = if discounted { -i32::from(vote.vote_state) } else { i32::from(vote.vote_state) };
+    if delta == 0 {
+      continue;
+    }
+    let author = match vote.vote_type {
+      ItemOrComment::Item =>
+        sqlx::query_scalar!(
+          "UPDATE items SET points = points + $1 WHERE id = $2
+           RETURNING CASE WHEN deleted_at IS NULL THEN username END",
+          delta,
+          vote.content_id
+        )
+        .fetch_optional(&mut *conn)
+        .await?,
+      ItemOrComment::Comment =>
+        sqlx::query_scalar!(
+          "UPDATE comments SET points = GREATEST(points + $1, $3) WHERE id = $2
+           RETURNING CASE WHEN deleted_at IS NULL THEN username END",
+          delta,
+          vote.content_id,
+          crate::MIN_COMMENT_POINTS
+        )
+        .fetch_optional(&mut *conn)
+        .await?,
+    };
+    // the content may have been deleted since, taking its karma with it
+    if let Some(author) = author.flatten() {
+      let author: Username = author.into();
+      adjust_karma(&mut *conn, &author, delta, KarmaReason::VoteDiscounted, Some(&vote.content_id))
+        .await?;
+    }
+  }
+
+  Ok(())
+}
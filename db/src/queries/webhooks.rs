@@ -0,0 +1,215 @@
+use super::*;
+
+// todo(config)
+pub const WEBHOOK_DELIVERIES_PAGE_SIZE: i64 = 30;
+
+pub async fn create_webhook(pool: &DbPool, webhook: &Webhook) -> DbResult<()> {
+  trace!("create_webhook for: {}", webhook.username);
+  let Webhook { id, username, url, secret, events, active, created } = webhook.clone();
+
+  sqlx::query!(
+    "INSERT INTO webhooks (id, username, url, secret, events, active, created)
+     VALUES ($1, $2, $3, $4, $5, $6, $7)",
+    id.0,
+    username.0,
+    url.0,
+    secret,
+    &events,
+    active,
+    created.0,
+  )
+  .execute(pool)
+  .await?;
+
+  Ok(())
+}
+
+pub async fn get_assert_webhook(pool: &DbPool, id: &Ulid) -> DbResult<Webhook> {
+  trace!("get_assert_webhook with: {id}");
+  sqlx::query_as!(
+    Webhook,
+    "SELECT id, username, url as \"url: Url\", secret, events, active, created
+     FROM webhooks WHERE id = $1",
+    id.0
+  )
+  .fetch_optional(pool)
+  .await?
+  .ok_or(DbError::NotFound("webhook".into()))
+}
+
+/// Get the webhooks registered by `username`, or every webhook if `username` is None.
+pub async fn get_webhooks(pool: &DbPool, username: Option<&Username>) -> DbResult<Vec<Webhook>> {
+  trace!("get_webhooks for: {username:?}");
+  sqlx::query_as!(
+    Webhook,
+    "SELECT id, username, url as \"url: Url\", secret, events, active, created
+     FROM webhooks WHERE $1::TEXT IS NULL OR username = $1
+     ORDER BY created DESC",
+    username.map(|u| u.0.clone())
+  )
+  .fetch_all(pool)
+  .await
+  .map_err(DbError::from)
+}
+
+/// Delete a webhook, and its deliveries.
+pub async fn delete_webhook(pool: &DbPool, id: &Ulid) -> DbResult<()> {
+  trace!("delete_webhook with: {id}");
+  sqlx::query!("DELETE FROM webhooks WHERE id = $1", id.0).execute(pool).await?;
+  Ok(())
+}
+
+/// Queue a delivery of `event` to each active webhook subscribed to it.
+///
+/// Called within the transaction that makes the change, so that an event is queued if and only if
+/// the change is committed.
+pub(crate) async fn enqueue_webhook_event(
+  conn: &mut PgConnection,
+  event: WebhookEvent,
+  data: &impl Serialize,
+) -> DbResult<()> {
+  let event_name = event.to_string();
+  let webhook_ids =
+    sqlx::query_scalar!("SELECT id FROM webhooks WHERE active AND $1 = ANY(events)", event_name)
+      .fetch_all(&mut *conn)
+      .await?;
+  if webhook_ids.is_empty() {
+    return Ok(());
+  }
+  trace!("enqueue_webhook_event {event_name} for {} webhooks", webhook_ids.len());
+
+  for webhook_id in webhook_ids {
+    let id = Ulid::new();
+    let payload = serde_json::json!({
+      "id": id,
+      "event": event_name,
+      "created": now(),
+      "data": data,
+    })
+    .to_string();
+
+    sqlx::query!(
+      "INSERT INTO webhook_deliveries (id, webhook_id, event, payload) VALUES ($1, $2, $3, $4)",
+      id.0,
+      webhook_id,
+      event_name,
+      payload,
+    )
+    .execute(&mut *conn)
+    .await?;
+  }
+
+  Ok(())
+}
+
+/// Claim up to `limit` pending deliveries that are due, with their webhooks.
+///
+/// Claimed deliveries are not due again until `lease_until`, so that concurrent workers don't
+/// deliver them twice, and so that a worker dying mid-delivery doesn't lose them.
+pub async fn claim_due_webhook_deliveries(
+  pool: &DbPool,
+  limit: i64,
+  lease_until: &Timestamp,
+) -> DbResult<Vec<(WebhookDelivery, Webhook)>> {
+  let deliveries = sqlx::query_as!(
+    WebhookDelivery,
+    "UPDATE webhook_deliveries SET next_attempt = $2
+     WHERE id IN (
+       SELECT id FROM webhook_deliveries
+       WHERE status = 'pending' AND next_attempt <= NOW()
+       ORDER BY next_attempt
+       LIMIT $1
+       FOR UPDATE SKIP LOCKED
+     )
+     RETURNING
+       id,
+       webhook_id,
+       event,
+       payload,
+       status as \"status: WebhookDeliveryStatus\",
+       attempts,
+       next_attempt,
+       response_status,
+       error,
+       created,
+       delivered",
+    limit,
+    lease_until.0
+  )
+  .fetch_all(pool)
+  .await?;
+
+  let mut due = Vec::with_capacity(deliveries.len());
+  for delivery in deliveries {
+    let webhook = get_assert_webhook(pool, &delivery.webhook_id).await?;
+    due.push((delivery, webhook));
+  }
+  Ok(due)
+}
+
+/// Record the outcome of an attempt to deliver `id`.
+///
+/// `status` is `Delivered` on success, `Failed` once retries are exhausted, and otherwise
+/// `Pending`, to be retried at `next_attempt`.
+pub async fn record_webhook_attempt(
+  pool: &DbPool,
+  id: &Ulid,
+  status: WebhookDeliveryStatus,
+  response_status: Option<i32>,
+  error: Option<String>,
+  next_attempt: &Timestamp,
+) -> DbResult<()> {
+  trace!("record_webhook_attempt for {id}: {status:?}");
+  sqlx::query!(
+    "UPDATE webhook_deliveries SET
+       status = $2,
+       attempts = attempts + 1,
+       response_status = $3,
+       error = $4,
+       next_attempt = $5,
+       delivered = CASE WHEN $2 = 'delivered'::webhook_delivery_status_enum THEN NOW() END
+     WHERE id = $1",
+    id.0,
+    status as WebhookDeliveryStatus,
+    response_status,
+    error,
+    next_attempt.0,
+  )
+  .execute(pool)
+  .await?;
+
+  Ok(())
+}
+
+/// Get the `page` of deliveries for a webhook, newest first.
+pub async fn get_webhook_deliveries(
+  pool: &DbPool,
+  webhook_id: &Ulid,
+  page: &Page,
+) -> DbResult<Vec<WebhookDelivery>> {
+  trace!("get_webhook_deliveries for: {webhook_id}");
+  sqlx::query_as!(
+    WebhookDelivery,
+    "SELECT
+       id,
+       webhook_id,
+       event,
+       payload,
+       status as \"status: WebhookDeliveryStatus\",
+       attempts,
+       next_attempt,
+       response_status,
+       error,
+       created,
+       delivered
+     FROM webhook_deliveries WHERE webhook_id = $1
+     ORDER BY created DESC
+     LIMIT $2 OFFSET $3",
+    webhook_id.0,
+    WEBHOOK_DELIVERIES_PAGE_SIZE,
+    (page.page - 1) * WEBHOOK_DELIVERIES_PAGE_SIZE
+  )
+  .fetch_all(pool)
+  .await
+  .map_err(DbError::from)
+}
@@ -186,24 +186,34 @@ impl Default for Text {
 impl From<&str> for Text {
   fn from(s: &str) -> Self { Self(s.into()) }
 }
+impl From<String> for Text {
+  fn from(s: String) -> Self { Self(s) }
+}
 
+/// An item's content: a url, text, or both, but not neither.
 #[derive(Debug, Clone, Serialize, Deserialize, Validate, ToSchema)]
 #[serde(rename_all = "camelCase")]
-#[schema(default = TextOrUrl::default, example=TextOrUrl::default)]
-pub enum TextOrUrl {
-  Text(#[garde(dive)] Text),
-  Url(#[garde(dive)] Url),
+#[schema(default = ItemContent::default, example=ItemContent::default)]
+pub struct ItemContent {
+  #[garde(dive)]
+  pub url:  Option<Url>,
+  #[garde(dive, custom(crate::utils::validate_url_or_text(&self.url)))]
+  pub text: Option<Text>,
 }
-impl TextOrUrl {
+impl ItemContent {
+  pub fn new(url: Option<Url>, text: Option<Text>) -> Self { Self { url, text } }
+
+  pub fn url(url: Url) -> Self { Self::new(Some(url), None) }
+
+  pub fn text(text: Text) -> Self { Self::new(None, Some(text)) }
+
   pub fn url_domain_text(self) -> (Option<Url>, Option<Domain>, Option<Text>) {
-    match self {
-      TextOrUrl::Text(text) => (None, None, Some(text.clone())),
-      TextOrUrl::Url(url) => (Some(url.clone()), Some(Domain::from(url)), None),
-    }
+    let domain = self.url.clone().map(Domain::from);
+    (self.url, domain, self.text)
   }
 }
-impl Default for TextOrUrl {
-  fn default() -> Self { Self::Url(Url::default()) }
+impl Default for ItemContent {
+  fn default() -> Self { Self::url(Url::default()) }
 }
 
 /// `ulid::Ulid` does not implement encode, so define a newtype wrapping a String instead
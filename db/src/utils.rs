@@ -11,26 +11,106 @@ use tracing_subscriber::{
   util::SubscriberInitExt,
 };
 
-use crate::{error::DbError, Timestamp};
+use crate::{error::DbError, Text, Timestamp, Url, Username, MAX_MENTIONS};
 
 static USERNAME_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[0-9A-Za-z_]+$").unwrap());
+/// `@username`, at the start of the text or after whitespace, so that emails and urls don't match,
+/// or opening the profile link `link_mentions` wraps it in, so that sanitized text still matches.
+static MENTION_REGEX: Lazy<Regex> =
+  Lazy::new(|| Regex::new(r"(^|\s|>)@([0-9A-Za-z_]{3,25})\b").unwrap());
+
+/// Usernames that may not be registered, compared case-insensitively.
+const RESERVED_USERNAMES: &[&str] = &[
+  "admin",
+  "administrator",
+  "anonymous",
+  "deleted",
+  "mod",
+  "moderator",
+  "moderators",
+  "root",
+  "staff",
+  "support",
+  "system",
+  "zkhn",
+];
+
+/// Garde validator for usernames being registered: they contain only ascii letters, digits, and
+/// underscores, and are not reserved.
+///
+/// Accounts created before these rules may break them, so `Username` itself doesn't check them,
+/// and such users can still log in and be looked up.
+pub fn validate_new_username(username: &Username, _context: &()) -> garde::Result {
+  let value = username.0.as_str();
+  if !USERNAME_REGEX.is_match(value) {
+    return Err(garde::Error::new("may only contain letters, numbers, and underscores"));
+  }
+  if RESERVED_USERNAMES.iter().any(|reserved| reserved.eq_ignore_ascii_case(value)) {
+    return Err(garde::Error::new("is reserved"));
+  }
+  Ok(())
+}
+
+/// Garde validator: an item has a url, text, or both.
+pub(crate) fn validate_url_or_text(
+  url: &Option<Url>,
+) -> impl FnOnce(&Option<Text>, &()) -> garde::Result + '_ {
+  move |text, _context| match (url, text) {
+    (None, None) => Err(garde::Error::new("an item needs a url, text, or both")),
+    _ => Ok(()),
+  }
+}
 
 pub fn now() -> Timestamp { Utc::now().into() }
 
+/// The distinct usernames `@mentioned` in `text`, in order of first appearance, ignoring any past
+/// the first `MAX_MENTIONS`. Mentions are not checked against `users`.
+pub fn extract_mentions(text: &str) -> Vec<Username> {
+  let mut mentions: Vec<Username> = Vec::new();
+  for capture in MENTION_REGEX.captures_iter(text) {
+    let username = &capture[2];
+    if !mentions.iter().any(|m| m.0.eq_ignore_ascii_case(username)) {
+      mentions.push(username.into());
+    }
+    if mentions.len() == MAX_MENTIONS {
+      break;
+    }
+  }
+  mentions
+}
+
+/// Replace each `@mention` of a user in `mentions` with a link to their profile.
+///
+/// `mentions` should hold the mentions resolved against `users`, so that only real users are
+/// linked. Matching is case-insensitive; links use the username as stored.
+pub fn link_mentions(text: &str, mentions: &[Username]) -> String {
+  MENTION_REGEX
+    .replace_all(text, |capture: &regex::Captures| {
+      let (prefix, mentioned) = (&capture[1], &capture[2]);
+      match mentions.iter().find(|m| m.0.eq_ignore_ascii_case(mentioned)) {
+        Some(username) => format!("{prefix}<a href=\"/users/{username}\">@{mentioned}</a>"),
+        None => capture[0].to_string(),
+      }
+    })
+    .to_string()
+}
+
 // todo(sanitize)
 /// Sanitize text:
 /// - Trim whitespace
 /// - Remove HTML tags
 /// - Parse Markdown
 /// - Parse Latex - todo(latex)
-/// - Linkify URLs
+/// - Linkify URLs and resolved `mentions`
 /// - Prevent XSS attacks with `ammonia`
-pub fn sanitize_text(text: &str) -> String {
+pub fn sanitize_text(text: &str, mentions: &[Username]) -> String {
   let mut text = text.to_string();
   text = text.trim().to_string();
   // Remove HTML Tags
   let re_tags = Regex::new(r"<[^>]+>").unwrap();
   text = re_tags.replace_all(&text, "").to_string();
+  // Link mentions to profiles
+  text = link_mentions(&text, mentions);
   // Replace Markdown-like Italic Syntax
   let re_italic = Regex::new(r"\*([^*]+)\*").unwrap();
   text = re_italic.replace_all(&text, "<i>$1</i>").to_string();
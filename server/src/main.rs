@@ -26,7 +26,8 @@ async fn main(
       Key::generate()
     });
 
-  let app = api::app(pool, session_key).await.expect("failed to build app")
+  let config = utils::api_config(&secret_store).unwrap();
+  let app = api::app(pool, session_key, config).await.expect("failed to build app")
     .layer(cors::cors_layer())
     // prod(analytics)
     // .layer(Analytics::new(analytics_key.unwrap_or("".to_string()))) 
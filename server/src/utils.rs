@@ -1,4 +1,5 @@
 use anyhow::Context;
+use api::{ApiConfig, Privilege};
 use tracing_subscriber::filter::EnvFilter;
 
 use crate::error::ServerError;
@@ -21,3 +22,98 @@ pub(crate) fn setup(secret_store: &shuttle_runtime::SecretStore) -> Result<(), S
 
   Ok(())
 }
+
+/// Build the api configuration, overriding defaults with any values set in the secret store.
+pub(crate) fn api_config(
+  secret_store: &shuttle_runtime::SecretStore,
+) -> Result<ApiConfig, ServerError> {
+  let mut config = ApiConfig::default();
+
+  let get_u32 = |key: &str| -> Result<Option<u32>, ServerError> {
+    Ok(
+      secret_store
+        .get(key)
+        .map(|v| v.parse())
+        .transpose()
+        .with_context(|| format!("invalid {key}"))?,
+    )
+  };
+  // argon2 parameters; changing these causes existing hashes to be replaced on login
+  match (get_u32("ARGON2_M_COST")?, get_u32("ARGON2_T_COST")?, get_u32("ARGON2_P_COST")?) {
+    (Some(m_cost), Some(t_cost), Some(p_cost)) =>
+      config = config.with_argon2_params(m_cost, t_cost, p_cost)?,
+    (None, None, None) => {},
+    _ => {
+      let e =
+        anyhow::anyhow!("ARGON2_M_COST, ARGON2_T_COST and ARGON2_P_COST must be set together");
+      return Err(e.into());
+    },
+  }
+  if let Some(mode) = secret_store.get("REGISTRATION_MODE") {
+    config = config.with_registration_mode(mode.parse()?);
+  }
+  // karma thresholds, e.g. KARMA_TO_DOWNVOTE or KARMA_TO_SUBMIT_LINKS
+  for privilege in Privilege::ALL {
+    let key = format!("KARMA_TO_{}", privilege.as_str().to_uppercase().replace('-', "_"));
+    if let Some(karma) = secret_store.get(&key) {
+      config = config
+        .with_privilege_karma(privilege, karma.parse().with_context(|| format!("invalid {key}"))?);
+    }
+  }
+  if let Some(require) = secret_store.get("REQUIRE_PROOF_OF_WORK") {
+    let require = require.parse().context("invalid REQUIRE_PROOF_OF_WORK")?;
+    let difficulty = get_u32("POW_BASE_DIFFICULTY")?.unwrap_or(config.pow_base_difficulty);
+    config = config.with_proof_of_work(require, difficulty);
+  }
+  if let Some(domain) = secret_store.get("SIWE_DOMAIN") {
+    config = config.with_siwe_domain(&domain);
+  }
+  if let Some(pem) = secret_store.get("ANON_TOKEN_KEY") {
+    config = config.with_anon_token_key(&pem);
+  }
+  // let webhooks target local receivers, for development and tests
+  if let Some(allow) = secret_store.get("WEBHOOK_ALLOW_PRIVATE_URLS") {
+    config = config
+      .with_private_webhook_urls(allow.parse().context("invalid WEBHOOK_ALLOW_PRIVATE_URLS")?);
+  }
+  if let Some(secs) = secret_store.get("WEBHOOK_POLL_SECS") {
+    let secs = secs.parse().context("invalid WEBHOOK_POLL_SECS")?;
+    config = config.with_webhook_poll_interval(std::time::Duration::from_secs(secs));
+  }
+  let export_inline_limit = match secret_store.get("EXPORT_INLINE_LIMIT") {
+    Some(limit) => limit.parse().context("invalid EXPORT_INLINE_LIMIT")?,
+    None => config.export_inline_limit,
+  };
+  let export_poll_interval = match secret_store.get("EXPORT_POLL_SECS") {
+    Some(secs) => std::time::Duration::from_secs(secs.parse().context("invalid EXPORT_POLL_SECS")?),
+    None => config.export_poll_interval,
+  };
+  config = config.with_data_exports(export_inline_limit, export_poll_interval);
+  // 0 disables the scheduled job
+  if let Some(secs) = secret_store.get("KARMA_RECONCILE_SECS") {
+    let secs: u64 = secs.parse().context("invalid KARMA_RECONCILE_SECS")?;
+    config = config
+      .with_karma_reconcile_interval((secs > 0).then(|| std::time::Duration::from_secs(secs)));
+  }
+  let vote_analysis_interval = match secret_store.get("VOTE_ANALYSIS_SECS") {
+    Some(secs) => {
+      let secs: u64 = secs.parse().context("invalid VOTE_ANALYSIS_SECS")?;
+      (secs > 0).then(|| std::time::Duration::from_secs(secs))
+    },
+    None => config.vote_analysis_interval,
+  };
+  let discount_flagged_votes = match secret_store.get("DISCOUNT_FLAGGED_VOTES") {
+    Some(discount) => discount.parse().context("invalid DISCOUNT_FLAGGED_VOTES")?,
+    None => config.discount_flagged_votes,
+  };
+  config = config.with_vote_analysis(vote_analysis_interval, discount_flagged_votes);
+  let show_prefix = secret_store.get("SHOW_TITLE_PREFIX");
+  let ask_prefix = secret_store.get("ASK_TITLE_PREFIX");
+  if show_prefix.is_some() || ask_prefix.is_some() {
+    let show = show_prefix.unwrap_or_else(|| config.title_prefixes.show.clone());
+    let ask = ask_prefix.unwrap_or_else(|| config.title_prefixes.ask.clone());
+    config = config.with_title_prefixes(&show, &ask);
+  }
+
+  Ok(config)
+}
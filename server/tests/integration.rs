@@ -6,16 +6,23 @@ use api::*;
 use db::{
   models::{
     item::{Item, ItemCategory, ItemType},
+    notification::NotificationType,
     user_favorite::{FavoriteStateEnum, UserFavorite},
     user_vote::VoteState,
+    vote_flag::VoteFlagStatus,
+    webhook::{WebhookDeliveryStatus, WebhookEvent},
   },
-  Ulid,
+  ItemContent, Ulid, Url,
 };
+use k256::ecdsa::SigningKey;
 use reqwest::Client;
 use serial_test::serial;
 
 use self::integration_utils::cargo_shuttle_run;
-use crate::integration_utils::{send, send_get};
+use crate::integration_utils::{
+  await_data_export, await_webhooks, cargo_shuttle_run_with_secrets, make_moderator, psql, send,
+  send_get, webhook_receiver,
+};
 
 pub const WEBSERVER_URL: &str = "http://localhost:8000";
 
@@ -50,6 +57,957 @@ async fn user_crud() {
     ChangePasswordPayload::new("alice", Some("new_password"), None, "password").unwrap();
   send(&c, new_payload, "PUT", "users/change-password", 200, "d").await;
   send(&c, "", "GET", "users/alice", 200, "e").await;
+
+  // usernames are unique regardless of case, and must match the username rules
+  let available =
+    send_get::<UsernameAvailableResponse>(&c, "", "GET", "users/available/ALICE", 200, "f").await;
+  assert!(!available.available);
+  let available =
+    send_get::<UsernameAvailableResponse>(&c, "", "GET", "users/available/carol", 200, "g").await;
+  assert!(available.available);
+  send(&c, "", "GET", "users/available/Admin", 422, "h").await;
+  send(&c, "", "GET", "users/available/not-valid", 422, "i").await;
+  let shouty_alice = CreateUserPayload { username: "ALICE".into(), ..CreateUserPayload::default() };
+  send(&c, shouty_alice, "POST", "users", 409, "j").await;
+  let reserved = CreateUserPayload { username: "admin".into(), ..CreateUserPayload::default() };
+  send(&c, reserved, "POST", "users", 422, "k").await;
+  // the rules are for new accounts only, so older accounts breaking them may still be looked up
+  send(&c, "", "GET", "users/foo-bar", 404, "l").await;
+  send(&c, CredentialsPayload::new("admin", "password", None), "POST", "users/login", 401, "m")
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn user_sessions() {
+  let mut _child_guard = cargo_shuttle_run().await;
+  let c = Client::builder().cookie_store(true).build().unwrap();
+  let c2 = Client::builder().cookie_store(true).build().unwrap();
+
+  send(&c, "", "GET", "users/sessions", 401, "00").await;
+  send(&c, CreateUserPayload::default(), "POST", "users", 200, "01").await;
+  send(&c, CredentialsPayload::default(), "POST", "users/login", 200, "02").await;
+  send(&c2, CredentialsPayload::default(), "POST", "users/login", 200, "03").await;
+
+  // any request by a logged in user marks the session seen
+  tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+  let sessions =
+    send_get::<Vec<UserSessionResponse>>(&c, "", "GET", "users/sessions", 200, "10").await;
+  assert_eq!(sessions.len(), 2);
+  assert_eq!(sessions.iter().filter(|s| s.current).count(), 1);
+  let current = sessions.iter().find(|s| s.current).unwrap();
+  assert!(current.last_seen.0 - current.created.0 >= chrono::Duration::try_seconds(1).unwrap());
+
+  // revoke the other client's session
+  let other = sessions.iter().find(|s| !s.current).unwrap();
+  send(&c, "", "DELETE", &format!("users/sessions/{}", other.id), 200, "20").await;
+  send(&c, "", "DELETE", &format!("users/sessions/{}", other.id), 404, "21").await;
+  send(&c2, "", "GET", "users/authenticate", 401, "22").await;
+  send(&c, "", "GET", "users/authenticate", 200, "23").await;
+
+  // log out everywhere
+  send(&c2, CredentialsPayload::default(), "POST", "users/login", 200, "30").await;
+  send(&c2, "", "POST", "users/logout-all", 200, "31").await;
+  send(&c, "", "GET", "users/authenticate", 401, "32").await;
+  send(&c2, "", "GET", "users/authenticate", 401, "33").await;
+}
+
+#[tokio::test]
+#[serial]
+async fn password_rehash() {
+  // a fixed key, so that sessions survive the restart
+  let session_key = "0123456789abcdef".repeat(4);
+  let guard = cargo_shuttle_run_with_secrets(&[("SESSION_KEY", &session_key)], false).await;
+  let c = Client::builder().cookie_store(true).build().unwrap();
+  let c2 = Client::builder().cookie_store(true).build().unwrap();
+  send(&c, CreateUserPayload::default(), "POST", "users", 200, "00").await;
+  send(&c2, CredentialsPayload::default(), "POST", "users/login", 200, "01").await;
+
+  // unknown usernames and incorrect passwords are indistinguishable
+  let unknown = CredentialsPayload::new("nobody", "password", None);
+  let unknown = send(&c, unknown, "POST", "users/login", 401, "02").await.text().await.unwrap();
+  let wrong = CredentialsPayload::new("alice", "wrong_password", None);
+  let wrong = send(&c, wrong, "POST", "users/login", 401, "03").await.text().await.unwrap();
+  assert_eq!(unknown, wrong);
+
+  // restart with more argon2 iterations; alice's session survives until her hash is replaced
+  drop(guard);
+  let secrets = [
+    ("SESSION_KEY", session_key.as_str()),
+    ("ARGON2_M_COST", "19456"),
+    ("ARGON2_T_COST", "3"),
+    ("ARGON2_P_COST", "1"),
+  ];
+  let _child_guard = cargo_shuttle_run_with_secrets(&secrets, true).await;
+  send(&c2, "", "GET", "users/authenticate", 200, "10").await;
+  // logging in replaces the outdated hash, ending the sessions made with it
+  send(&c, CredentialsPayload::default(), "POST", "users/login", 200, "11").await;
+  send(&c2, "", "GET", "users/authenticate", 401, "12").await;
+  send(&c, "", "GET", "users/authenticate", 200, "13").await;
+  // the new hash is current, so logging in again leaves other sessions alone
+  send(&c2, CredentialsPayload::default(), "POST", "users/login", 200, "14").await;
+  send(&c, "", "GET", "users/authenticate", 200, "15").await;
+}
+
+#[tokio::test]
+#[serial]
+async fn login_throttle() {
+  let mut _child_guard = cargo_shuttle_run().await;
+  let c = Client::builder().cookie_store(true).build().unwrap();
+  send(&c, CreateUserPayload::default(), "POST", "users", 200, "00").await;
+  let wrong = CredentialsPayload::new("alice", "wrong_password", None);
+
+  // a few failures are free, then even the right password must wait
+  for tag in ["10", "11", "12"] {
+    send(&c, wrong.clone(), "POST", "users/login", 401, tag).await;
+  }
+  let res = send(&c, CredentialsPayload::default(), "POST", "users/login", 429, "13").await;
+  let retry_after: u64 =
+    res.headers()[reqwest::header::RETRY_AFTER].to_str().unwrap().parse().unwrap();
+  assert!(retry_after > 0);
+  // the username is throttled, whichever ip the client claims to be
+  let spoofed = c.post(format!("{WEBSERVER_URL}/users/login")).header("x-forwarded-for", "1.2.3.4");
+  let res = spoofed.json(&CredentialsPayload::default()).send().await.unwrap();
+  assert_eq!(res.status(), 429, "Test 14 failed");
+
+  // logging in after waiting resets the counters, so the next failures are free again
+  tokio::time::sleep(std::time::Duration::from_secs(retry_after)).await;
+  send(&c, CredentialsPayload::default(), "POST", "users/login", 200, "20").await;
+  send(&c, wrong.clone(), "POST", "users/login", 401, "21").await;
+  send(&c, wrong, "POST", "users/login", 401, "22").await;
+
+  // parallel guesses are counted before the password is checked, so only the free ones get through
+  send(&c, CreateUserPayload::bob(), "POST", "users", 200, "30").await;
+  let guesses: Vec<_> = (0..10)
+    .map(|_| {
+      let request = c.post(format!("{WEBSERVER_URL}/users/login"));
+      let wrong = CredentialsPayload::new("bob", "wrong_password", None);
+      tokio::spawn(async move { request.json(&wrong).send().await.unwrap().status() })
+    })
+    .collect();
+  let mut statuses = Vec::new();
+  for guess in guesses {
+    statuses.push(guess.await.unwrap().as_u16());
+  }
+  assert!(statuses.iter().all(|s| [401, 429].contains(s)), "Test 31 failed: {statuses:?}");
+  assert!(statuses.iter().filter(|s| **s == 401).count() <= 3, "Test 32 failed: {statuses:?}");
+}
+
+#[tokio::test]
+#[serial]
+async fn user_delete() {
+  let mut _child_guard = cargo_shuttle_run().await;
+  let c = Client::builder().cookie_store(true).build().unwrap();
+  send(&c, CreateUserPayload::default(), "POST", "users", 200, "00").await;
+  send(&c, CreateUserPayload::bob(), "POST", "users", 200, "01").await;
+
+  // bob posts an item, alice upvotes it
+  send(&c, CredentialsPayload::bob(), "POST", "users/login", 200, "10").await;
+  let id = send_get::<Ulid>(&c, CreateItemPayload::default(), "POST", "items", 200, "11").await;
+  send(&c, "", "POST", "users/logout", 200, "12").await;
+  send(&c, CredentialsPayload::default(), "POST", "users/login", 200, "13").await;
+  send(&c, VotePayload::new(&id, VoteState::Upvote), "POST", "items/vote", 200, "14").await;
+  send(&c, "", "POST", "users/logout", 200, "15").await;
+
+  // bob deletes his account
+  send(&c, DeleteUserPayload::new("password"), "DELETE_JSON", "users", 401, "20").await;
+  send(&c, CredentialsPayload::bob(), "POST", "users/login", 200, "21").await;
+  send(&c, DeleteUserPayload::new("wrong_password"), "DELETE_JSON", "users", 401, "22").await;
+  send(&c, DeleteUserPayload::new("password"), "DELETE_JSON", "users", 200, "23").await;
+  send(&c, "", "GET", "users/authenticate", 401, "24").await;
+  send(&c, "", "GET", "users/bob", 404, "25").await;
+  send(&c, CredentialsPayload::bob(), "POST", "users/login", 401, "26").await;
+
+  // bob's item remains, with its points, attributed to the placeholder
+  let item =
+    send_get::<GetItemResponse>(&c, "", "GET", &format!("items/{id}?page=1"), 200, "30").await.item;
+  assert_eq!(item.username, "[deleted]".into());
+  assert_eq!(item.points, 2);
+}
+
+#[tokio::test]
+#[serial]
+async fn user_export() {
+  let mut _child_guard = cargo_shuttle_run().await;
+  let c = Client::builder().cookie_store(true).build().unwrap();
+  send(&c, CreateUserPayload::default(), "POST", "users", 200, "00").await;
+  send(&c, "", "GET", "users/export", 401, "01").await;
+
+  send(&c, CredentialsPayload::default(), "POST", "users/login", 200, "10").await;
+  send(&c, CreateItemPayload::default(), "POST", "items", 200, "11").await;
+  let export = send_get::<UserExport>(&c, "", "GET", "users/export", 200, "12").await;
+  assert_eq!(export.user.username, "alice".into());
+  assert_eq!(export.user.password_hash, "[redacted]");
+  assert_eq!(export.items.len(), 1);
+
+  // someone else's export, or one that doesn't exist
+  send(&c, "", "GET", &format!("users/export/{}", Ulid::new()), 404, "20").await;
+}
+
+#[tokio::test]
+#[serial]
+async fn user_export_background() {
+  // export every account in the background
+  let mut _child_guard =
+    cargo_shuttle_run_with_secrets(&[("EXPORT_INLINE_LIMIT", "0")], false).await;
+  let c = Client::builder().cookie_store(true).build().unwrap();
+  send(&c, CreateUserPayload::default(), "POST", "users", 200, "00").await;
+  send(&c, CreateUserPayload::bob(), "POST", "users", 200, "01").await;
+
+  send(&c, CredentialsPayload::default(), "POST", "users/login", 200, "10").await;
+  send(&c, CreateItemPayload::default(), "POST", "items", 200, "11").await;
+  let pending =
+    send_get::<DataExportPendingResponse>(&c, "", "GET", "users/export", 202, "12").await;
+  let res = await_data_export(&c, &pending.download_url, std::time::Duration::from_secs(10)).await;
+  assert_eq!(res.status(), 200);
+  let export = res.json::<UserExport>().await.unwrap();
+  assert_eq!(export.user.username, "alice".into());
+  assert_eq!(export.items.len(), 1);
+
+  // only alice may download it
+  send(&c, CredentialsPayload::bob(), "POST", "users/login", 200, "20").await;
+  send(&c, "", "GET", &pending.download_url[1..], 404, "21").await;
+
+  // expired exports are deleted by the worker, whether or not they were downloaded
+  psql("UPDATE data_exports SET created = NOW() - INTERVAL '8 days'");
+  let start_time = std::time::Instant::now();
+  while psql("SELECT COUNT(*) FROM data_exports") != "0" {
+    assert!(start_time.elapsed().as_secs() < 10, "Test 30 failed: expected exports to be deleted");
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+  }
+}
+
+#[tokio::test]
+#[serial]
+async fn user_notifications() {
+  let mut _child_guard = cargo_shuttle_run().await;
+  let c = Client::builder().cookie_store(true).build().unwrap();
+  send(&c, CreateUserPayload::default(), "POST", "users", 200, "00").await;
+  send(&c, "", "GET", "users/notifications?page=1", 401, "01").await;
+  send(&c, MarkNotificationsReadPayload::default(), "POST", "users/notifications/read", 401, "02")
+    .await;
+
+  send(&c, CredentialsPayload::default(), "POST", "users/login", 200, "10").await;
+  let auth =
+    send_get::<AuthenticateUserResponse>(&c, "", "GET", "users/authenticate", 200, "11").await;
+  assert_eq!(auth.unread_notifications, 0);
+  let notifications =
+    send_get::<NotificationsResponse>(&c, "", "GET", "users/notifications?page=1", 200, "12").await;
+  assert!(notifications.notifications.is_empty());
+  assert_eq!(notifications.unread_count, 0);
+  send(&c, "", "GET", "users/notifications?page=0", 422, "13").await;
+
+  let ids = MarkNotificationsReadPayload::new(Some(vec![Ulid::new()]));
+  send(&c, ids, "POST", "users/notifications/read", 200, "20").await;
+  send(&c, MarkNotificationsReadPayload::default(), "POST", "users/notifications/read", 200, "21")
+    .await;
+
+  // alice mentions bob, a nonexistent user, and herself in an item
+  send(&c, CreateUserPayload::bob(), "POST", "users", 200, "30").await;
+  let text = ItemContent::text("hey @Bob, @nobody_here and @alice: thoughts?".into());
+  let payload = CreateItemPayload::new("mention test", None, text, ItemCategory::Other).unwrap();
+  let item_id = send_get::<Ulid>(&c, payload, "POST", "items", 200, "31").await;
+  // mentions of existing users are linked to their profiles
+  let path = format!("items/{item_id}?page=1");
+  let item = send_get::<GetItemResponse>(&c, "", "GET", &path, 200, "31a").await.item;
+  let text = item.text.unwrap().0;
+  assert!(text.contains(r#"<a href="/users/bob""#), "{text}");
+  assert!(text.contains(">@Bob</a>"), "{text}");
+  assert!(!text.contains("/users/nobody_here"), "{text}");
+  let auth =
+    send_get::<AuthenticateUserResponse>(&c, "", "GET", "users/authenticate", 200, "32").await;
+  assert_eq!(auth.unread_notifications, 0);
+  send(&c, "", "POST", "users/logout", 200, "33").await;
+
+  send(&c, CredentialsPayload::bob(), "POST", "users/login", 200, "40").await;
+  let notifications =
+    send_get::<NotificationsResponse>(&c, "", "GET", "users/notifications?page=1", 200, "41").await;
+  assert_eq!(notifications.unread_count, 1);
+  assert_eq!(notifications.notifications[0].notification_type, NotificationType::Mention);
+  assert_eq!(notifications.notifications[0].comment_by, "alice".into());
+  send(&c, MarkNotificationsReadPayload::default(), "POST", "users/notifications/read", 200, "42")
+    .await;
+  let auth =
+    send_get::<AuthenticateUserResponse>(&c, "", "GET", "users/authenticate", 200, "43").await;
+  assert_eq!(auth.unread_notifications, 0);
+
+  // bob replies to alice's item
+  let payload = CreateCommentPayload::new(&item_id, None, "a reply to alice");
+  let comment_id = send_get::<Ulid>(&c, payload, "POST", "comments", 200, "50").await;
+  let payload = CreateCommentPayload::new(&Ulid::new(), None, "a reply to nothing");
+  send(&c, payload, "POST", "comments", 404, "51").await;
+  let payload = CreateCommentPayload::new(&item_id, Some(&Ulid::new()), "a reply to nothing");
+  send(&c, payload, "POST", "comments", 404, "52").await;
+  send(&c, "", "POST", "users/logout", 200, "53").await;
+  let payload = CreateCommentPayload::new(&item_id, None, "a logged out reply");
+  send(&c, payload, "POST", "comments", 401, "54").await;
+
+  // alice is notified, and replies to bob's comment, and to her own item
+  send(&c, CredentialsPayload::default(), "POST", "users/login", 200, "60").await;
+  let notifications =
+    send_get::<NotificationsResponse>(&c, "", "GET", "users/notifications?page=1", 200, "61").await;
+  assert_eq!(notifications.unread_count, 1);
+  assert_eq!(notifications.notifications[0].notification_type, NotificationType::Reply);
+  assert_eq!(notifications.notifications[0].comment_by, "bob".into());
+  assert_eq!(notifications.notifications[0].comment_id, Some(comment_id.0.clone()));
+  let auth =
+    send_get::<AuthenticateUserResponse>(&c, "", "GET", "users/authenticate", 200, "62").await;
+  assert_eq!(auth.unread_notifications, 1);
+  let payload = CreateCommentPayload::new(&item_id, Some(&comment_id), "a reply to bob");
+  send(&c, payload, "POST", "comments", 200, "63").await;
+  let payload = CreateCommentPayload::new(&item_id, None, "a reply to myself");
+  send(&c, payload, "POST", "comments", 200, "64").await;
+  let auth =
+    send_get::<AuthenticateUserResponse>(&c, "", "GET", "users/authenticate", 200, "65").await;
+  assert_eq!(auth.unread_notifications, 1);
+  send(&c, "", "POST", "users/logout", 200, "66").await;
+
+  // bob is notified of the reply to his comment
+  send(&c, CredentialsPayload::bob(), "POST", "users/login", 200, "70").await;
+  let notifications =
+    send_get::<NotificationsResponse>(&c, "", "GET", "users/notifications?page=1", 200, "71").await;
+  assert_eq!(notifications.unread_count, 1);
+  assert_eq!(notifications.notifications[0].notification_type, NotificationType::Reply);
+  assert_eq!(notifications.notifications[0].comment_by, "alice".into());
+  assert_eq!(notifications.notifications[0].parent_comment_id, Some(comment_id.0));
+}
+
+#[tokio::test]
+#[serial]
+async fn user_invites() {
+  let mut _child_guard = cargo_shuttle_run().await;
+  let c = Client::builder().cookie_store(true).build().unwrap();
+  send(&c, CreateUserPayload::default(), "POST", "users", 200, "00").await;
+  send(&c, "", "POST", "users/invites", 401, "01").await;
+
+  // registration is open in dev, but a given invite code must still be valid
+  send(&c, CreateUserPayload::bob().with_invite_code("not_a_code"), "POST", "users", 403, "02")
+    .await;
+  send(&c, "", "GET", "users/bob", 404, "03").await;
+
+  // new users lack the karma to invite, and only moderators may audit invite trees
+  send(&c, CredentialsPayload::default(), "POST", "users/login", 200, "10").await;
+  send(&c, "", "POST", "users/invites", 403, "11").await;
+  let codes = send_get::<Vec<InviteCodeResponse>>(&c, "", "GET", "users/invites", 200, "12").await;
+  assert!(codes.is_empty());
+  send(&c, "", "GET", "users/invites/tree/alice", 403, "13").await;
+}
+
+#[tokio::test]
+#[serial]
+async fn concurrent_invites() {
+  // anyone may mint invites
+  let mut _child_guard =
+    cargo_shuttle_run_with_secrets(&[("KARMA_TO_MINT_INVITES", "0")], false).await;
+  let c = Client::builder().cookie_store(true).build().unwrap();
+  send(&c, CreateUserPayload::default(), "POST", "users", 200, "00").await;
+  send(&c, CredentialsPayload::default(), "POST", "users/login", 200, "01").await;
+
+  // a burst of mints may not take alice past the limit on unused codes
+  let mints: Vec<_> = (0..MAX_UNUSED_INVITE_CODES * 2)
+    .map(|_| {
+      let request = c.post(format!("{WEBSERVER_URL}/users/invites"));
+      tokio::spawn(async move { request.send().await.unwrap().status().as_u16() })
+    })
+    .collect();
+  let mut statuses = Vec::new();
+  for mint in mints {
+    statuses.push(mint.await.unwrap());
+  }
+  assert!(statuses.iter().all(|s| [200, 403].contains(s)), "Test 10 failed: {statuses:?}");
+  let codes = send_get::<Vec<InviteCodeResponse>>(&c, "", "GET", "users/invites", 200, "11").await;
+  assert_eq!(codes.len() as i64, MAX_UNUSED_INVITE_CODES);
+  send(&c, "", "POST", "users/invites", 403, "12").await;
+}
+
+#[tokio::test]
+#[serial]
+async fn proof_of_work() {
+  let mut _child_guard = cargo_shuttle_run().await;
+  let c = Client::builder().cookie_store(true).build().unwrap();
+  // submission challenges are bound to an account
+  send(&c, "", "GET", "pow/challenge?action=item", 401, "00").await;
+  send(&c, "", "GET", "pow/challenge?action=nonsense", 400, "01").await;
+
+  // proof of work is optional in dev, but a given solution must be valid
+  let challenge =
+    send_get::<PowChallengeResponse>(&c, "", "GET", "pow/challenge?action=signup", 200, "10").await;
+  let solution = solve_pow_challenge(&challenge);
+  send(&c, CreateUserPayload::default().with_pow(solution.clone()), "POST", "users", 200, "11")
+    .await;
+  // each challenge may be used once
+  send(&c, CreateUserPayload::bob().with_pow(solution), "POST", "users", 403, "12").await;
+
+  // a signup challenge doesn't solve an item submission
+  send(&c, CredentialsPayload::default(), "POST", "users/login", 200, "20").await;
+  let challenge =
+    send_get::<PowChallengeResponse>(&c, "", "GET", "pow/challenge?action=signup", 200, "21").await;
+  let payload = CreateItemPayload::default().with_pow(solve_pow_challenge(&challenge));
+  send(&c, payload, "POST", "items", 403, "22").await;
+  let challenge =
+    send_get::<PowChallengeResponse>(&c, "", "GET", "pow/challenge?action=item", 200, "23").await;
+  let payload = CreateItemPayload::default().with_pow(solve_pow_challenge(&challenge));
+  let item_id = send_get::<Ulid>(&c, payload, "POST", "items", 200, "24").await;
+
+  // nor does an item challenge solve a comment
+  let challenge =
+    send_get::<PowChallengeResponse>(&c, "", "GET", "pow/challenge?action=item", 200, "30").await;
+  let payload = CreateCommentPayload::new(&item_id, None, "a worked comment")
+    .with_pow(solve_pow_challenge(&challenge));
+  send(&c, payload, "POST", "comments", 403, "31").await;
+  let challenge =
+    send_get::<PowChallengeResponse>(&c, "", "GET", "pow/challenge?action=comment", 200, "32")
+      .await;
+  let payload = CreateCommentPayload::new(&item_id, None, "a worked comment")
+    .with_pow(solve_pow_challenge(&challenge));
+  send(&c, payload.clone(), "POST", "comments", 200, "33").await;
+  send(&c, payload, "POST", "comments", 403, "34").await;
+}
+
+#[tokio::test]
+#[serial]
+async fn siwe_login() {
+  let mut _child_guard = cargo_shuttle_run().await;
+  let c = Client::builder().cookie_store(true).build().unwrap();
+  send(&c, CreateUserPayload::default(), "POST", "users", 200, "00").await;
+  let key = SigningKey::from_slice(&[7u8; 32]).unwrap();
+  let message = |domain: &str, nonce: &str| {
+    SiweMessage {
+      domain:          domain.into(),
+      address:         siwe_address(key.verifying_key()),
+      statement:       Some("Sign in to zkhn".into()),
+      uri:             "http://localhost:8000".into(),
+      version:         "1".into(),
+      chain_id:        1,
+      nonce:           nonce.into(),
+      issued_at:       chrono::Utc::now(),
+      expiration_time: Some(chrono::Utc::now() + chrono::Duration::minutes(5)),
+      not_before:      None,
+      request_id:      None,
+      resources:       vec![],
+    }
+    .to_string()
+  };
+  let signed = |message: &str| SiweVerifyPayload::new(message, &sign_siwe_message(&key, message));
+
+  // an unlinked wallet can't log in
+  let nonce = send_get::<SiweNonceResponse>(&c, "", "GET", "auth/siwe/nonce", 200, "01").await;
+  let payload = signed(&message("localhost:8000", &nonce.nonce));
+  send(&c, payload, "POST", "auth/siwe/verify", 401, "02").await;
+
+  // alice links the wallet; the signed message can't be replayed
+  send(&c, CredentialsPayload::default(), "POST", "users/login", 200, "10").await;
+  let nonce = send_get::<SiweNonceResponse>(&c, "", "GET", "auth/siwe/nonce", 200, "11").await;
+  let payload = signed(&message("localhost:8000", &nonce.nonce));
+  send(&c, payload.clone(), "POST", "auth/siwe/verify", 200, "12").await;
+  send(&c, payload, "POST", "auth/siwe/verify", 401, "13").await;
+  send(&c, "", "POST", "users/logout", 200, "14").await;
+
+  // messages for another domain, or signed by another key, are rejected
+  let nonce = send_get::<SiweNonceResponse>(&c, "", "GET", "auth/siwe/nonce", 200, "20").await;
+  let payload = signed(&message("evil.example.com", &nonce.nonce));
+  send(&c, payload, "POST", "auth/siwe/verify", 401, "21").await;
+  let nonce = send_get::<SiweNonceResponse>(&c, "", "GET", "auth/siwe/nonce", 200, "22").await;
+  let msg = message("localhost:8000", &nonce.nonce);
+  let other_key = SigningKey::from_slice(&[8u8; 32]).unwrap();
+  let payload = SiweVerifyPayload::new(&msg, &sign_siwe_message(&other_key, &msg));
+  send(&c, payload, "POST", "auth/siwe/verify", 401, "23").await;
+
+  // the wallet logs in to alice's account
+  let nonce = send_get::<SiweNonceResponse>(&c, "", "GET", "auth/siwe/nonce", 200, "30").await;
+  let payload = signed(&message("localhost:8000", &nonce.nonce));
+  send(&c, payload, "POST", "auth/siwe/verify", 200, "31").await;
+  let auth =
+    send_get::<AuthenticateUserResponse>(&c, "", "GET", "users/authenticate", 200, "32").await;
+  assert_eq!(auth.username.0, "alice");
+}
+
+#[tokio::test]
+#[serial]
+async fn anonymous_items() {
+  let mut _child_guard = cargo_shuttle_run().await;
+  let c = Client::builder().cookie_store(true).build().unwrap();
+  send(&c, CreateUserPayload::default(), "POST", "users", 200, "00").await;
+  let key = send_get::<AnonKeyResponse>(&c, "", "GET", "anon/key", 200, "01").await;
+  let request = AnonTokenRequest::new(&key.public_key).unwrap();
+  let payload = IssueAnonTokensPayload::new(vec![request.blinded_message.clone()]);
+  send(&c, payload.clone(), "POST", "anon/tokens", 401, "02").await;
+
+  // new users lack the karma to be issued tokens
+  send(&c, CredentialsPayload::default(), "POST", "users/login", 200, "10").await;
+  send(&c, payload, "POST", "anon/tokens", 403, "11").await;
+  let too_many = IssueAnonTokensPayload::new(vec![request.blinded_message; 4]);
+  send(&c, too_many, "POST", "anon/tokens", 422, "12").await;
+
+  // tokens we didn't sign are rejected
+  let forged = AnonToken {
+    message:    "00".repeat(32),
+    randomizer: "00".repeat(32),
+    signature:  "00".repeat(256),
+  };
+  let payload = CreateAnonymousItemPayload::new(CreateItemPayload::default(), forged);
+  send(&c, payload, "POST", "items/anonymous", 401, "20").await;
+}
+
+#[tokio::test]
+#[serial]
+async fn anonymous_comments() {
+  // anyone may be issued tokens
+  let mut _child_guard =
+    cargo_shuttle_run_with_secrets(&[("KARMA_TO_ANONYMOUS_POSTING", "0")], false).await;
+  let c = Client::builder().cookie_store(true).build().unwrap();
+  send(&c, CreateUserPayload::default(), "POST", "users", 200, "00").await;
+  send(&c, CredentialsPayload::default(), "POST", "users/login", 200, "01").await;
+  let item_id =
+    send_get::<Ulid>(&c, CreateItemPayload::default(), "POST", "items", 200, "02").await;
+
+  // alice is issued a token, and spends it on an anonymous reply to her own item
+  let key = send_get::<AnonKeyResponse>(&c, "", "GET", "anon/key", 200, "10").await;
+  let request = AnonTokenRequest::new(&key.public_key).unwrap();
+  let payload = IssueAnonTokensPayload::new(vec![request.blinded_message.clone()]);
+  let issued = send_get::<AnonTokensResponse>(&c, payload, "POST", "anon/tokens", 200, "11").await;
+  let token = request.finalize(&issued.blind_signatures[0]).unwrap();
+  send(&c, "", "POST", "users/logout", 200, "12").await;
+  let comment = CreateCommentPayload::new(&item_id, None, "an anonymous reply");
+  let payload = CreateAnonymousCommentPayload::new(comment, token);
+  send_get::<Ulid>(&c, payload.clone(), "POST", "comments/anonymous", 200, "13").await;
+  // each token may be spent once
+  send(&c, payload, "POST", "comments/anonymous", 409, "14").await;
+
+  // the reply is anonymous, and earns no one karma
+  send(&c, CredentialsPayload::default(), "POST", "users/login", 200, "20").await;
+  let notifications =
+    send_get::<NotificationsResponse>(&c, "", "GET", "users/notifications?page=1", 200, "21").await;
+  assert_eq!(notifications.unread_count, 1);
+  assert_eq!(notifications.notifications[0].comment_by, db::ANONYMOUS_USERNAME.into());
+  let alice = send_get::<GetUserResponse>(&c, "", "GET", "users/alice", 200, "22").await;
+  assert_eq!(alice.karma, 1);
+  let path = "users/%5Banonymous%5D";
+  let anonymous = send_get::<GetUserResponse>(&c, "", "GET", path, 200, "23").await;
+  assert_eq!(anonymous.karma, 0);
+}
+
+#[tokio::test]
+#[serial]
+async fn comment_revisions() {
+  let mut _child_guard = cargo_shuttle_run().await;
+  let c = Client::builder().cookie_store(true).build().unwrap();
+  send(&c, CreateUserPayload::default(), "POST", "users", 200, "00").await;
+  send(&c, CreateUserPayload::bob(), "POST", "users", 200, "01").await;
+  send(&c, CredentialsPayload::default(), "POST", "users/login", 200, "02").await;
+  let item_id =
+    send_get::<Ulid>(&c, CreateItemPayload::default(), "POST", "items", 200, "03").await;
+  let payload = CreateCommentPayload::new(&item_id, None, "a comment to edit");
+  let id = send_get::<Ulid>(&c, payload, "POST", "comments", 200, "04").await;
+
+  // alice edits her comment, which is sanitized
+  let edit = EditCommentPayload::new(&id, "short");
+  send(&c, edit, "PUT", "comments/edit-comment", 422, "10").await;
+  let edit = EditCommentPayload::new(&Ulid::new(), "a comment to nowhere");
+  send(&c, edit, "PUT", "comments/edit-comment", 404, "11").await;
+  let edit = EditCommentPayload::new(&id, "<b>an edited comment</b> for @bob");
+  send(&c, edit, "PUT", "comments/edit-comment", 200, "12").await;
+
+  // the edit is recorded, along with the original
+  let path = format!("comments/{id}/revisions");
+  let revisions = send_get::<RevisionsResponse>(&c, "", "GET", &path, 200, "20").await.revisions;
+  assert_eq!(revisions.iter().map(|r| r.revision).collect::<Vec<_>>(), vec![1, 2]);
+  assert_eq!(revisions[0].text.as_deref(), Some("a comment to edit"));
+  let text = revisions[1].text.clone().unwrap();
+  assert!(!text.contains("<b>"), "{text}");
+  assert!(text.contains(r#"<a href="/users/bob""#), "{text}");
+  assert!(revisions.iter().all(|r| r.title.is_none() && r.item_category.is_none()));
+  let path = format!("comments/{id}/revisions/diff");
+  let diff = send_get::<RevisionDiffResponse>(&c, "", "GET", &path, 200, "21").await;
+  assert_eq!((diff.from, diff.to), (1, 2));
+  assert!(diff.diff.contains("-a comment to edit"), "{}", diff.diff);
+  assert!(diff.diff.contains("+an edited comment"), "{}", diff.diff);
+  send(&c, "", "GET", &format!("comments/{id}/revisions/diff?from=2&to=1"), 400, "22").await;
+  send(&c, "", "GET", &format!("comments/{id}/revisions/diff?to=3"), 404, "23").await;
+  send(&c, "", "GET", &format!("comments/{}/revisions", Ulid::new()), 404, "24").await;
+  send(&c, "", "POST", "users/logout", 200, "25").await;
+  send(&c, "", "GET", &format!("comments/{id}/revisions"), 401, "26").await;
+
+  // only the author may edit, or see the revisions
+  send(&c, CredentialsPayload::bob(), "POST", "users/login", 200, "30").await;
+  let edit = EditCommentPayload::new(&id, "bob's edit of alice");
+  send(&c, edit, "PUT", "comments/edit-comment", 403, "31").await;
+  send(&c, "", "GET", &format!("comments/{id}/revisions"), 403, "32").await;
+  send(&c, "", "GET", &format!("comments/{id}/revisions/diff"), 403, "33").await;
+
+  // a comment with replies may no longer be edited
+  let payload = CreateCommentPayload::new(&item_id, Some(&id), "a reply to alice");
+  send(&c, payload, "POST", "comments", 200, "40").await;
+  send(&c, "", "POST", "users/logout", 200, "41").await;
+  send(&c, CredentialsPayload::default(), "POST", "users/login", 200, "42").await;
+  let edit = EditCommentPayload::new(&id, "a second edited comment");
+  send(&c, edit, "PUT", "comments/edit-comment", 403, "43").await;
+}
+
+#[tokio::test]
+#[serial]
+async fn webhooks() {
+  let mut _child_guard = cargo_shuttle_run().await;
+  let received = webhook_receiver(8001).await;
+  let c = Client::builder().cookie_store(true).build().unwrap();
+  send(&c, CreateUserPayload::default(), "POST", "users", 200, "00").await;
+  send(&c, CreateUserPayload::bob(), "POST", "users", 200, "01").await;
+  let events = [
+    WebhookEvent::ItemCreated,
+    WebhookEvent::ItemDeleted,
+    WebhookEvent::ItemVoted,
+    WebhookEvent::CommentCreated,
+  ];
+  let payload = CreateWebhookPayload::new("http://localhost:8001/", &events);
+  send(&c, payload.clone(), "POST", "webhooks", 401, "02").await;
+
+  // alice registers a webhook
+  send(&c, CredentialsPayload::default(), "POST", "users/login", 200, "10").await;
+  send(&c, CreateWebhookPayload::new("http://localhost:8001/", &[]), "POST", "webhooks", 422, "11")
+    .await;
+  let webhook = send_get::<WebhookResponse>(&c, payload, "POST", "webhooks", 200, "12").await;
+  let secret = webhook.secret.expect("secret returned on creation");
+  let webhooks = send_get::<Vec<WebhookResponse>>(&c, "", "GET", "webhooks", 200, "13").await;
+  assert_eq!(webhooks.len(), 1);
+  assert!(webhooks[0].secret.is_none());
+
+  // creating an item delivers a signed payload
+  let item_id =
+    send_get::<Ulid>(&c, CreateItemPayload::default(), "POST", "items", 200, "20").await;
+  await_webhooks(&received, 1, std::time::Duration::from_secs(10)).await;
+  let (headers, body) = received.lock().unwrap()[0].clone();
+  assert_eq!(headers[EVENT_HEADER], "item.created");
+  assert_eq!(headers[SIGNATURE_HEADER], sign_webhook_payload(&secret, &body).as_str());
+  let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+  assert_eq!(body["data"]["id"], item_id.0);
+
+  let path = format!("webhooks/{}/deliveries?page=1", webhook.id);
+  let deliveries = send_get::<Vec<WebhookDeliveryResponse>>(&c, "", "GET", &path, 200, "21").await;
+  assert_eq!(deliveries.len(), 1);
+  assert_eq!(deliveries[0].status, WebhookDeliveryStatus::Delivered);
+  send(&c, "", "POST", "users/logout", 200, "22").await;
+
+  // bob can't see or delete alice's webhook
+  send(&c, CredentialsPayload::bob(), "POST", "users/login", 200, "30").await;
+  send(&c, "", "GET", &path, 404, "31").await;
+  send(&c, "", "DELETE", &format!("webhooks/{}", webhook.id), 404, "32").await;
+
+  // bob votes on and comments on alice's item; the vote is delivered without its voter
+  send(&c, VotePayload::new(&item_id, VoteState::Upvote), "POST", "items/vote", 200, "33").await;
+  let payload = CreateCommentPayload::new(&item_id, None, "a comment for @alice");
+  let comment_id = send_get::<Ulid>(&c, payload, "POST", "comments", 200, "34").await;
+  await_webhooks(&received, 3, std::time::Duration::from_secs(10)).await;
+  let bodies: Vec<(String, serde_json::Value)> = received.lock().unwrap()[1..]
+    .iter()
+    .map(|(headers, body)| {
+      let event = headers[EVENT_HEADER].to_str().unwrap().to_string();
+      (event, serde_json::from_str(body).unwrap())
+    })
+    .collect();
+  let (_, vote) = bodies.iter().find(|(event, _)| event == "item.voted").unwrap();
+  assert_eq!(vote["data"]["itemId"], item_id.0);
+  assert!(vote["data"].get("username").is_none());
+  let (_, comment) = bodies.iter().find(|(event, _)| event == "comment.created").unwrap();
+  assert_eq!(comment["data"]["id"], comment_id.0);
+  // top-level comments are sanitized too
+  let text = comment["data"]["comment_text"].as_str().unwrap();
+  assert!(text.contains(r#"<a href="/users/alice""#), "{text}");
+  send(&c, "", "POST", "users/logout", 200, "35").await;
+
+  // deleting an item sends only its id
+  send(&c, CredentialsPayload::default(), "POST", "users/login", 200, "40").await;
+  let item_id =
+    send_get::<Ulid>(&c, CreateItemPayload::default(), "POST", "items", 200, "41").await;
+  send(&c, "", "DELETE", &format!("items/delete-item/{item_id}"), 200, "42").await;
+  await_webhooks(&received, 5, std::time::Duration::from_secs(10)).await;
+  let deleted = received
+    .lock()
+    .unwrap()
+    .iter()
+    .find(|(headers, _)| headers[EVENT_HEADER] == "item.deleted")
+    .map(|(_, body)| serde_json::from_str::<serde_json::Value>(body).unwrap())
+    .unwrap();
+  assert_eq!(deleted["data"], serde_json::json!({ "itemId": item_id.0 }));
+
+  send(&c, "", "DELETE", &format!("webhooks/{}", webhook.id), 200, "50").await;
+  send(&c, "", "GET", &path, 404, "51").await;
+}
+
+#[tokio::test]
+#[serial]
+async fn karma_reconciliation() {
+  let mut _child_guard = cargo_shuttle_run().await;
+  let c = Client::builder().cookie_store(true).build().unwrap();
+  send(&c, CreateUserPayload::default(), "POST", "users", 200, "00").await;
+  send(&c, CreateUserPayload::bob(), "POST", "users", 200, "01").await;
+  send(&c, "", "POST", "admin/karma/reconcile", 401, "02").await;
+
+  // bob upvotes alice's item, then alice deletes it, taking its points back
+  send(&c, CredentialsPayload::default(), "POST", "users/login", 200, "10").await;
+  let id = send_get::<Ulid>(&c, CreateItemPayload::default(), "POST", "items", 200, "11").await;
+  let bob_creds = CredentialsPayload::new("bob", "password", None);
+  send(&c, bob_creds, "POST", "users/login", 200, "12").await;
+  send(&c, VotePayload::new(&id, VoteState::Upvote), "POST", "items/vote", 200, "13").await;
+  let (points, karma) = get_points_karma(&c, &id).await;
+  assert_eq!((points, karma), (2, 2));
+  send(&c, CredentialsPayload::default(), "POST", "users/login", 200, "14").await;
+  send(&c, "", "DELETE", &format!("items/delete-item/{id}"), 200, "15").await;
+  let karma = send_get::<GetUserResponse>(&c, "", "GET", "users/alice", 200, "16").await.karma;
+  assert_eq!(karma, 0);
+
+  // the audit endpoints are moderator only
+  send(&c, "", "POST", "admin/karma/reconcile?correct=true", 403, "20").await;
+  send(&c, "", "GET", "admin/karma/reconciliations?page=1", 403, "21").await;
+  send(&c, "", "GET", &format!("admin/karma/reconciliations/{}", Ulid::new()), 403, "22").await;
+}
+
+#[tokio::test]
+#[serial]
+async fn karma_floor_reconciliation() {
+  let mut _child_guard = cargo_shuttle_run().await;
+  let c = Client::builder().cookie_store(true).build().unwrap();
+  let carol = CreateUserPayload::new("carol", "password", None, None).unwrap();
+  let carol_creds = CredentialsPayload::new("carol", "password", None);
+  send(&c, CreateUserPayload::default(), "POST", "users", 200, "00").await;
+  send(&c, CreateUserPayload::bob(), "POST", "users", 200, "01").await;
+  send(&c, carol, "POST", "users", 200, "02").await;
+  // moderators may downvote without the karma to
+  make_moderator("bob");
+  make_moderator("carol");
+
+  // alice's karma hits zero, so carol's downvote takes nothing
+  send(&c, CredentialsPayload::default(), "POST", "users/login", 200, "10").await;
+  let id = send_get::<Ulid>(&c, CreateItemPayload::default(), "POST", "items", 200, "11").await;
+  send(&c, CredentialsPayload::bob(), "POST", "users/login", 200, "12").await;
+  send(&c, VotePayload::new(&id, VoteState::Downvote), "POST", "items/vote", 200, "13").await;
+  send(&c, carol_creds, "POST", "users/login", 200, "14").await;
+  send(&c, VotePayload::new(&id, VoteState::Downvote), "POST", "items/vote", 200, "15").await;
+  let (points, karma) = get_points_karma(&c, &id).await;
+  assert_eq!((points, karma), (-1, 0));
+
+  // but her change of heart gives the full two
+  send(&c, VotePayload::new(&id, VoteState::Upvote), "POST", "items/vote", 200, "20").await;
+  let (points, karma) = get_points_karma(&c, &id).await;
+  assert_eq!((points, karma), (1, 2));
+
+  // which reconciliation doesn't mistake for drift
+  let reconciliation =
+    send_get::<KarmaReconciliationResponse>(&c, "", "POST", "admin/karma/reconcile", 200, "30")
+      .await;
+  assert_eq!(reconciliation.discrepancy_count, 0);
+}
+
+#[tokio::test]
+#[serial]
+async fn restore_deleted() {
+  let mut _child_guard = cargo_shuttle_run().await;
+  let c = Client::builder().cookie_store(true).build().unwrap();
+  let carol = CreateUserPayload::new("carol", "password", None, None).unwrap();
+  let carol_creds = CredentialsPayload::new("carol", "password", None);
+  send(&c, CreateUserPayload::default(), "POST", "users", 200, "00").await;
+  send(&c, CreateUserPayload::bob(), "POST", "users", 200, "01").await;
+  send(&c, carol, "POST", "users", 200, "02").await;
+  // moderators may downvote without the karma to
+  make_moderator("bob");
+  make_moderator("carol");
+
+  // alice's first item is upvoted twice, her second downvoted twice
+  send(&c, CredentialsPayload::default(), "POST", "users/login", 200, "10").await;
+  let id = send_get::<Ulid>(&c, CreateItemPayload::default(), "POST", "items", 200, "11").await;
+  let other_id =
+    send_get::<Ulid>(&c, CreateItemPayload::default(), "POST", "items", 200, "12").await;
+  send(&c, CredentialsPayload::bob(), "POST", "users/login", 200, "13").await;
+  send(&c, VotePayload::new(&id, VoteState::Upvote), "POST", "items/vote", 200, "14").await;
+  send(&c, VotePayload::new(&other_id, VoteState::Downvote), "POST", "items/vote", 200, "15").await;
+  let payload = CreateCommentPayload::new(&other_id, None, "a comment to delete");
+  let comment_id = send_get::<Ulid>(&c, payload, "POST", "comments", 200, "16").await;
+  send(&c, carol_creds.clone(), "POST", "users/login", 200, "17").await;
+  send(&c, VotePayload::new(&id, VoteState::Upvote), "POST", "items/vote", 200, "18").await;
+  send(&c, VotePayload::new(&other_id, VoteState::Downvote), "POST", "items/vote", 200, "19").await;
+  assert_eq!(get_points_karma(&c, &id).await, (3, 2));
+
+  // deleting the first item can only take the two karma she has
+  send(&c, CredentialsPayload::default(), "POST", "users/login", 200, "20").await;
+  send(&c, "", "DELETE", &format!("items/delete-item/{id}"), 200, "21").await;
+  assert_eq!(get_points_karma(&c, &id).await, (3, 0));
+  let path = "items/get-items-by-page/ranked?page=1";
+  let page = send_get::<GetItemsPageResponse>(&c, "", "GET", path, 200, "22").await;
+  assert_eq!(page.count, 1);
+  assert!(page.items.iter().all(|ranked| ranked.item.id != id));
+
+  // so restoring it gives back two, not three
+  send(&c, CredentialsPayload::bob(), "POST", "users/login", 200, "30").await;
+  let deleted = send_get::<Vec<Item>>(&c, "", "GET", "admin/items/deleted?page=1", 200, "31").await;
+  assert_eq!(deleted.iter().map(|i| &i.id).collect::<Vec<_>>(), [&id]);
+  let item =
+    send_get::<Item>(&c, "", "POST", &format!("admin/items/{id}/restore"), 200, "32").await;
+  assert!(item.deleted_at.is_none());
+  send(&c, "", "POST", &format!("admin/items/{id}/restore"), 404, "33").await;
+  assert_eq!(get_points_karma(&c, &id).await, (3, 2));
+  let deleted = send_get::<Vec<Item>>(&c, "", "GET", "admin/items/deleted?page=1", 200, "34").await;
+  assert!(deleted.is_empty());
+
+  // bob deletes his comment, taking its point from his karma
+  assert_eq!(get_karma(&c, "bob").await, 1);
+  send(&c, carol_creds, "POST", "users/login", 200, "40").await;
+  send(&c, "", "DELETE", &format!("comments/delete-comment/{comment_id}"), 403, "41").await;
+  send(&c, CredentialsPayload::bob(), "POST", "users/login", 200, "42").await;
+  send(&c, "", "DELETE", &format!("comments/delete-comment/{comment_id}"), 200, "43").await;
+  send(&c, "", "DELETE", &format!("comments/delete-comment/{comment_id}"), 403, "44").await;
+  send(&c, "", "DELETE", &format!("comments/delete-comment/{}", Ulid::new()), 404, "45").await;
+  assert_eq!(get_karma(&c, "bob").await, 0);
+  let payload = CreateCommentPayload::new(&other_id, Some(&comment_id), "a reply to nothing");
+  send(&c, payload, "POST", "comments", 404, "46").await;
+
+  // and a moderator restores it
+  let path = "admin/comments/deleted?page=1";
+  let deleted = send_get::<Vec<DeletedCommentResponse>>(&c, "", "GET", path, 200, "50").await;
+  assert_eq!(deleted.len(), 1);
+  assert_eq!(deleted[0].id, comment_id);
+  assert_eq!(deleted[0].comment_text.0, "a comment to delete");
+  send(&c, "", "POST", &format!("admin/comments/{comment_id}/restore"), 200, "51").await;
+  send(&c, "", "POST", &format!("admin/comments/{comment_id}/restore"), 404, "52").await;
+  assert_eq!(get_karma(&c, "bob").await, 1);
+
+  let reconciliation =
+    send_get::<KarmaReconciliationResponse>(&c, "", "POST", "admin/karma/reconcile", 200, "60")
+      .await;
+  assert_eq!(reconciliation.discrepancy_count, 0);
+}
+
+#[tokio::test]
+#[serial]
+async fn foreign_keys() {
+  let mut _child_guard = cargo_shuttle_run().await;
+  let c = Client::builder().cookie_store(true).build().unwrap();
+  send(&c, CreateUserPayload::default(), "POST", "users", 200, "00").await;
+  send(&c, CreateUserPayload::bob(), "POST", "users", 200, "01").await;
+  send(&c, CredentialsPayload::default(), "POST", "users/login", 200, "02").await;
+  let id = send_get::<Ulid>(&c, CreateItemPayload::default(), "POST", "items", 200, "03").await;
+  send(&c, CredentialsPayload::bob(), "POST", "users/login", 200, "04").await;
+  let payload = CreateCommentPayload::new(&id, None, "a reply to alice");
+  let comment_id = send_get::<Ulid>(&c, payload, "POST", "comments", 200, "05").await;
+  send(&c, VotePayload::new(&id, VoteState::Upvote), "POST", "items/vote", 200, "06").await;
+
+  // renaming a user carries over to everything naming them
+  psql("UPDATE users SET username = 'robert' WHERE username = 'bob'");
+  let count =
+    format!("SELECT COUNT(*) FROM comments WHERE id = '{comment_id}' AND username = 'robert'");
+  assert_eq!(psql(&count), "1");
+  let count = "SELECT COUNT(*) FROM user_votes WHERE username = 'robert'";
+  assert_eq!(psql(count), "1");
+
+  // removing an item removes its comments, votes and notifications with it
+  psql(&format!("DELETE FROM items WHERE id = '{id}'"));
+  assert_eq!(psql(&format!("SELECT COUNT(*) FROM comments WHERE id = '{comment_id}'")), "0");
+  assert_eq!(psql(&format!("SELECT COUNT(*) FROM user_votes WHERE content_id = '{id}'")), "0");
+  assert_eq!(psql(&format!("SELECT COUNT(*) FROM notifications WHERE item_id = '{id}'")), "0");
+  send(&c, "", "GET", &format!("items/{id}"), 404, "10").await;
+
+  // without the placeholder to take her content, alice's account can't be deleted
+  send(&c, CredentialsPayload::default(), "POST", "users/login", 200, "20").await;
+  send(&c, CreateItemPayload::default(), "POST", "items", 200, "21").await;
+  psql("DELETE FROM users WHERE username = '[deleted]'");
+  send(&c, DeleteUserPayload::new("password"), "DELETE_JSON", "users", 409, "22").await;
+  send(&c, "", "GET", "users/alice", 200, "23").await;
+}
+
+#[tokio::test]
+#[serial]
+async fn karma_history() {
+  let mut _child_guard = cargo_shuttle_run().await;
+  let c = Client::builder().cookie_store(true).build().unwrap();
+  send(&c, CreateUserPayload::default(), "POST", "users", 200, "00").await;
+  send(&c, CredentialsPayload::default(), "POST", "users/login", 200, "01").await;
+  send(&c, CreateItemPayload::default(), "POST", "items", 200, "02").await;
+
+  // every bucket is included, ending with today's
+  let history =
+    send_get::<KarmaHistoryResponse>(&c, "", "GET", "users/alice/karma-history", 200, "10").await;
+  assert_eq!(history.points.len(), 31);
+  let today = history.points.last().unwrap();
+  assert_eq!((today.delta, today.karma), (1, 1));
+  assert!(history.points[..30].iter().all(|p| p.karma == 0));
+  let path = "users/alice/karma-history?bucket=week";
+  let history = send_get::<KarmaHistoryResponse>(&c, "", "GET", path, 200, "11").await;
+  assert_eq!(history.points.last().unwrap().karma, 1);
+
+  let path = "users/alice/karma-history?from=2024-02-01T00:00:00Z&to=2024-01-01T00:00:00Z";
+  send(&c, "", "GET", path, 400, "20").await;
+  let path = "users/alice/karma-history?from=2000-01-01T00:00:00Z&to=2024-01-01T00:00:00Z";
+  send(&c, "", "GET", path, 400, "21").await;
+  // the default span would begin before the earliest representable time
+  let path = "users/alice/karma-history?to=-262144-01-02T00:00:00Z";
+  send(&c, "", "GET", path, 400, "22").await;
+  send(&c, "", "GET", "users/bob/karma-history", 404, "23").await;
+}
+
+#[tokio::test]
+#[serial]
+async fn leaders() {
+  let mut _child_guard = cargo_shuttle_run().await;
+  let c = Client::builder().cookie_store(true).build().unwrap();
+  send(&c, CreateUserPayload::default(), "POST", "users", 200, "00").await;
+  send(&c, CreateUserPayload::bob(), "POST", "users", 200, "01").await;
+  send(&c, CredentialsPayload::default(), "POST", "users/login", 200, "02").await;
+  send(&c, CreateItemPayload::default(), "POST", "items", 200, "03").await;
+
+  let board = send_get::<LeadersResponse>(&c, "", "GET", "leaders", 200, "10").await;
+  assert_eq!(board.board, LeaderBoard::Karma);
+  let ranked: Vec<_> =
+    board.leaders.iter().map(|l| (l.rank, l.username.0.as_str(), l.score)).collect();
+  assert_eq!(ranked, [(1, "alice", 1), (2, "bob", 0)]);
+
+  let path = "leaders?board=karma-gained";
+  let board = send_get::<LeadersResponse>(&c, "", "GET", path, 200, "11").await;
+  assert_eq!(board.leaders.len(), 1);
+  // alice's item hasn't earned enough points to count
+  let path = "leaders?board=submissions";
+  let board = send_get::<LeadersResponse>(&c, "", "GET", path, 200, "12").await;
+  assert!(board.leaders.is_empty());
+  send(&c, "", "GET", "leaders?board=nonsense", 400, "13").await;
+}
+
+#[tokio::test]
+#[serial]
+async fn vote_flags() {
+  let mut _child_guard = cargo_shuttle_run().await;
+  let c = Client::builder().cookie_store(true).build().unwrap();
+  send(&c, CreateUserPayload::default(), "POST", "users", 200, "00").await;
+  send(&c, "", "POST", "admin/vote-flags/analyze", 401, "01").await;
+
+  // the review queue is moderator only
+  send(&c, CredentialsPayload::default(), "POST", "users/login", 200, "10").await;
+  send(&c, "", "POST", "admin/vote-flags/analyze", 403, "11").await;
+  send(&c, "", "GET", "admin/vote-flags?status=pending&page=1", 403, "12").await;
+  let review = ReviewVoteFlagPayload::new(VoteFlagStatus::Confirmed, true);
+  send(&c, review, "PUT", &format!("admin/vote-flags/{}", Ulid::new()), 403, "13").await;
+}
+
+#[tokio::test]
+#[serial]
+async fn concurrent_votes() {
+  let mut _child_guard = cargo_shuttle_run().await;
+  let c = Client::builder().cookie_store(true).build().unwrap();
+  send(&c, CreateUserPayload::default(), "POST", "users", 200, "00").await;
+  send(&c, CreateUserPayload::bob(), "POST", "users", 200, "01").await;
+  send(&c, CredentialsPayload::default(), "POST", "users/login", 200, "02").await;
+  let id = send_get::<Ulid>(&c, CreateItemPayload::default(), "POST", "items", 200, "03").await;
+  let bob = Client::builder().cookie_store(true).build().unwrap();
+  send(&bob, CredentialsPayload::bob(), "POST", "users/login", 200, "04").await;
+
+  // a burst of clicks: each upvote undoes the last, so an odd number leaves one upvote
+  let votes: Vec<_> = (0..9)
+    .map(|_| {
+      let (bob, payload) = (bob.clone(), VotePayload::new(&id, VoteState::Upvote));
+      tokio::spawn(async move { send(&bob, payload, "POST", "items/vote", 200, "10").await })
+    })
+    .collect();
+  for vote in votes {
+    vote.await.unwrap();
+  }
+  let (points, karma) = get_points_karma(&c, &id).await;
+  assert_eq!((points, karma), (2, 2));
 }
 
 #[tokio::test]
@@ -77,6 +1035,46 @@ async fn item_crud() {
   // post duplicate item for alice as alice with invalid payload: 422
   send(&c, CreateItemPayload::default(), "POST", "items", 200, "14").await;
   // todo(testing, banned) banned user post item: 401
+  // items may have both a url and text, but not neither
+  let both = ItemContent::new(Some(Url::default()), Some("some text to go along".into()));
+  let both = CreateItemPayload::new("an item with both", None, both, ItemCategory::Other).unwrap();
+  let both_id = send_get::<Ulid>(&c, both, "POST", "items", 200, "14a").await;
+  let item =
+    send_get::<GetItemResponse>(&c, "", "GET", &format!("items/{both_id}?page=1"), 200, "14b")
+      .await
+      .item;
+  assert!(item.url.is_some());
+  assert_eq!(item.text.unwrap().0, "some text to go along");
+  let mut neither = serde_json::to_value(CreateItemPayload::default()).unwrap();
+  neither["content"] = serde_json::json!({ "url": null, "text": null });
+  send(&c, neither, "POST", "items", 422, "14c").await;
+  // the item type is inferred from the title, case-insensitively
+  let (url, question) =
+    (ItemContent::default(), ItemContent::text("what do you all think?".into()));
+  let show =
+    CreateItemPayload::new("show zkhn: my project", None, url.clone(), ItemCategory::Other);
+  let show_id = send_get::<Ulid>(&c, show.unwrap(), "POST", "items", 200, "15").await;
+  let item =
+    send_get::<GetItemResponse>(&c, "", "GET", &format!("items/{show_id}?page=1"), 200, "15a")
+      .await
+      .item;
+  assert_eq!(item.item_type, ItemType::Show);
+  // a type given must match the title's prefix: 400
+  let ask = CreateItemPayload::new(
+    "Ask ZKHN: a question",
+    Some(ItemType::News),
+    question,
+    ItemCategory::Other,
+  );
+  send(&c, ask.unwrap(), "POST", "items", 400, "15b").await;
+  // ask items may not have a url: 400
+  let ask = CreateItemPayload::new("Ask ZKHN: a question", None, url.clone(), ItemCategory::Other);
+  send(&c, ask.unwrap(), "POST", "items", 400, "15c").await;
+  // the type is inferred from the title as stored, after tags and whitespace are stripped
+  let ask = CreateItemPayload::new("<b>Ask ZKHN:</b> why?", None, url.clone(), ItemCategory::Other);
+  send(&c, ask.unwrap(), "POST", "items", 400, "15d").await;
+  let ask = CreateItemPayload::new("   Ask   ZKHN:  why?", None, url, ItemCategory::Other);
+  send(&c, ask.unwrap(), "POST", "items", 400, "15e").await;
 
   // get item with fake id: 404
   let fake_id = Ulid::new();
@@ -107,8 +1105,12 @@ async fn item_crud() {
   let nonevote = VotePayload::new(&id, VoteState::None);
   vote(&c, &upvote, &id, _points, _karma, 1, "32").await;
   vote(&c, &upvote, &id, _points, _karma, 0, "33").await;
-  vote(&c, &downvote, &id, _points, _karma, -1, "34a").await;
-  vote(&c, &downvote, &id, _points, _karma, 0, "34b").await;
+  // alice lacks the karma to downvote
+  send(&c, downvote, "POST", "items/vote", 403, "34a").await;
+  let auth =
+    send_get::<AuthenticateUserResponse>(&c, "", "GET", "users/authenticate", 200, "34b").await;
+  assert!(auth.privileges.contains(&Privilege::SubmitLinks));
+  assert!(!auth.privileges.contains(&Privilege::Downvote));
   vote(&c, &upvote, &id, _points, _karma, 1, "34c").await;
   vote(&c, &nonevote, &id, _points, _karma, 0, "34d").await;
 
@@ -126,32 +1128,54 @@ async fn item_crud() {
   send(&c, CredentialsPayload::default(), "POST", "users/login", 200, "38li").await;
 
   // edit item
-  let edit =
-    EditItemPayload::new(&id, "new title", "new text", ItemCategory::Paper, ItemType::News);
+  let edit = EditItemPayload::new(&id, Some("new"), None, None);
   send(&c, edit, "PUT", "items/edit-item", 422, "39").await;
-  let edit = EditItemPayload::new(
-    &id,
-    "new title",
-    "new text text text",
-    ItemCategory::Paper,
-    ItemType::News,
-  );
-  send(&c, edit, "PUT", "items/edit-item", 200, "39a").await;
+  send(&c, EditItemPayload::new(&id, None, None, None), "PUT", "items/edit-item", 400, "39a").await;
+  // the default item is a url item, which may have text too
+  let edit = EditItemPayload::new(&id, None, Some("new text text text"), None);
+  send(&c, edit, "PUT", "items/edit-item", 200, "39b").await;
+  let edit = EditItemPayload::new(&id, Some("  Show ZKHN:   <b>new</b> title "), None, None);
+  send(&c, edit, "PUT", "items/edit-item", 200, "39c").await;
+  let edit = EditItemPayload::new(&id, None, None, Some(ItemCategory::Paper));
+  send(&c, edit, "PUT", "items/edit-item", 200, "39d").await;
+  // the default item is a url item, so may not become an ask item
+  let edit = EditItemPayload::new(&id, Some("Ask ZKHN: new title"), None, None);
+  send(&c, edit, "PUT", "items/edit-item", 400, "39e").await;
   let item =
     send_get::<GetItemResponse>(&c, "", "GET", &format!("items/{id}?page=1"), 200, "40").await.item;
-  assert_eq!(item.title, "new title".into());
+  assert_eq!(item.title, "Show ZKHN: new title".into());
+  assert!(item.url.is_some());
   assert_eq!(item.text.unwrap(), "new text text text".into());
   assert_eq!(item.item_category, ItemCategory::Paper);
-  assert_eq!(item.item_type, ItemType::News);
-  let edit = EditItemPayload::new(
-    &bob_item_id,
-    "new title",
-    "new text text text",
-    ItemCategory::Paper,
-    ItemType::News,
-  );
+  assert_eq!(item.item_type, ItemType::Show);
+  let edit = EditItemPayload::new(&bob_item_id, Some("new title"), None, None);
   send(&c, edit.clone(), "PUT", "items/edit-item", 403, "41").await;
 
+  // the edit is recorded, along with the original
+  let revisions =
+    send_get::<RevisionsResponse>(&c, "", "GET", &format!("items/{id}/revisions"), 200, "41a")
+      .await
+      .revisions;
+  assert_eq!(revisions.iter().map(|r| r.revision).collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+  assert_eq!(revisions[2].title.as_deref(), Some("Show ZKHN: new title"));
+  let diff = send_get::<RevisionDiffResponse>(
+    &c,
+    "",
+    "GET",
+    &format!("items/{id}/revisions/diff"),
+    200,
+    "41b",
+  )
+  .await
+  .diff;
+  assert!(diff.contains("+category: paper"));
+  send(&c, "", "GET", &format!("items/{id}/revisions/diff?from=2&to=1"), 400, "41c").await;
+  send(&c, "", "GET", &format!("items/{bob_item_id}/revisions"), 403, "41d").await;
+  let path = format!("items/{id}/revisions/diff?to={}", i32::MIN);
+  send(&c, "", "GET", &path, 400, "41e").await;
+  send(&c, "", "GET", &format!("items/{id}/revisions/diff?from=0&to=1"), 400, "41f").await;
+  send(&c, "", "GET", &format!("items/{id}/revisions/diff?to=1"), 400, "41g").await;
+
   // create a few more items
   let items = send_get::<GetItemsPageResponse>(
     &c,
@@ -177,8 +1201,23 @@ async fn item_crud() {
 
   // delete
   send(&c, "", "DELETE", &format!("items/delete-item/{id}"), 200, "100").await;
-  send(&c, "", "DELETE", &format!("items/delete-item/{id}"), 404, "100a").await;
-  send(&c, "", "GET", &format!("items/{id}?page=1"), 404, "100b").await;
+  send(&c, "", "DELETE", &format!("items/delete-item/{id}"), 403, "100a").await;
+  // deleted items stay resolvable, but their content is hidden
+  let item = send_get::<GetItemResponse>(&c, "", "GET", &format!("items/{id}?page=1"), 200, "100b")
+    .await
+    .item;
+  assert_eq!(item.title, "[deleted]".into());
+  assert!(item.url.is_none() && item.text.is_none());
+  assert!(item.deleted_at.is_some());
+  // but are left out of listings
+  let path = "items/get-items-by-page/ranked?page=1";
+  let page = send_get::<GetItemsPageResponse>(&c, "", "GET", path, 200, "100f").await;
+  assert!(page.items.iter().all(|ranked| ranked.item.id != id));
+  assert_eq!(page.count, items.count - 1);
+  send(&c, VotePayload::new(&id, VoteState::Upvote), "POST", "items/vote", 404, "100c").await;
+  // only moderators may restore it
+  send(&c, "", "POST", &format!("admin/items/{id}/restore"), 403, "100d").await;
+  send(&c, "", "GET", "admin/items/deleted?page=1", 403, "100e").await;
 }
 
 async fn favorite(
@@ -214,6 +1253,11 @@ async fn vote(
   assert_eq!(karma, _karma + inc);
 }
 
+async fn get_karma(c: &Client, username: &str) -> i32 {
+  let path = format!("users/{username}");
+  send_get::<GetUserResponse>(c, "", "GET", &path, 200, "get_karma").await.karma
+}
+
 async fn get_points_karma(c: &Client, id: &Ulid) -> (i32, i32) {
   let points =
     send_get::<GetItemResponse>(c, "", "GET", &format!("items/{id}?page=1"), 200, "get_points")
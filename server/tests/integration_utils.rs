@@ -1,5 +1,11 @@
-use std::{process, process::Command, time};
+use std::{
+  process,
+  process::Command,
+  sync::{Arc, Mutex},
+  time,
+};
 
+use axum::{http::HeaderMap, routing, Router};
 use reqwest::{Client, RequestBuilder, Response};
 use serde::de::DeserializeOwned;
 
@@ -20,6 +26,7 @@ pub async fn send(
     "PUT_EMPTY" => client.put(format!("{}/{}", WEBSERVER_URL, path)).send_empty().await,
     "GET" => client.get(format!("{}/{}", WEBSERVER_URL, path)).send_empty().await,
     "DELETE" => client.delete(format!("{}/{}", WEBSERVER_URL, path)).send_empty().await,
+    "DELETE_JSON" => client.delete(format!("{}/{}", WEBSERVER_URL, path)).send_json(payload).await,
     _ => panic!("Invalid method"),
   };
   assert_eq!(res.status(), status, "Test {} failed", tag);
@@ -48,7 +55,32 @@ pub async fn cargo_shuttle_run() -> ChildGuard {
     .arg("run")
     .spawn()
     .expect("Failed to start example binary");
+  await_server_ready(child).await
+}
+
+/// Run the shuttle server with the secrets in `Secrets.dev.toml`, overridden by `extra`. If
+/// `keep_db`, keep the database of the previous run, so that a test may restart the server with
+/// different secrets.
+pub async fn cargo_shuttle_run_with_secrets(extra: &[(&str, &str)], keep_db: bool) -> ChildGuard {
+  server_cleanup();
+  if !keep_db {
+    rm_docker_claude();
+  }
+  let mut secrets = std::fs::read_to_string("Secrets.dev.toml").expect("failed to read secrets");
+  for (key, value) in extra {
+    secrets.push_str(&format!("{key}=\"{value}\"\n"));
+  }
+  let path = std::env::temp_dir().join("Secrets.test.toml");
+  std::fs::write(&path, secrets).expect("failed to write secrets");
+  let child = process::Command::new("cargo")
+    .args(["shuttle", "run", "--secrets"])
+    .arg(&path)
+    .spawn()
+    .expect("Failed to start example binary");
+  await_server_ready(child).await
+}
 
+async fn await_server_ready(child: process::Child) -> ChildGuard {
   let start_time = time::Instant::now();
   let mut is_server_ready = false;
 
@@ -68,6 +100,50 @@ pub async fn cargo_shuttle_run() -> ChildGuard {
   ChildGuard { child }
 }
 
+/// A webhook request captured by `webhook_receiver`: its headers and raw body.
+pub type ReceivedWebhooks = Arc<Mutex<Vec<(HeaderMap, String)>>>;
+
+/// Serve a local webhook receiver at `http://localhost:{port}/`, recording each request.
+pub async fn webhook_receiver(port: u16) -> ReceivedWebhooks {
+  let received = ReceivedWebhooks::default();
+  let recorder = received.clone();
+  let app = Router::new().route(
+    "/",
+    routing::post(move |headers: HeaderMap, body: String| async move {
+      recorder.lock().unwrap().push((headers, body));
+    }),
+  );
+  let listener = tokio::net::TcpListener::bind(("127.0.0.1", port)).await.unwrap();
+  tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+  received
+}
+
+/// Wait up to `timeout` for the receiver to have received `n` requests.
+pub async fn await_webhooks(received: &ReceivedWebhooks, n: usize, timeout: time::Duration) {
+  let start_time = time::Instant::now();
+  while received.lock().unwrap().len() < n {
+    assert!(start_time.elapsed() < timeout, "expected {n} webhook deliveries");
+    tokio::time::sleep(time::Duration::from_millis(200)).await;
+  }
+}
+
+/// Poll a background data export until it's ready, and return its response.
+pub async fn await_data_export(
+  client: &Client,
+  download_url: &str,
+  timeout: time::Duration,
+) -> Response {
+  let start_time = time::Instant::now();
+  loop {
+    let res = client.get(format!("{}{}", WEBSERVER_URL, download_url)).send_empty().await;
+    if res.status() != 202 {
+      return res;
+    }
+    assert!(start_time.elapsed() < timeout, "expected data export to be generated");
+    tokio::time::sleep(time::Duration::from_millis(200)).await;
+  }
+}
+
 trait ClientExt {
   async fn send_json(self, payload: impl serde::Serialize) -> Response;
   async fn send_empty(self) -> Response;
@@ -97,7 +173,8 @@ impl Drop for ChildGuard {
 
 /// remove any artifacts of previous tests
 fn server_cleanup() {
-  Command::new("pkill").arg("server").spawn().expect("Failed to kill server");
+  // wait, so that a server being restarted isn't mistaken for the new one being ready
+  Command::new("pkill").arg("server").status().expect("Failed to kill server");
   println!("Killed test server");
 }
 
@@ -113,14 +190,32 @@ fn _db_setup() {
   println!("test database setup");
 }
 
-/// Remove existing Docker container
-fn rm_docker_claude() {
+/// Run `sql` against the test server's database, bypassing the api, and return the output.
+pub fn psql(sql: &str) -> String {
+  let output = Command::new("docker")
+    .args(["exec", &postgres_container_id(), "psql", "-U", "postgres", "-tA", "-c", sql])
+    .output()
+    .expect("Failed to execute psql");
+  assert!(output.status.success(), "{sql} failed: {}", String::from_utf8_lossy(&output.stderr));
+  String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
+
+/// Make `username` a moderator. Moderators are appointed in the database; log in afterwards.
+pub fn make_moderator(username: &str) {
+  psql(&format!("UPDATE users SET is_moderator = true WHERE username = '{username}'"));
+}
+
+fn postgres_container_id() -> String {
   let output = Command::new("docker")
     .args(["ps", "--quiet", "--filter", "name=shuttle_tk-shuttle-zkhn-rust-api3_shared_postgres"])
     .output()
     .expect("Failed to execute docker ps command");
+  String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
 
-  let container_id = String::from_utf8_lossy(&output.stdout).trim().to_string();
+/// Remove existing Docker container
+fn rm_docker_claude() {
+  let container_id = postgres_container_id();
 
   if !container_id.is_empty() {
     let _ = Command::new("docker")